@@ -865,9 +865,20 @@ impl Program<NamedDeBruijn> {
         )
     }
 
-    /// Evaluate a Program as a specific PlutusVersion
+    /// Evaluate a Program as a specific PlutusVersion, with the built-in cost
+    /// model of that version.
     pub fn eval_version(self, initial_budget: ExBudget, version: &Language) -> EvalResult {
-        let mut machine = Machine::new(version.clone(), CostModel::default(), initial_budget, 200);
+        self.eval_version_with(initial_budget, version, CostModel::for_language(version))
+    }
+
+    /// Evaluate a Program as a specific PlutusVersion with the given cost model.
+    pub fn eval_version_with(
+        self,
+        initial_budget: ExBudget,
+        version: &Language,
+        cost_model: CostModel,
+    ) -> EvalResult {
+        let mut machine = Machine::new(version.clone(), cost_model, initial_budget, 200);
 
         let term = machine.run(self.term);
 
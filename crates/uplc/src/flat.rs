@@ -21,28 +21,15 @@ pub struct FlatBigInt(pub BigInt);
 
 impl Encode for FlatBigInt {
     fn encode(&self, e: &mut Encoder) -> Result<(), en::Error> {
-        let (sign, bytes) = self.0.to_bytes_be();
-        let sign_bit = if sign == num_bigint::Sign::Minus { 1u8 } else { 0u8 };
-        
-        e.bool(sign_bit != 0);
-        bytes.encode(e)?;  // Vec<u8> should already have Encode impl
-        
+        e.big_integer(self.0.clone());
+
         Ok(())
     }
 }
 
 impl Decode<'_> for FlatBigInt {
     fn decode(d: &mut Decoder) -> Result<Self, de::Error> {
-        let is_negative = d.bool()?;
-        let bytes = Vec::<u8>::decode(d)?;  // Vec<u8> should already have Decode impl
-        
-        let sign = if is_negative {
-            num_bigint::Sign::Minus
-        } else {
-            num_bigint::Sign::Plus
-        };
-        
-        Ok(FlatBigInt(BigInt::from_bytes_be(sign, &bytes)))
+        Ok(FlatBigInt(d.big_integer()?))
     }
 }
 
@@ -1073,6 +1060,40 @@ mod tests {
         assert_eq!(actual_bytes, expected_bytes)
     }
 
+    #[test]
+    fn flat_encode_negative_integer() {
+        let program = Program::<Name> {
+            version: (1, 0, 0),
+            term: Term::Constant(Constant::Integer((-1000).into()).into()),
+        };
+
+        // -1000 zigzags to 1999, written as two 7-bit groups, low first.
+        let expected_bytes = vec![
+            0b00000001, 0b00000000, 0b00000000, 0b01001000, 0b00110011, 0b11000011, 0b11000001,
+        ];
+
+        let actual_bytes = program.to_flat().unwrap();
+
+        assert_eq!(actual_bytes, expected_bytes);
+        assert_eq!(Program::<Name>::unflat(&actual_bytes).unwrap(), program);
+    }
+
+    #[test]
+    fn flat_round_trip_big_integers() {
+        let big: num_bigint::BigInt = num_bigint::BigInt::from(2).pow(100) + 7;
+
+        for value in [big.clone(), -big, 0.into(), i64::MIN.into()] {
+            let program = Program::<Name> {
+                version: (1, 0, 0),
+                term: Term::Constant(Constant::Integer(value.clone()).into()),
+            };
+
+            let decoded = Program::<Name>::unflat(&program.to_flat().unwrap()).unwrap();
+
+            assert_eq!(decoded, program, "{}", value);
+        }
+    }
+
     #[test]
     fn flat_encode_list_list_integer() {
         let program = Program::<Name> {
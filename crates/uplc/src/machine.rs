@@ -20,6 +20,7 @@ use self::{
     value::{Env, Value},
 };
 
+#[derive(Clone)]
 pub(crate) enum MachineState {
     Return(Context, Value),
     Compute(Context, Env, Term<NamedDeBruijn>),
@@ -18,7 +18,7 @@ macro_rules! hashmap {
 }
 
 /// Can be negative
-#[derive(Debug, Clone, PartialEq, Eq, Copy, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, serde::Serialize, serde::Deserialize)]
 pub struct ExBudget {
     pub mem: i64,
     pub cpu: i64,
@@ -85,6 +85,15 @@ impl CostModel {
             builtin_costs: BuiltinCosts::v3(),
         }
     }
+
+    /// The built-in cost model of `language`.
+    pub fn for_language(language: &Language) -> Self {
+        match language {
+            Language::PlutusV1 => Self::v1(),
+            Language::PlutusV2 => Self::v2(),
+            Language::PlutusV3 => Self::v3(),
+        }
+    }
 }
 
 /// There's no entry for Error since we'll be exiting anyway; also, what would
@@ -1,31 +1,139 @@
 use super::{
-    Context, Machine, MachineState, 
+    Context, Machine, MachineState, Trace,
     value::Value,
     discharge::value_as_term,
     Error,
 };
 use crate::ast::{NamedDeBruijn, Term};
-use crate::machine::cost_model::{ExBudget, StepKind};
-use serde::Serialize;
-use std::rc::Rc;
+use crate::machine::cost_model::{CostModel, ExBudget, StepKind};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, rc::Rc};
 
-// In crates/uplc/src/machine/debug.rs
-#[derive(Clone, Debug, Serialize)]
+mod breakpoint;
+mod condition;
+mod context;
+mod provenance;
+mod scope;
+mod view;
+
+pub use breakpoint::{Breakpoint, BreakpointHit};
+pub use condition::{Condition, ConditionContext};
+use breakpoint::{saturating_builtin, BreakpointEntry};
+pub use provenance::{Items, Origin, Path, Segment};
+use provenance::Provenance;
+use scope::Scopes;
+pub use view::{render_value, FrameView, MachineView, StateView};
+
+/// How many steps apart checkpoints are taken by default.
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct StepSnapshot {
     pub step: usize,
     pub state_type: String,
     pub term: String,
     pub environment: Vec<String>,
     pub context_depth: usize,
+    pub continuation: Vec<ContinuationFrame>,
+    pub cpu: i64,
+    pub mem: i64,
+    /// What stepping from this state charges against the budget.
+    #[serde(default)]
+    pub cost: StepCost,
+
+    pub validator_phase: Option<ValidatorPhase>,  // Datum check, redeemer check, etc.
+    pub current_check: Option<String>,            // Human-readable current validation
+    /// The transaction, shared by every snapshot of the session. Traces
+    /// carry it once rather than per step.
+    #[serde(skip)]
+    pub script_context: Option<Rc<ScriptContextSnapshot>>,
+}
+
+/// The budget one step spends, and what it is spent on.
+///
+/// Debug sessions charge every step as it happens, so the sum of all step
+/// costs plus the start-up cost is exactly what the evaluation spent.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepCost {
     pub cpu: i64,
     pub mem: i64,
-    
-    pub validator_phase: ValidatorPhase,  // Datum check, redeemer check, etc.
-    pub current_check: Option<String>,     // Human-readable current validation
-    pub script_context: Option<ScriptContextSnapshot>,  // Tx inputs/outputs
+    /// `None` for steps that are free, such as returning a value to a frame.
+    pub charge: Option<Charge>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Charge {
+    /// The machine cost of computing a term of this kind.
+    Machine { step: String },
+    /// A saturated builtin call, costed from the sizes of its arguments.
+    Builtin {
+        function: String,
+        argument_sizes: Vec<i64>,
+    },
+}
+
+impl std::fmt::Display for Charge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Charge::Machine { step } => write!(f, "{} step", step),
+            Charge::Builtin {
+                function,
+                argument_sizes,
+            } => {
+                let sizes: Vec<String> = argument_sizes.iter().map(|s| s.to_string()).collect();
+                write!(f, "{} with argument sizes ({})", function, sizes.join(", "))
+            }
+        }
+    }
+}
+
+/// One pending frame of the continuation stack: what the machine will do
+/// with the value currently being computed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContinuationFrame {
+    /// The function is evaluated; apply it to the value being computed.
+    AwaitArg { function: String },
+    /// Compute the argument next, then apply the value being computed to it.
+    AwaitFunTerm { argument: String },
+    /// Apply the value being computed to this already evaluated argument.
+    AwaitFunValue { argument: String },
+    /// Force the value being computed.
+    Force,
+    /// Store the value being computed as the next field of a constr.
+    Constr {
+        tag: usize,
+        fields: Vec<String>,
+        pending: Vec<String>,
+    },
+    /// Select a branch using the value being computed.
+    Cases { branches: usize },
+}
+
+impl std::fmt::Display for ContinuationFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContinuationFrame::AwaitArg { function } => write!(f, "apply {function} to ·"),
+            ContinuationFrame::AwaitFunTerm { argument }
+            | ContinuationFrame::AwaitFunValue { argument } => write!(f, "apply · to {argument}"),
+            ContinuationFrame::Force => f.write_str("force ·"),
+            ContinuationFrame::Constr {
+                tag,
+                fields,
+                pending,
+            } => write!(
+                f,
+                "constr {tag} ({} of {} fields evaluated)",
+                fields.len(),
+                fields.len() + pending.len() + 1
+            ),
+            ContinuationFrame::Cases { branches } => write!(f, "case · of {branches} branches"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ValidatorPhase {
     DatumValidation,
     RedeemerValidation,
@@ -35,101 +143,587 @@ pub enum ValidatorPhase {
     Custom(String),
 }
 
-#[derive(Clone, Debug, Serialize)]
+/// The transaction a script is evaluated against, as its script context
+/// presents it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ScriptContextSnapshot {
+    pub transaction_id: String,
+    /// Why the script runs, e.g. `spend <tx id>#0` or `mint <policy id>`.
+    pub purpose: String,
     pub inputs: Vec<TxInputSummary>,
+    pub reference_inputs: Vec<TxInputSummary>,
     pub outputs: Vec<TxOutputSummary>,
+    pub fee: u64,
+    /// Required signers' key hashes, hex.
     pub signatories: Vec<String>,
-    pub time_range: Option<(i64, i64)>,
+    pub validity_range: ValidityRange,
     pub redeemer: String,
 }
 
+/// Validity interval in POSIX milliseconds; `None` is an open bound.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidityRange {
+    pub lower_bound: Option<u64>,
+    pub upper_bound: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TxInputSummary {
+    /// `<tx id>#<index>`
+    pub output_reference: String,
+    pub address: String,
+    pub value: ValueSummary,
+    pub datum: DatumSummary,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TxOutputSummary {
+    pub address: String,
+    pub value: ValueSummary,
+    pub datum: DatumSummary,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValueSummary {
+    pub lovelace: u64,
+    pub assets: Vec<AssetSummary>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetSummary {
+    pub policy_id: String,
+    /// Hex-encoded asset name.
+    pub asset_name: String,
+    pub quantity: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DatumSummary {
+    None,
+    Hash { hash: String },
+    Inline { data: String },
+}
+
+/// Where the session cursor currently is.
+#[derive(Clone)]
+enum Cursor {
+    Running(MachineState),
+    Failed(Error),
+}
+
+/// What the machine looked like on the step before the cursor.
+#[derive(Clone, Copy)]
+struct Observation {
+    traces: usize,
+    spent: ExBudget,
+}
+
+/// Everything needed to resume the machine from a given step.
+struct Checkpoint {
+    step: usize,
+    cursor: Cursor,
+    previous: Observation,
+    ex_budget: ExBudget,
+    unbudgeted_steps: [u32; 10],
+    spend_counter: Option<Vec<i64>>,
+    traces: usize,
+    hits: BTreeMap<usize, usize>,
+}
+
+/// A stepping debugger over a [`Machine`].
+///
+/// The session owns the machine and its current state and only advances when
+/// asked to. Nothing is rendered until [`DebugSession::snapshot`] is called, so
+/// long executions cost one machine state plus a checkpoint every
+/// `checkpoint_interval` steps. Going backwards restores the nearest earlier
+/// checkpoint and replays forward from there.
+pub struct DebugSession {
+    machine: Machine,
+    cursor: Cursor,
+    step: usize,
+    last_step: Option<usize>,
+    initial_budget: ExBudget,
+    previous: Observation,
+    checkpoints: Vec<Checkpoint>,
+    checkpoint_interval: usize,
+    breakpoints: Vec<BreakpointEntry>,
+    next_breakpoint_id: usize,
+    hits: BTreeMap<usize, usize>,
+    scopes: Scopes,
+    script_context: Option<Rc<ScriptContextSnapshot>>,
+    provenance: Option<Provenance>,
+}
+
+impl DebugSession {
+    pub fn new(machine: Machine, term: Term<NamedDeBruijn>) -> DebugSession {
+        DebugSession::with_checkpoint_interval(machine, term, DEFAULT_CHECKPOINT_INTERVAL)
+    }
+
+    pub fn with_checkpoint_interval(
+        mut machine: Machine,
+        term: Term<NamedDeBruijn>,
+        checkpoint_interval: usize,
+    ) -> DebugSession {
+        // Charge each step as it is taken so that budgets and step costs are
+        // exact at every step rather than batched.
+        machine.slippage = 1;
+
+        let initial_budget = machine.ex_budget;
+        let startup_budget = machine.costs.machine_costs.get(StepKind::StartUp);
+        let scopes = Scopes::new(&term);
+
+        let cursor = match machine.spend_budget(startup_budget) {
+            Ok(()) => Cursor::Running(MachineState::Compute(Context::NoFrame, Rc::new(vec![]), term)),
+            Err(e) => Cursor::Failed(e),
+        };
+
+        let previous = Observation {
+            traces: 0,
+            spent: initial_budget - machine.ex_budget,
+        };
+
+        let mut session = DebugSession {
+            machine,
+            cursor,
+            step: 0,
+            last_step: None,
+            initial_budget,
+            previous,
+            checkpoints: vec![],
+            checkpoint_interval: checkpoint_interval.max(1),
+            breakpoints: vec![],
+            next_breakpoint_id: 0,
+            hits: BTreeMap::new(),
+            scopes,
+            script_context: None,
+            provenance: None,
+        };
+
+        session.record_checkpoint();
+        session.check_finished();
+
+        session
+    }
+
+    /// Index of the step the cursor is on.
+    pub fn current_step(&self) -> usize {
+        self.step
+    }
+
+    /// Index of the final step, once the machine has reached it.
+    pub fn last_step(&self) -> Option<usize> {
+        self.last_step
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match &self.cursor {
+            Cursor::Running(MachineState::Done(_)) | Cursor::Failed(_) => true,
+            Cursor::Running(_) => false,
+        }
+    }
+
+    /// The result of the evaluation, once the cursor is on the final step.
+    pub fn outcome(&self) -> Option<Result<Term<NamedDeBruijn>, Error>> {
+        match &self.cursor {
+            Cursor::Running(MachineState::Done(term)) => Some(Ok(term.clone())),
+            Cursor::Failed(e) => Some(Err(e.clone())),
+            Cursor::Running(_) => None,
+        }
+    }
+
+    /// Remaining budget at the current step.
+    pub fn ex_budget(&self) -> ExBudget {
+        self.machine.ex_budget
+    }
+
+    /// Budget spent up to the current step, start-up cost included.
+    pub fn spent(&self) -> ExBudget {
+        self.initial_budget - self.machine.ex_budget
+    }
+
+    /// Traces emitted up to the current step.
+    pub fn traces(&self) -> &[Trace] {
+        &self.machine.traces
+    }
+
+    /// Number of pending continuation frames at the current step.
+    pub fn context_depth(&self) -> usize {
+        match &self.cursor {
+            Cursor::Running(MachineState::Compute(context, _, _))
+            | Cursor::Running(MachineState::Return(context, _)) => count_context_depth(context),
+            _ => 0,
+        }
+    }
+
+    /// The pending continuation frames at the current step, innermost first.
+    pub fn continuation(&self) -> Vec<ContinuationFrame> {
+        match &self.cursor {
+            Cursor::Running(MachineState::Compute(context, _, _))
+            | Cursor::Running(MachineState::Return(context, _)) => continuation_frames(context),
+            _ => vec![],
+        }
+    }
+
+    /// Attach the transaction the program is evaluated against to every
+    /// snapshot.
+    pub fn set_script_context(&mut self, script_context: ScriptContextSnapshot) {
+        self.script_context = Some(Rc::new(script_context));
+    }
+
+    pub fn script_context(&self) -> Option<&ScriptContextSnapshot> {
+        self.script_context.as_deref()
+    }
+
+    /// Render the current step.
+    pub fn snapshot(&self) -> StepSnapshot {
+        match &self.cursor {
+            Cursor::Running(state) => StepSnapshot {
+                script_context: self.script_context.clone(),
+                ..capture_snapshot(
+                    self.step,
+                    state,
+                    &self.machine.ex_budget,
+                    &self.machine.costs,
+                    &self.scopes,
+                )
+            },
+            Cursor::Failed(e) => StepSnapshot {
+                step: self.step,
+                state_type: "Error".to_string(),
+                term: format!("{}", e),
+                environment: vec![],
+                context_depth: 0,
+                continuation: vec![],
+                cpu: self.machine.ex_budget.cpu,
+                mem: self.machine.ex_budget.mem,
+                cost: StepCost::default(),
+                validator_phase: None,
+                current_check: None,
+                script_context: self.script_context.clone(),
+            },
+        }
+    }
+
+    /// Advance one step. Returns `false` when already on the final step.
+    pub fn step(&mut self) -> bool {
+        let Cursor::Running(state) = &self.cursor else {
+            return false;
+        };
+
+        let before = self.observe();
+        let derived = self.provenance.as_ref().and_then(|provenance| provenance.derive(state));
+
+        let next = match state.clone() {
+            MachineState::Compute(context, env, t) => self.machine.compute(context, env, t),
+            MachineState::Return(context, value) => self.machine.return_compute(context, value),
+            MachineState::Done(_) => return false,
+        };
+
+        self.cursor = match next {
+            Ok(state) => Cursor::Running(state),
+            Err(e) => Cursor::Failed(e),
+        };
+        self.step += 1;
+        self.previous = before;
+        self.count_hits();
+
+        if let (Some(provenance), Some(origin), Cursor::Running(MachineState::Return(_, value))) =
+            (&mut self.provenance, derived, &self.cursor)
+        {
+            provenance.record(value, origin, self.step);
+        }
+
+        if self.step % self.checkpoint_interval == 0 {
+            self.record_checkpoint();
+        }
+        self.check_finished();
+
+        true
+    }
+
+    /// Go back one step. Returns `false` when already on step 0.
+    pub fn step_back(&mut self) -> bool {
+        if self.step == 0 {
+            return false;
+        }
+
+        self.goto(self.step - 1);
+
+        true
+    }
+
+    /// Move the cursor to `target`, stopping early on the final step.
+    /// Returns the step actually reached.
+    pub fn goto(&mut self, target: usize) -> usize {
+        if target < self.step {
+            self.restore(target);
+        }
+
+        while self.step < target && self.step() {}
+
+        self.step
+    }
+
+    /// Step until the machine finishes and return the final step index.
+    pub fn run_to_end(&mut self) -> usize {
+        while self.step() {}
+
+        self.step
+    }
+
+    fn restore(&mut self, target: usize) {
+        let index = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.step <= target)
+            .saturating_sub(1);
+
+        let checkpoint = &self.checkpoints[index];
+
+        self.cursor = checkpoint.cursor.clone();
+        self.previous = checkpoint.previous;
+        self.step = checkpoint.step;
+        self.machine.ex_budget = checkpoint.ex_budget;
+        self.machine.unbudgeted_steps = checkpoint.unbudgeted_steps;
+        self.machine.traces.truncate(checkpoint.traces);
+        self.hits = checkpoint.hits.clone();
+
+        if let Some(provenance) = &mut self.provenance {
+            provenance.rewind(checkpoint.step);
+        }
+
+        if let (Some(counter), Some(saved)) =
+            (&mut self.machine.spend_counter, &checkpoint.spend_counter)
+        {
+            counter.copy_from_slice(saved);
+        }
+    }
+
+    fn record_checkpoint(&mut self) {
+        let index = match self
+            .checkpoints
+            .binary_search_by_key(&self.step, |checkpoint| checkpoint.step)
+        {
+            // Replaying over an existing checkpoint only refreshes hit counts.
+            Ok(index) => {
+                self.checkpoints[index].hits = self.hits.clone();
+                return;
+            }
+            Err(index) => index,
+        };
+
+        self.checkpoints.insert(index, Checkpoint {
+            step: self.step,
+            cursor: self.cursor.clone(),
+            previous: self.previous,
+            ex_budget: self.machine.ex_budget,
+            unbudgeted_steps: self.machine.unbudgeted_steps,
+            spend_counter: self.machine.spend_counter.map(|counter| counter.to_vec()),
+            traces: self.machine.traces.len(),
+            hits: self.hits.clone(),
+        });
+    }
+
+    fn observe(&self) -> Observation {
+        Observation {
+            traces: self.machine.traces.len(),
+            spent: self.spent(),
+        }
+    }
+
+    fn check_finished(&mut self) {
+        if self.is_finished() {
+            self.last_step = Some(self.step);
+        }
+    }
+}
+
 impl Machine {
-    /// Run the machine with step-by-step snapshots for debugging
+    /// Start a lazy debugging session over `term`.
+    pub fn debug_session(self, term: Term<NamedDeBruijn>) -> DebugSession {
+        DebugSession::new(self, term)
+    }
+
+    /// Run the machine with step-by-step snapshots for debugging.
+    ///
+    /// Every step is rendered eagerly; prefer [`Machine::debug_session`] for
+    /// anything but small programs.
     pub fn run_debug(&mut self, term: Term<NamedDeBruijn>) -> Result<(Term<NamedDeBruijn>, Vec<StepSnapshot>), Error> {
         use MachineState::*;
 
         let mut snapshots = Vec::new();
         let mut step_count = 0;
 
+        // See `DebugSession::with_checkpoint_interval`.
+        self.slippage = 1;
+
         let startup_budget = self.costs.machine_costs.get(StepKind::StartUp);
         self.spend_budget(startup_budget)?;
 
+        // Holding on to the program keeps the addresses `scopes` is keyed by alive.
+        let program = term.clone();
+        let scopes = Scopes::new(&program);
         let mut state = Compute(Context::NoFrame, Rc::new(vec![]), term);
 
         loop {
             // Capture snapshot BEFORE processing this state
-            snapshots.push(capture_snapshot(step_count, &state, &self.ex_budget));
+            snapshots.push(capture_snapshot(step_count, &state, &self.ex_budget, &self.costs, &scopes));
             step_count += 1;
 
             state = match state {
                 Compute(context, env, t) => self.compute(context, env, t)?,
                 Return(context, value) => self.return_compute(context, value)?,
-                Done(t) => {
-                    // Final snapshot
-                    snapshots.push(StepSnapshot {
-                        step: step_count,
-                        state_type: "Done".to_string(),
-                        term: format!("{}", t),
-                        environment: vec![],
-                        context_depth: 0,
-                        cpu: self.ex_budget.cpu,
-                        mem: self.ex_budget.mem,
-                    });
-                    return Ok((t, snapshots));
-                }
+                Done(t) => return Ok((t, snapshots)),
             };
         }
     }
 }
 
-fn capture_snapshot(step: usize, state: &MachineState, budget: &ExBudget) -> StepSnapshot {
-    match state {
-        MachineState::Compute(context, env, term) => {
-            StepSnapshot {
-                step,
-                state_type: "Compute".to_string(),
-                term: format!("{}", term),
-                environment: env.iter()
-                    .enumerate()
-                    .map(|(i, v)| format!("[{}] {}", i, pretty_value(v)))
-                    .collect(),
-                context_depth: count_context_depth(context),
-                cpu: budget.cpu,
-                mem: budget.mem,
-            }
-        }
-        MachineState::Return(context, value) => {
-            StepSnapshot {
-                step,
-                state_type: "Return".to_string(),
-                term: pretty_value(value),
-                environment: vec![],
-                context_depth: count_context_depth(context),
-                cpu: budget.cpu,
-                mem: budget.mem,
-            }
-        }
-        MachineState::Done(term) => {
-            StepSnapshot {
-                step,
-                state_type: "Done".to_string(),
-                term: format!("{}", term),
-                environment: vec![],
-                context_depth: 0,
-                cpu: budget.cpu,
-                mem: budget.mem,
-            }
-        }
+fn capture_snapshot(
+    step: usize,
+    state: &MachineState,
+    budget: &ExBudget,
+    costs: &CostModel,
+    scopes: &Scopes,
+) -> StepSnapshot {
+    let (state_type, term, environment, continuation) = match state {
+        MachineState::Compute(context, env, term) => (
+            "Compute",
+            format!("{}", term),
+            scopes
+                .names(term, env.len())
+                .into_iter()
+                .zip(env.iter().rev())
+                .map(|(name, v)| format!("{} = {}", name, pretty_value(v)))
+                .collect(),
+            continuation_frames(context),
+        ),
+        MachineState::Return(context, value) => (
+            "Return",
+            pretty_value(value),
+            vec![],
+            continuation_frames(context),
+        ),
+        MachineState::Done(term) => ("Done", format!("{}", term), vec![], vec![]),
+    };
+
+    StepSnapshot {
+        step,
+        state_type: state_type.to_string(),
+        term,
+        environment,
+        context_depth: continuation.len(),
+        continuation,
+        cpu: budget.cpu,
+        mem: budget.mem,
+        cost: step_cost(state, costs),
+        validator_phase: None,
+        current_check: None,
+        script_context: None,
     }
 }
 
+/// What the machine will charge when it steps from `state`.
+fn step_cost(state: &MachineState, costs: &CostModel) -> StepCost {
+    let charge = |budget: ExBudget, charge: Charge| StepCost {
+        cpu: budget.cpu,
+        mem: budget.mem,
+        charge: Some(charge),
+    };
+
+    if let Some((fun, args)) = saturating_builtin(state) {
+        let args: Vec<Value> = args.into_iter().cloned().collect();
+        // A builtin that cannot be costed fails the step without spending.
+        let budget = costs
+            .builtin_costs
+            .to_ex_budget(fun, &args)
+            .unwrap_or(ExBudget { cpu: 0, mem: 0 });
+
+        return charge(
+            budget,
+            Charge::Builtin {
+                function: fun.to_string(),
+                argument_sizes: args.iter().map(|arg| arg.to_ex_mem()).collect(),
+            },
+        );
+    }
+
+    let kind = match state {
+        MachineState::Compute(_, _, term) => match term {
+            Term::Var(_) => StepKind::Var,
+            Term::Delay(_) => StepKind::Delay,
+            Term::Lambda { .. } => StepKind::Lambda,
+            Term::Apply { .. } => StepKind::Apply,
+            Term::Constant(_) => StepKind::Constant,
+            Term::Force(_) => StepKind::Force,
+            Term::Builtin(_) => StepKind::Builtin,
+            Term::Constr { .. } => StepKind::Constr,
+            Term::Case { .. } => StepKind::Case,
+            Term::Error => return StepCost::default(),
+        },
+        MachineState::Return(_, _) | MachineState::Done(_) => return StepCost::default(),
+    };
+
+    charge(
+        costs.machine_costs.get(kind),
+        Charge::Machine {
+            step: kind.to_string(),
+        },
+    )
+}
+
 fn pretty_value(value: &Value) -> String {
     // Convert value back to term for display
     let term = value_as_term(value.clone());
     format!("{}", term)
 }
 
+fn continuation_frames(mut context: &Context) -> Vec<ContinuationFrame> {
+    let mut frames = vec![];
+
+    loop {
+        let (frame, rest) = match context {
+            Context::NoFrame => return frames,
+            Context::FrameAwaitArg(fun, rest) => (
+                ContinuationFrame::AwaitArg {
+                    function: pretty_value(fun),
+                },
+                rest,
+            ),
+            Context::FrameAwaitFunTerm(_, arg, rest) => (
+                ContinuationFrame::AwaitFunTerm {
+                    argument: format!("{}", arg),
+                },
+                rest,
+            ),
+            Context::FrameAwaitFunValue(arg, rest) => (
+                ContinuationFrame::AwaitFunValue {
+                    argument: pretty_value(arg),
+                },
+                rest,
+            ),
+            Context::FrameForce(rest) => (ContinuationFrame::Force, rest),
+            Context::FrameConstr(_, tag, pending, fields, rest) => (
+                ContinuationFrame::Constr {
+                    tag: *tag,
+                    fields: fields.iter().map(pretty_value).collect(),
+                    pending: pending.iter().rev().map(|term| format!("{}", term)).collect(),
+                },
+                rest,
+            ),
+            Context::FrameCases(_, branches, rest) => (
+                ContinuationFrame::Cases {
+                    branches: branches.len(),
+                },
+                rest,
+            ),
+        };
+
+        frames.push(frame);
+        context = rest;
+    }
+}
+
 fn count_context_depth(context: &Context) -> usize {
     match context {
         Context::NoFrame => 0,
@@ -140,4 +734,375 @@ fn count_context_depth(context: &Context) -> usize {
         Context::FrameConstr(_, _, _, _, rest) => 1 + count_context_depth(rest),
         Context::FrameCases(_, _, rest) => 1 + count_context_depth(rest),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        render_value, Breakpoint, Charge, Condition, ContinuationFrame, DebugSession, FrameView,
+        Segment, StateView,
+    };
+    use crate::{
+        ast::{Constant, DeBruijn, NamedDeBruijn, Program},
+        builtins::DefaultFunction,
+        machine::{
+            cost_model::{CostModel, ExBudget},
+            value::Value, Machine,
+        },
+        parser,
+    };
+    use pallas_primitives::conway::Language;
+
+    fn session(code: &str, checkpoint_interval: usize) -> DebugSession {
+        let program: Program<NamedDeBruijn> = parser::program(code).unwrap().try_into().unwrap();
+
+        let machine = Machine::new(
+            Language::PlutusV2,
+            CostModel::default(),
+            ExBudget::default(),
+            1,
+        );
+
+        DebugSession::with_checkpoint_interval(machine, program.term, checkpoint_interval)
+    }
+
+    const PROGRAM: &str =
+        "(program 1.0.0 [(lam x [(builtin addInteger) x (con integer 1)]) (con integer 41)])";
+
+    #[test]
+    fn session_matches_run_debug() {
+        let program: Program<NamedDeBruijn> =
+            parser::program(PROGRAM).unwrap().try_into().unwrap();
+
+        let mut machine = Machine::new(
+            Language::PlutusV2,
+            CostModel::default(),
+            ExBudget::default(),
+            1,
+        );
+
+        let (_, snapshots) = machine.run_debug(program.term).unwrap();
+
+        let mut session = session(PROGRAM, 3);
+        let mut lazy = vec![session.snapshot()];
+
+        while session.step() {
+            lazy.push(session.snapshot());
+        }
+
+        assert_eq!(session.last_step(), Some(snapshots.len() - 1));
+        assert_eq!(
+            serde_json::to_string(&lazy).unwrap(),
+            serde_json::to_string(&snapshots).unwrap()
+        );
+    }
+
+    #[test]
+    fn goto_backwards_replays_from_checkpoint() {
+        let mut session = session(PROGRAM, 4);
+
+        let last = session.run_to_end();
+        let end = serde_json::to_string(&session.snapshot()).unwrap();
+
+        session.goto(5);
+        let fifth = serde_json::to_string(&session.snapshot()).unwrap();
+
+        assert_eq!(session.goto(0), 0);
+        assert_eq!(session.goto(5), 5);
+        assert_eq!(serde_json::to_string(&session.snapshot()).unwrap(), fifth);
+
+        assert_eq!(session.goto(usize::MAX), last);
+        assert_eq!(serde_json::to_string(&session.snapshot()).unwrap(), end);
+
+        assert!(session.step_back());
+        assert_eq!(session.current_step(), last - 1);
+    }
+
+    #[test]
+    fn failures_become_a_final_error_step() {
+        let mut session = session("(program 1.0.0 [(lam x (error)) (con integer 1)])", 2);
+
+        let last = session.run_to_end();
+
+        assert!(session.is_finished());
+        assert!(session.outcome().unwrap().is_err());
+        assert_eq!(session.snapshot().state_type, "Error");
+        assert_eq!(session.snapshot().step, last);
+    }
+
+    #[test]
+    fn continuation_frames_describe_pending_work() {
+        let mut session = session(
+            "(program 1.1.0 (constr 2 (con integer 1) [(lam x x) (con integer 2)] (con integer 3)))",
+            5,
+        );
+
+        let constr = loop {
+            assert!(session.step());
+
+            if let Some(ContinuationFrame::Constr { tag, fields, pending }) = session
+                .continuation()
+                .into_iter()
+                .find(|frame| matches!(frame, ContinuationFrame::Constr { fields, .. } if fields.len() == 1))
+            {
+                break (tag, fields, pending);
+            }
+        };
+
+        assert_eq!(constr, (2, vec!["(con integer 1)".to_string()], vec!["(con integer 3)".to_string()]));
+
+        session.step();
+        let snapshot = session.snapshot();
+        assert_eq!(snapshot.context_depth, snapshot.continuation.len());
+        assert_eq!(
+            serde_json::to_value(&snapshot.continuation[0]).unwrap()["kind"],
+            "await_fun_term"
+        );
+        assert_eq!(snapshot.continuation[0].to_string(), "apply · to (con integer 2)");
+    }
+
+    #[test]
+    fn view_exposes_the_state_as_values() {
+        let mut session = session(TWICE, 5);
+        session.add_breakpoint(Breakpoint::Builtin(DefaultFunction::AddInteger));
+        session.continue_forward().unwrap();
+
+        let view = session.view();
+        let (fun, args) = view.saturating.as_ref().unwrap();
+        assert_eq!(*fun, DefaultFunction::AddInteger);
+        assert_eq!(render_value(args[0]), "(con integer 1)");
+        assert!(matches!(view.frames.first(), Some(FrameView::AwaitArg { function: Value::Builtin { .. } })));
+        assert!(matches!(view.state, StateView::Return { .. }));
+        assert!(view.traces.is_empty() && view.emitted().is_empty());
+        assert_eq!(view.initial(), ExBudget::default());
+
+        let mut failing = self::session("(program 1.0.0 [(lam x (error)) (con integer 1)])", 2);
+        failing.run_to_end();
+        assert!(matches!(failing.view().state, StateView::Failed { .. }));
+        assert!(failing.view().frames.is_empty());
+    }
+
+    #[test]
+    fn provenance_follows_data_through_builtins() {
+        // The first field of `ctx`, as an integer.
+        let mut session = session(
+            "(program 1.0.0 [(lam ctx [(builtin unIData) [(force (builtin headList)) [(force (force (builtin sndPair))) [(builtin unConstrData) ctx]]]]) (con data (Constr 0 [I 5, B #00]))])",
+            2,
+        );
+        session.track_provenance(vec!["ctx".to_string()]);
+
+        let mut origins = vec![];
+        let mut field = 0;
+        loop {
+            if let StateView::Return { value } = session.view().state {
+                if let Some(origin) = session.provenance(value) {
+                    origins.push(origin.to_string());
+                    field = session.current_step();
+                }
+            }
+            if !session.step() {
+                break;
+            }
+        }
+        origins.dedup();
+        assert_eq!(origins, ["ctx", "ctx.fields", "ctx.fields[0]"]);
+        assert!(matches!(session.provenance_root("ctx"), Some(Constant::Data(_))));
+
+        // Going back forgets values computed later, replaying finds them again.
+        session.goto(3);
+        session.goto(field);
+        let view = session.view();
+        let StateView::Return { value } = view.state else {
+            panic!("expected the field to be returned");
+        };
+        assert_eq!(view.origin(value).unwrap().path().segments, [Segment::Field(0)]);
+    }
+
+    #[test]
+    fn environment_is_named_after_binders() {
+        const NESTED: &str = "(program 1.0.0 [(lam x [(lam y [(builtin addInteger) x y]) (con integer 2)]) (con integer 1)])";
+
+        let innermost_environment = |mut session: DebugSession| loop {
+            let environment = session.snapshot().environment;
+
+            if environment.len() == 2 {
+                return environment;
+            }
+
+            assert!(session.step());
+        };
+
+        assert_eq!(
+            innermost_environment(session(NESTED, 5)),
+            vec!["y_1 = (con integer 2)", "x_2 = (con integer 1)"]
+        );
+
+        let program: Program<NamedDeBruijn> = parser::program(NESTED).unwrap().try_into().unwrap();
+        let program: Program<DeBruijn> = program.into();
+        let program: Program<NamedDeBruijn> = program.into();
+        let machine = Machine::new(
+            Language::PlutusV2,
+            CostModel::default(),
+            ExBudget::default(),
+            1,
+        );
+
+        assert_eq!(
+            innermost_environment(DebugSession::new(machine, program.term)),
+            vec!["i_1 = (con integer 2)", "i_2 = (con integer 1)"]
+        );
+    }
+
+    const TWICE: &str = "(program 1.0.0 [(lam f [f [f (con integer 1)]]) (lam x [(force (builtin trace)) (con string \"add\") [(builtin addInteger) x (con integer 1)]])])";
+
+    #[test]
+    fn continue_stops_on_each_builtin_call() {
+        let mut session = session(TWICE, 5);
+        let id = session.add_breakpoint(Breakpoint::Builtin(DefaultFunction::AddInteger));
+
+        let first = session.continue_forward().unwrap();
+        let (fun, args) = session.saturating_builtin().unwrap();
+        assert_eq!(first.id, id);
+        assert_eq!(fun, DefaultFunction::AddInteger);
+        assert_eq!(args.len(), 2);
+
+        let second = session.continue_forward().unwrap();
+        assert!(second.step > first.step);
+
+        assert_eq!(session.continue_forward(), None);
+        assert!(session.is_finished());
+
+        assert_eq!(session.continue_backward(), Some(second));
+        assert_eq!(session.continue_backward(), Some(first));
+        assert_eq!(session.continue_backward(), None);
+        assert_eq!(session.current_step(), 0);
+    }
+
+    #[test]
+    fn conditional_breakpoints_filter_on_arguments_and_hits() {
+        let mut by_argument = session(TWICE, 5);
+        by_argument.add_conditional_breakpoint(
+            Breakpoint::Builtin(DefaultFunction::AddInteger),
+            Some(Condition::parse("arg[0] == 2").unwrap()),
+        );
+
+        let mut by_hits = session(TWICE, 5);
+        let id = by_hits.add_conditional_breakpoint(
+            Breakpoint::Builtin(DefaultFunction::AddInteger),
+            Some(Condition::parse("hits == 2").unwrap()),
+        );
+
+        let hit = by_argument.continue_forward().unwrap();
+        assert_eq!(by_hits.continue_forward().unwrap().step, hit.step);
+        assert_eq!(by_hits.hit_count(id), 2);
+
+        assert!(by_hits.continue_forward().is_none());
+        assert_eq!(by_hits.continue_backward().unwrap().step, hit.step);
+        assert_eq!(by_hits.hit_count(id), 2);
+    }
+
+    #[test]
+    fn late_breakpoints_count_hits_from_where_they_are_set() {
+        let mut session = session(TWICE, 4);
+        let mut first = session.add_breakpoint(Breakpoint::Builtin(DefaultFunction::AddInteger));
+        let hit = session.continue_forward().unwrap();
+        session.remove_breakpoint(first);
+
+        // Set on a matching step, it counts that step but none before it.
+        first = session.add_breakpoint(Breakpoint::Builtin(DefaultFunction::AddInteger));
+        assert_eq!(session.hit_count(first), 1);
+        session.run_to_end();
+        assert_eq!(session.hit_count(first), 2);
+
+        let end = session.current_step();
+        let late = session.add_breakpoint(Breakpoint::Builtin(DefaultFunction::AddInteger));
+        assert_eq!(session.hit_count(late), 0);
+
+        // Counts are restored from checkpoints and rebuilt while replaying.
+        for (step, hits) in [(hit.step, 1), (hit.step - 1, 0), (0, 0), (end, 2)] {
+            session.goto(step);
+            assert_eq!(session.hit_count(first), hits, "step {}", step);
+            assert_eq!(session.hit_count(late), 0, "step {}", step);
+        }
+    }
+
+    #[test]
+    fn trace_breakpoints_follow_emitted_traces() {
+        let mut session = session(TWICE, 3);
+        session.add_breakpoint(Breakpoint::Trace);
+
+        let hit = session.continue_forward().unwrap();
+        assert_eq!(session.traces().len(), 1);
+        assert!(session.continue_forward().is_some());
+        assert_eq!(session.traces().len(), 2);
+        assert_eq!(session.continue_backward(), Some(hit));
+        assert_eq!(session.traces().len(), 1);
+    }
+
+    #[test]
+    fn budget_breakpoint_stops_where_threshold_is_crossed() {
+        let mut session = session(TWICE, 2);
+        session.run_to_end();
+        let total = session.spent();
+
+        session.goto(0);
+        session.add_breakpoint(Breakpoint::Cpu(total.cpu));
+
+        assert!(session.continue_forward().is_some());
+        assert_eq!(session.spent().cpu, total.cpu);
+    }
+
+    #[test]
+    fn error_breakpoint_stops_before_the_failure() {
+        let mut session = session("(program 1.0.0 [(lam x (error)) (con integer 1)])", 2);
+        session.add_breakpoint(Breakpoint::Error);
+
+        assert!(session.continue_forward().is_some());
+        assert!(!session.is_finished());
+        assert!(session.step());
+        assert!(session.outcome().unwrap().is_err());
+    }
+
+    #[test]
+    fn step_costs_add_up_to_the_spent_budget() {
+        let program: Program<NamedDeBruijn> =
+            parser::program(PROGRAM).unwrap().try_into().unwrap();
+
+        // Slippage is ignored by debug sessions, every step is charged at once.
+        let machine = Machine::new(
+            Language::PlutusV2,
+            CostModel::default(),
+            ExBudget::default(),
+            200,
+        );
+        let mut session = machine.debug_session(program.term);
+
+        let mut previous = session.spent();
+        let startup = previous;
+        let (mut cpu, mut mem) = (startup.cpu, startup.mem);
+
+        loop {
+            let cost = session.snapshot().cost;
+            cpu += cost.cpu;
+            mem += cost.mem;
+
+            if let Some(Charge::Builtin {
+                function,
+                argument_sizes,
+            }) = &cost.charge
+            {
+                assert_eq!(function, "addInteger");
+                assert_eq!(argument_sizes, &vec![1, 1]);
+            }
+
+            if !session.step() {
+                break;
+            }
+
+            assert_eq!(session.spent() - previous, ExBudget { cpu: cost.cpu, mem: cost.mem });
+            previous = session.spent();
+        }
+
+        assert_eq!(session.spent(), ExBudget { cpu, mem });
+    }
+}
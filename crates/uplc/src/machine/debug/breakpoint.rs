@@ -0,0 +1,280 @@
+use std::fmt::Display;
+
+use super::{Cursor, DebugSession, condition::{Condition, ConditionContext}};
+use crate::{
+    ast::Term,
+    builtins::DefaultFunction,
+    machine::{Context, MachineState, value::Value},
+};
+
+/// A condition on which [`DebugSession::continue_forward`] and
+/// [`DebugSession::continue_backward`] stop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// The builtin is about to receive its last argument and be evaluated.
+    Builtin(DefaultFunction),
+    /// `(error)` is about to be computed.
+    Error,
+    /// The previous step emitted a trace.
+    Trace,
+    /// Total CPU spent reached this amount on the previous step.
+    Cpu(i64),
+    /// Total memory spent reached this amount on the previous step.
+    Mem(i64),
+    Step(usize),
+}
+
+/// A registered breakpoint with its optional condition.
+#[derive(Clone, Debug)]
+pub(super) struct BreakpointEntry {
+    pub(super) id: usize,
+    pub(super) breakpoint: Breakpoint,
+    pub(super) condition: Option<Condition>,
+    /// The step it was set on; hits are counted from there.
+    pub(super) since: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BreakpointHit {
+    pub id: usize,
+    pub step: usize,
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Builtin(fun) => write!(f, "builtin {fun}"),
+            Breakpoint::Error => f.write_str("error"),
+            Breakpoint::Trace => f.write_str("trace"),
+            Breakpoint::Cpu(threshold) => write!(f, "cpu >= {threshold}"),
+            Breakpoint::Mem(threshold) => write!(f, "mem >= {threshold}"),
+            Breakpoint::Step(step) => write!(f, "step {step}"),
+        }
+    }
+}
+
+impl Breakpoint {
+    pub(super) fn is_hit(&self, session: &DebugSession) -> bool {
+        let previous = &session.previous;
+
+        match self {
+            Breakpoint::Builtin(expected) => session
+                .saturating_builtin()
+                .is_some_and(|(fun, _)| fun == *expected),
+            Breakpoint::Error => matches!(
+                session.cursor,
+                Cursor::Running(MachineState::Compute(_, _, Term::Error))
+            ),
+            Breakpoint::Trace => session.machine.traces.len() > previous.traces,
+            Breakpoint::Cpu(threshold) => {
+                previous.spent.cpu < *threshold && session.spent().cpu >= *threshold
+            }
+            Breakpoint::Mem(threshold) => {
+                previous.spent.mem < *threshold && session.spent().mem >= *threshold
+            }
+            Breakpoint::Step(step) => session.step == *step,
+        }
+    }
+}
+
+impl DebugSession {
+    /// Register a breakpoint and return its id.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.add_conditional_breakpoint(breakpoint, None)
+    }
+
+    /// Register a breakpoint that only stops when `condition` holds.
+    ///
+    /// Its hits are counted from the current step on, as steps are taken.
+    pub fn add_conditional_breakpoint(
+        &mut self,
+        breakpoint: Breakpoint,
+        condition: Option<Condition>,
+    ) -> usize {
+        let id = self.next_breakpoint_id;
+
+        self.next_breakpoint_id += 1;
+        self.breakpoints.push(BreakpointEntry {
+            id,
+            breakpoint,
+            condition,
+            since: self.step,
+        });
+
+        if self.breakpoints.last().is_some_and(|entry| entry.breakpoint.is_hit(self)) {
+            self.hits.insert(id, 1);
+        }
+        // A checkpoint on this step must restore the hit just counted.
+        if self.step % self.checkpoint_interval == 0 {
+            self.record_checkpoint();
+        }
+
+        id
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) -> bool {
+        let before = self.breakpoints.len();
+
+        self.breakpoints.retain(|entry| entry.id != id);
+        self.hits.remove(&id);
+
+        self.breakpoints.len() != before
+    }
+
+    /// Replace the condition of an existing breakpoint.
+    pub fn set_condition(&mut self, id: usize, condition: Option<Condition>) -> bool {
+        match self.breakpoints.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.condition = condition;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = (usize, &Breakpoint)> {
+        self.breakpoints
+            .iter()
+            .map(|entry| (entry.id, &entry.breakpoint))
+    }
+
+    pub fn condition(&self, id: usize) -> Option<&Condition> {
+        self.breakpoints
+            .iter()
+            .find(|entry| entry.id == id)
+            .and_then(|entry| entry.condition.as_ref())
+    }
+
+    /// How many times the breakpoint matched from the step it was set on up
+    /// to and including the current step, whether or not its condition held.
+    pub fn hit_count(&self, id: usize) -> usize {
+        self.hits.get(&id).copied().unwrap_or(0)
+    }
+
+    /// The first breakpoint hit on the current step, if any.
+    pub fn breakpoint_hit(&self) -> Option<BreakpointHit> {
+        self.breakpoints
+            .iter()
+            .find(|entry| {
+                entry.breakpoint.is_hit(self)
+                    && entry.condition.as_ref().map_or(true, |condition| {
+                        condition.eval(&self.condition_context(self.hit_count(entry.id)))
+                    })
+            })
+            .map(|entry| BreakpointHit {
+                id: entry.id,
+                step: self.step,
+            })
+    }
+
+    /// What conditions can observe on the current step.
+    pub fn condition_context(&self, hits: usize) -> ConditionContext<'_> {
+        let (env, value) = match &self.cursor {
+            Cursor::Running(MachineState::Compute(_, env, _)) => (env.iter().rev().collect(), None),
+            Cursor::Running(MachineState::Return(_, value)) => (vec![], Some(value)),
+            _ => (vec![], None),
+        };
+
+        ConditionContext {
+            args: self
+                .saturating_builtin()
+                .map(|(_, args)| args)
+                .unwrap_or_default(),
+            env,
+            value,
+            hits,
+            step: self.step,
+            spent: self.spent(),
+        }
+    }
+
+    /// Bump the counter of every breakpoint matching the current step, for
+    /// breakpoints set on or before it.
+    pub(super) fn count_hits(&mut self) {
+        let matched: Vec<usize> = self
+            .breakpoints
+            .iter()
+            .filter(|entry| entry.since <= self.step && entry.breakpoint.is_hit(self))
+            .map(|entry| entry.id)
+            .collect();
+
+        for id in matched {
+            *self.hits.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    /// Step forward until a breakpoint is hit or the machine finishes.
+    pub fn continue_forward(&mut self) -> Option<BreakpointHit> {
+        while self.step() {
+            if let Some(hit) = self.breakpoint_hit() {
+                return Some(hit);
+            }
+        }
+
+        None
+    }
+
+    /// Move back to the closest earlier step hitting a breakpoint, or to step 0.
+    pub fn continue_backward(&mut self) -> Option<BreakpointHit> {
+        let mut end = self.step;
+
+        while end > 0 {
+            self.restore(end - 1);
+
+            let start = self.step;
+            let mut found = None;
+
+            loop {
+                if let Some(hit) = self.breakpoint_hit() {
+                    found = Some(hit);
+                }
+
+                if self.step + 1 >= end || !self.step() {
+                    break;
+                }
+            }
+
+            if let Some(hit) = found {
+                self.goto(hit.step);
+
+                return Some(hit);
+            }
+
+            end = start;
+        }
+
+        self.goto(0);
+
+        None
+    }
+
+    /// The builtin the next step will evaluate, with all of its arguments.
+    pub fn saturating_builtin(&self) -> Option<(DefaultFunction, Vec<&Value>)> {
+        match &self.cursor {
+            Cursor::Running(state) => saturating_builtin(state),
+            Cursor::Failed(_) => None,
+        }
+    }
+}
+
+/// The builtin `state` is about to evaluate, with all of its arguments.
+pub(super) fn saturating_builtin(state: &MachineState) -> Option<(DefaultFunction, Vec<&Value>)> {
+    let MachineState::Return(context, value) = state else {
+        return None;
+    };
+
+    match (context, value) {
+        (Context::FrameAwaitArg(Value::Builtin { fun, runtime }, _), arg)
+        | (Context::FrameAwaitFunValue(arg, _), Value::Builtin { fun, runtime })
+            if !runtime.needs_force() && runtime.args.len() + 1 == fun.arity() =>
+        {
+            Some((*fun, runtime.args.iter().chain([arg]).collect()))
+        }
+        (Context::FrameForce(_), Value::Builtin { fun, runtime })
+            if runtime.forces + 1 == fun.force_count() && runtime.is_ready() =>
+        {
+            Some((*fun, runtime.args.iter().collect()))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,311 @@
+use std::{cmp::Ordering, fmt::Display, ops::Neg, str::FromStr};
+
+use num_bigint::BigInt;
+use pallas_primitives::conway::PlutusData;
+use peg::{error::ParseError, str::LineCol};
+
+use crate::{
+    ast::Constant,
+    machine::{
+        cost_model::ExBudget,
+        value::{Value, from_pallas_bigint},
+    },
+};
+
+/// A breakpoint condition such as `arg[0] == 42 && hits >= 3`.
+///
+/// Operands:
+/// * `arg[i]` — the i-th argument of the builtin about to be evaluated
+/// * `env[i]` — the i-th environment binding, innermost first
+/// * `value` — the value being returned
+/// * `hits`, `step`, `cpu`, `mem` — the breakpoint's hit count (this hit
+///   included), the step number and the total budget spent
+/// * integer, `#abcd` bytestring, `"text"` string, `True`/`False` and `()`
+///   literals
+///
+/// `Data` integers and bytestrings compare against integer and bytestring
+/// literals. Operands that do not exist at a step (e.g. `arg[2]` on a step
+/// that is not a builtin call) and comparisons between different kinds make
+/// the comparison false.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Comparison, Operand),
+    Truthy(Operand),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Eq,
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Arg(usize),
+    Env(usize),
+    Value,
+    Hits,
+    Step,
+    Cpu,
+    Mem,
+    Literal(Scalar),
+}
+
+/// The comparable shape of an operand.
+#[derive(Clone, Debug, PartialEq)]
+enum Scalar {
+    Integer(BigInt),
+    ByteString(Vec<u8>),
+    String(String),
+    Bool(bool),
+    Unit,
+    Data(PlutusData),
+}
+
+/// What a condition can observe on a step.
+pub struct ConditionContext<'a> {
+    pub args: Vec<&'a Value>,
+    pub env: Vec<&'a Value>,
+    pub value: Option<&'a Value>,
+    pub hits: usize,
+    pub step: usize,
+    pub spent: ExBudget,
+}
+
+impl Condition {
+    pub fn parse(src: &str) -> Result<Condition, ParseError<LineCol>> {
+        let expr = condition::condition(src)?;
+
+        Ok(Condition {
+            source: src.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn eval(&self, context: &ConditionContext) -> bool {
+        self.expr.eval(context)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseError<LineCol>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Condition::parse(s)
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Expr {
+    fn eval(&self, context: &ConditionContext) -> bool {
+        match self {
+            Expr::Or(left, right) => left.eval(context) || right.eval(context),
+            Expr::And(left, right) => left.eval(context) && right.eval(context),
+            Expr::Not(inner) => !inner.eval(context),
+            Expr::Truthy(operand) => matches!(operand.resolve(context), Some(Scalar::Bool(true))),
+            Expr::Compare(left, comparison, right) => {
+                let (Some(left), Some(right)) = (left.resolve(context), right.resolve(context))
+                else {
+                    return false;
+                };
+
+                match comparison {
+                    Comparison::Eq => left.loosely_equals(&right),
+                    Comparison::NotEq => !left.loosely_equals(&right),
+                    Comparison::Less => left.order(&right) == Some(Ordering::Less),
+                    Comparison::LessEq => {
+                        matches!(left.order(&right), Some(Ordering::Less | Ordering::Equal))
+                    }
+                    Comparison::Greater => left.order(&right) == Some(Ordering::Greater),
+                    Comparison::GreaterEq => {
+                        matches!(left.order(&right), Some(Ordering::Greater | Ordering::Equal))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, context: &ConditionContext) -> Option<Scalar> {
+        match self {
+            Operand::Arg(i) => context.args.get(*i).and_then(|v| Scalar::from_value(v)),
+            Operand::Env(i) => context.env.get(*i).and_then(|v| Scalar::from_value(v)),
+            Operand::Value => context.value.and_then(Scalar::from_value),
+            Operand::Hits => Some(Scalar::Integer(context.hits.into())),
+            Operand::Step => Some(Scalar::Integer(context.step.into())),
+            Operand::Cpu => Some(Scalar::Integer(context.spent.cpu.into())),
+            Operand::Mem => Some(Scalar::Integer(context.spent.mem.into())),
+            Operand::Literal(scalar) => Some(scalar.clone()),
+        }
+    }
+}
+
+impl Scalar {
+    fn from_value(value: &Value) -> Option<Scalar> {
+        let Value::Con(constant) = value else {
+            return None;
+        };
+
+        match constant.as_ref() {
+            Constant::Integer(i) => Some(Scalar::Integer(i.clone())),
+            Constant::ByteString(b) => Some(Scalar::ByteString(b.clone())),
+            Constant::String(s) => Some(Scalar::String(s.clone())),
+            Constant::Bool(b) => Some(Scalar::Bool(*b)),
+            Constant::Unit => Some(Scalar::Unit),
+            Constant::Data(d) => Some(Scalar::Data(d.clone())),
+            _ => None,
+        }
+    }
+
+    /// Unwrap `I` and `B` data so they compare against plain literals.
+    fn unwrap_data(&self) -> Scalar {
+        match self {
+            Scalar::Data(PlutusData::BigInt(i)) => Scalar::Integer(from_pallas_bigint(i)),
+            Scalar::Data(PlutusData::BoundedBytes(b)) => Scalar::ByteString(b.to_vec()),
+            other => other.clone(),
+        }
+    }
+
+    fn loosely_equals(&self, other: &Scalar) -> bool {
+        self == other || self.unwrap_data() == other.unwrap_data()
+    }
+
+    fn order(&self, other: &Scalar) -> Option<Ordering> {
+        match (self.unwrap_data(), other.unwrap_data()) {
+            (Scalar::Integer(a), Scalar::Integer(b)) => Some(a.cmp(&b)),
+            (Scalar::ByteString(a), Scalar::ByteString(b)) => Some(a.cmp(&b)),
+            (Scalar::String(a), Scalar::String(b)) => Some(a.cmp(&b)),
+            _ => None,
+        }
+    }
+}
+
+peg::parser! {
+    grammar condition() for str {
+        pub rule condition() -> Expr
+          = _ e:expr() _ { e }
+
+        rule expr() -> Expr = precedence! {
+            l:(@) _ "||" _ r:@ { Expr::Or(Box::new(l), Box::new(r)) }
+            --
+            l:(@) _ "&&" _ r:@ { Expr::And(Box::new(l), Box::new(r)) }
+            --
+            "!" _ e:@ { Expr::Not(Box::new(e)) }
+            --
+            l:operand() _ c:comparison() _ r:operand() { Expr::Compare(l, c, r) }
+            o:operand() { Expr::Truthy(o) }
+            "(" _ e:expr() _ ")" { e }
+        }
+
+        rule comparison() -> Comparison
+          = "==" { Comparison::Eq }
+          / "!=" { Comparison::NotEq }
+          / "<=" { Comparison::LessEq }
+          / ">=" { Comparison::GreaterEq }
+          / "<" { Comparison::Less }
+          / ">" { Comparison::Greater }
+
+        rule operand() -> Operand
+          = "arg" _ "[" _ i:index() _ "]" { Operand::Arg(i) }
+          / "env" _ "[" _ i:index() _ "]" { Operand::Env(i) }
+          / "value" { Operand::Value }
+          / "hits" { Operand::Hits }
+          / "step" { Operand::Step }
+          / "cpu" { Operand::Cpu }
+          / "mem" { Operand::Mem }
+          / s:scalar() { Operand::Literal(s) }
+          / expected!("an operand (arg[i], env[i], value, hits, step, cpu, mem or a literal)")
+
+        rule scalar() -> Scalar
+          = n:big_number() { Scalar::Integer(n) }
+          / "#" h:$(['0'..='9' | 'a'..='f' | 'A'..='F']*) {? hex::decode(h).map(Scalar::ByteString).map_err(|_| "Invalid bytestring") }
+          / "\"" s:$([^ '"']*) "\"" { Scalar::String(s.to_string()) }
+          / ("True" / "true") { Scalar::Bool(true) }
+          / ("False" / "false") { Scalar::Bool(false) }
+          / "()" { Scalar::Unit }
+
+        rule index() -> usize
+          = n:$(['0'..='9']+) {? n.parse().or(Err("usize")) }
+
+        rule big_number() -> BigInt
+          = n:$("-"? ['0'..='9']+) {? (if let Some(digits) = n.strip_prefix('-') { BigInt::parse_bytes(digits.as_bytes(), 10).map(|i| i.neg()) } else { BigInt::parse_bytes(n.as_bytes(), 10) }).ok_or("BigInt") }
+
+        rule _ = [' ' | '\t' | '\n' | '\r']*
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Condition, ConditionContext};
+    use crate::machine::{cost_model::ExBudget, value::Value};
+    use pallas_primitives::conway::PlutusData;
+
+    fn context<'a>(args: Vec<&'a Value>, hits: usize) -> ConditionContext<'a> {
+        ConditionContext {
+            args,
+            env: vec![],
+            value: None,
+            hits,
+            step: 10,
+            spent: ExBudget { mem: 100, cpu: 1000 },
+        }
+    }
+
+    #[test]
+    fn compares_builtin_arguments() {
+        let forty_two = Value::integer(42.into());
+        let seven = Value::integer(7.into());
+        let condition = Condition::parse("arg[0] == 42 && arg[1] < 10").unwrap();
+
+        assert!(condition.eval(&context(vec![&forty_two, &seven], 1)));
+        assert!(!condition.eval(&context(vec![&seven, &seven], 1)));
+        assert!(!condition.eval(&context(vec![&forty_two], 1)));
+    }
+
+    #[test]
+    fn unwraps_data_for_literal_comparisons() {
+        let int = Value::data(PlutusData::BigInt(pallas_primitives::conway::BigInt::Int(42.into())));
+        let bytes = Value::data(PlutusData::BoundedBytes(vec![0xab, 0xcd].into()));
+
+        assert!(Condition::parse("arg[0] == 42").unwrap().eval(&context(vec![&int], 1)));
+        assert!(Condition::parse("arg[0] >= 40").unwrap().eval(&context(vec![&int], 1)));
+        assert!(Condition::parse("arg[0] == #abcd").unwrap().eval(&context(vec![&bytes], 1)));
+        assert!(!Condition::parse("arg[0] == \"abcd\"").unwrap().eval(&context(vec![&bytes], 1)));
+    }
+
+    #[test]
+    fn hit_counts_and_precedence() {
+        let condition = Condition::parse("!(hits < 3) || step == 0").unwrap();
+
+        assert!(!condition.eval(&context(vec![], 2)));
+        assert!(condition.eval(&context(vec![], 3)));
+        assert!(Condition::parse("cpu > 999 && mem <= 100").unwrap().eval(&context(vec![], 1)));
+    }
+
+    #[test]
+    fn reports_error_position() {
+        let error = Condition::parse("arg[0] == ").unwrap_err();
+
+        assert_eq!(error.location.column, 11);
+        assert!(Condition::parse("arg[0] === 1").is_err());
+    }
+}
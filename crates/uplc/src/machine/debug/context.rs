@@ -0,0 +1,175 @@
+use std::fmt::{self, Display};
+
+use pallas_primitives::conway::{
+    DatumOption, PlutusData, StakeCredential, TransactionInput, TransactionOutput, Value, Voter,
+};
+
+use super::{
+    AssetSummary, DatumSummary, ScriptContextSnapshot, TxInputSummary, TxOutputSummary,
+    ValidityRange, ValueSummary,
+};
+use crate::{
+    pretty::data_to_pretty,
+    tx::script_context::{
+        output_address, output_datum, output_value, ScriptContext, ScriptInfo, ScriptPurpose,
+        TxInInfo, TxInfo,
+    },
+};
+
+impl From<&ScriptContext> for ScriptContextSnapshot {
+    fn from(script_context: &ScriptContext) -> Self {
+        let (tx_info, purpose, redeemer) = match script_context {
+            ScriptContext::V1V2 { tx_info, purpose } => {
+                let redeemer = redeemer_of(tx_info, purpose);
+                (tx_info, describe_purpose(purpose), redeemer)
+            }
+            ScriptContext::V3 {
+                tx_info,
+                redeemer,
+                purpose,
+            } => (tx_info, describe_purpose(purpose), Some(redeemer.clone())),
+        };
+
+        let range = tx_info.valid_range();
+
+        ScriptContextSnapshot {
+            transaction_id: tx_info.id().to_string(),
+            purpose,
+            inputs: tx_info.inputs().iter().map(summarize_input).collect(),
+            reference_inputs: tx_info.reference_inputs().iter().map(summarize_input).collect(),
+            outputs: tx_info.outputs().iter().map(summarize_output).collect(),
+            fee: tx_info.fee(),
+            signatories: tx_info.signatories().iter().map(|s| s.to_string()).collect(),
+            validity_range: ValidityRange {
+                lower_bound: range.lower_bound,
+                upper_bound: range.upper_bound,
+            },
+            redeemer: redeemer.as_ref().map(data_to_pretty).unwrap_or_default(),
+        }
+    }
+}
+
+fn output_reference(input: &TransactionInput) -> String {
+    format!("{}#{}", input.transaction_id, input.index)
+}
+
+fn describe_purpose<T>(purpose: &ScriptInfo<T>) -> String {
+    match purpose {
+        ScriptInfo::Minting(policy_id) => format!("mint {}", policy_id),
+        ScriptInfo::Spending(input, _) => format!("spend {}", output_reference(input)),
+        ScriptInfo::Rewarding(StakeCredential::ScriptHash(hash)) => {
+            format!("withdraw from script {}", hash)
+        }
+        ScriptInfo::Rewarding(StakeCredential::AddrKeyhash(hash)) => {
+            format!("withdraw from key {}", hash)
+        }
+        ScriptInfo::Certifying(index, _) => format!("publish certificate {}", index),
+        ScriptInfo::Voting(voter) => {
+            let (role, hash) = match voter {
+                Voter::ConstitutionalCommitteeScript(hash) => ("committee script", hash),
+                Voter::ConstitutionalCommitteeKey(hash) => ("committee key", hash),
+                Voter::DRepScript(hash) => ("DRep script", hash),
+                Voter::DRepKey(hash) => ("DRep key", hash),
+                Voter::StakePoolKey(hash) => ("stake pool", hash),
+            };
+            format!("vote as {} {}", role, hash)
+        }
+        ScriptInfo::Proposing(index, _) => format!("propose procedure {}", index),
+    }
+}
+
+fn summarize_input(input: &TxInInfo) -> TxInputSummary {
+    let output = summarize_output(&input.resolved);
+
+    TxInputSummary {
+        output_reference: output_reference(&input.out_ref),
+        address: output.address,
+        value: output.value,
+        datum: output.datum,
+    }
+}
+
+fn summarize_output(output: &TransactionOutput) -> TxOutputSummary {
+    let address = output_address(output);
+
+    TxOutputSummary {
+        address: address.to_bech32().unwrap_or_else(|_| address.to_hex()),
+        value: summarize_value(&output_value(output)),
+        datum: match output_datum(output) {
+            None => DatumSummary::None,
+            Some(DatumOption::Hash(hash)) => DatumSummary::Hash {
+                hash: hash.to_string(),
+            },
+            Some(DatumOption::Data(data)) => DatumSummary::Inline {
+                data: data_to_pretty(&data.0),
+            },
+        },
+    }
+}
+
+fn summarize_value(value: &Value) -> ValueSummary {
+    match value {
+        Value::Coin(lovelace) => ValueSummary {
+            lovelace: *lovelace,
+            assets: vec![],
+        },
+        Value::Multiasset(lovelace, assets) => ValueSummary {
+            lovelace: *lovelace,
+            assets: assets
+                .iter()
+                .flat_map(|(policy_id, tokens)| {
+                    tokens.iter().map(move |(asset_name, quantity)| AssetSummary {
+                        policy_id: policy_id.to_string(),
+                        asset_name: hex::encode(asset_name.as_slice()),
+                        quantity: u64::from(quantity),
+                    })
+                })
+                .collect(),
+        },
+    }
+}
+
+impl Display for ValueSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} lovelace", self.lovelace)?;
+
+        for asset in &self.assets {
+            write!(f, " + {} {}.{}", asset.quantity, asset.policy_id, asset.asset_name)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for DatumSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatumSummary::None => write!(f, "no datum"),
+            DatumSummary::Hash { hash } => write!(f, "datum hash {}", hash),
+            DatumSummary::Inline { data } => write!(f, "inline datum {}", data),
+        }
+    }
+}
+
+impl Display for ValidityRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |b: Option<u64>, open: &str| b.map_or(open.to_string(), |ms| ms.to_string());
+
+        write!(
+            f,
+            "[{}, {}] (POSIX ms)",
+            bound(self.lower_bound, "-∞"),
+            bound(self.upper_bound, "+∞")
+        )
+    }
+}
+
+/// V1 and V2 script contexts do not hold the redeemer, only the transaction's
+/// redeemers keyed by purpose.
+fn redeemer_of(tx_info: &TxInfo, purpose: &ScriptPurpose) -> Option<PlutusData> {
+    tx_info
+        .redeemers()
+        .iter()
+        .find(|(p, _)| p == purpose)
+        .map(|(_, redeemer)| redeemer.data.clone())
+}
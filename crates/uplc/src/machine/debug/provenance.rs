@@ -0,0 +1,270 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use super::{saturating_builtin, Cursor, DebugSession};
+use crate::{
+    ast::{Constant, NamedDeBruijn, Term},
+    builtins::DefaultFunction,
+    machine::{value::Value, MachineState},
+};
+
+/// Where in one of the program's arguments a piece of data lives, e.g. the
+/// third field of the first field of `ctx`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path {
+    pub root: String,
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /// A field of a constructor.
+    Field(usize),
+    /// The index of a constructor.
+    Constructor,
+    /// An element of a list, or an entry of a map.
+    Index(usize),
+    /// The key of a map entry.
+    Key,
+    /// The value of a map entry.
+    Value,
+}
+
+/// Where a value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The data at `path`, or what `unIData`, `unBData` and the like made of
+    /// it.
+    Data(Path),
+    /// What `unConstrData` made of the constructor at `path`.
+    Constr(Path),
+    /// The fields of the constructor at `path`, or the elements of the list
+    /// or map there, from the `from`th on.
+    List { path: Path, from: usize, items: Items },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Items {
+    Fields,
+    Elements,
+}
+
+impl Path {
+    pub fn new(root: impl Into<String>) -> Path {
+        Path {
+            root: root.into(),
+            segments: vec![],
+        }
+    }
+
+    fn join(&self, segment: Segment) -> Path {
+        let mut path = self.clone();
+        path.segments.push(segment);
+        path
+    }
+}
+
+impl Origin {
+    /// The data this value was taken from.
+    pub fn path(&self) -> &Path {
+        match self {
+            Origin::Data(path) | Origin::Constr(path) | Origin::List { path, .. } => path,
+        }
+    }
+
+    /// What follows the path: the part of the list a value is, if it is a
+    /// tail of the list or the fields at the path.
+    pub fn suffix(&self) -> String {
+        match self {
+            Origin::Data(_) | Origin::Constr(_) => String::new(),
+            Origin::List { from: 0, items: Items::Elements, .. } => String::new(),
+            Origin::List { from: 0, items: Items::Fields, .. } => ".fields".to_string(),
+            Origin::List { from, items: Items::Elements, .. } => format!("[{}..]", from),
+            Origin::List { from, items: Items::Fields, .. } => format!(".fields[{}..]", from),
+        }
+    }
+
+    /// The origin of what `function` returns when its arguments come from
+    /// `arguments`, for the builtins that take data apart.
+    fn derive(function: DefaultFunction, arguments: &[Option<&Origin>]) -> Option<Origin> {
+        use DefaultFunction::*;
+
+        let origin = match (function, arguments.first()?.as_ref()?) {
+            (UnConstrData, Origin::Data(path)) => Origin::Constr(path.clone()),
+            (UnListData | UnMapData, Origin::Data(path)) => Origin::List {
+                path: path.clone(),
+                from: 0,
+                items: Items::Elements,
+            },
+            (UnIData | UnBData, Origin::Data(path)) => Origin::Data(path.clone()),
+            (FstPair, Origin::Constr(path)) => Origin::Data(path.join(Segment::Constructor)),
+            (SndPair, Origin::Constr(path)) => Origin::List {
+                path: path.clone(),
+                from: 0,
+                items: Items::Fields,
+            },
+            // A pair that is not a constructor is a map entry.
+            (FstPair, Origin::Data(path)) => Origin::Data(path.join(Segment::Key)),
+            (SndPair, Origin::Data(path)) => Origin::Data(path.join(Segment::Value)),
+            (HeadList, Origin::List { path, from, items }) => Origin::Data(path.join(match items {
+                Items::Fields => Segment::Field(*from),
+                Items::Elements => Segment::Index(*from),
+            })),
+            (TailList, Origin::List { path, from, items }) => Origin::List {
+                path: path.clone(),
+                from: from + 1,
+                items: *items,
+            },
+            _ => return None,
+        };
+
+        Some(origin)
+    }
+}
+
+/// Provenance of the values derived from a program's arguments.
+///
+/// Builtins return fresh constants, so the address of a constant identifies
+/// the call that produced it, as long as the constant is kept alive. Each
+/// entry holds on to its constant for that reason.
+pub(super) struct Provenance {
+    roots: Vec<(String, Rc<Constant>)>,
+    origins: HashMap<usize, Tracked>,
+}
+
+struct Tracked {
+    _value: Rc<Constant>,
+    origin: Origin,
+    step: usize,
+}
+
+impl Provenance {
+    /// Name the last `names.len()` constant arguments `term` is applied to.
+    pub(super) fn new(term: &Term<NamedDeBruijn>, names: Vec<String>) -> Provenance {
+        let mut arguments = vec![];
+        let mut term = term;
+        while let Term::Apply { function, argument } = term {
+            arguments.push(argument.as_ref());
+            term = function;
+        }
+
+        let mut provenance = Provenance {
+            roots: vec![],
+            origins: HashMap::new(),
+        };
+
+        // `arguments` is outermost first, that is, last applied first.
+        for (name, argument) in names.into_iter().rev().zip(arguments) {
+            if let Term::Constant(constant) = argument {
+                provenance.insert(constant, Origin::Data(Path::new(name.clone())), 0);
+                provenance.roots.push((name, constant.clone()));
+            }
+        }
+        provenance.roots.reverse();
+
+        provenance
+    }
+
+    pub(super) fn get(&self, value: &Value) -> Option<&Origin> {
+        match value {
+            Value::Con(constant) => self
+                .origins
+                .get(&(Rc::as_ptr(constant) as usize))
+                .map(|tracked| &tracked.origin),
+            _ => None,
+        }
+    }
+
+    pub(super) fn root(&self, name: &str) -> Option<&Constant> {
+        self.roots
+            .iter()
+            .find(|(root, _)| root == name)
+            .map(|(_, constant)| constant.as_ref())
+    }
+
+    /// The origin of the result of the builtin `state` is about to evaluate.
+    pub(super) fn derive(&self, state: &MachineState) -> Option<Origin> {
+        let (function, arguments) = saturating_builtin(state)?;
+        let origins: Vec<_> = arguments.into_iter().map(|value| self.get(value)).collect();
+
+        Origin::derive(function, &origins)
+    }
+
+    /// Record that `value`, first seen on `step`, came from `origin`.
+    pub(super) fn record(&mut self, value: &Value, origin: Origin, step: usize) {
+        if let Value::Con(constant) = value {
+            self.insert(constant, origin, step);
+        }
+    }
+
+    /// Forget what was recorded after `step`, before replaying from there.
+    pub(super) fn rewind(&mut self, step: usize) {
+        self.origins.retain(|_, tracked| tracked.step <= step);
+    }
+
+    fn insert(&mut self, constant: &Rc<Constant>, origin: Origin, step: usize) {
+        // `fstPair` and `sndPair` hand out the constants inside the pair, so
+        // the same constant can be derived twice; the first time is kept.
+        self.origins
+            .entry(Rc::as_ptr(constant) as usize)
+            .or_insert_with(|| Tracked {
+                _value: constant.clone(),
+                origin,
+                step,
+            });
+    }
+}
+
+impl DebugSession {
+    /// Track where values derived from the program's arguments come from.
+    ///
+    /// `roots` name the last arguments the program is applied to, in order,
+    /// e.g. `["datum", "redeemer", "ctx"]`. Arguments that are not constants
+    /// are not tracked.
+    pub fn track_provenance(&mut self, roots: Vec<String>) {
+        let current = self.step;
+        self.restore(0);
+
+        self.provenance = match &self.checkpoints[0].cursor {
+            Cursor::Running(MachineState::Compute(_, _, term)) => Some(Provenance::new(term, roots)),
+            _ => None,
+        };
+
+        self.goto(current);
+    }
+
+    /// Where `value` comes from, when provenance is tracked and `value` was
+    /// derived from one of the program's arguments.
+    pub fn provenance(&self, value: &Value) -> Option<&Origin> {
+        self.provenance.as_ref()?.get(value)
+    }
+
+    /// The argument named `name` by [`DebugSession::track_provenance`].
+    pub fn provenance_root(&self, name: &str) -> Option<&Constant> {
+        self.provenance.as_ref()?.root(name)
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Field(index) => write!(f, ".fields[{}]", index),
+            Segment::Constructor => write!(f, ".constructor"),
+            Segment::Index(index) => write!(f, "[{}]", index),
+            Segment::Key => write!(f, ".key"),
+            Segment::Value => write!(f, ".value"),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root)?;
+        self.segments.iter().try_for_each(|segment| write!(f, "{}", segment))
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.path(), self.suffix())
+    }
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::ast::{NamedDeBruijn, Term};
+
+/// The fake binder name Plutus uses for programs without names, e.g. those
+/// decoded from flat through `FakeNamedDeBruijn`.
+const FAKE_NAME: &str = "i";
+
+/// Binder names in scope for every term of a program.
+///
+/// The machine clones terms as it evaluates them, but the `Rc` parts of a term
+/// are shared with the original program, so their addresses identify where in
+/// the program the term being computed comes from. The environment of a
+/// `Compute` state holds exactly one value per enclosing lambda, which lets us
+/// name each slot after its binder.
+#[derive(Default)]
+pub(super) struct Scopes {
+    scopes: HashMap<usize, Option<Rc<Scope>>>,
+}
+
+/// Enclosing binders, innermost first.
+struct Scope {
+    name: Rc<NamedDeBruijn>,
+    parent: Option<Rc<Scope>>,
+}
+
+impl Scopes {
+    pub(super) fn new(term: &Term<NamedDeBruijn>) -> Scopes {
+        let mut scopes = Scopes::default();
+        let mut pending: Vec<(&Term<NamedDeBruijn>, Option<Rc<Scope>>)> = vec![(term, None)];
+
+        while let Some((term, scope)) = pending.pop() {
+            if let Some(key) = identity(term) {
+                scopes.scopes.insert(key, scope.clone());
+            }
+
+            match term {
+                Term::Var(_) | Term::Constant(_) | Term::Error | Term::Builtin(_) => {}
+                Term::Delay(body) | Term::Force(body) => pending.push((body, scope)),
+                Term::Lambda {
+                    parameter_name,
+                    body,
+                } => {
+                    let inner = Rc::new(Scope {
+                        name: parameter_name.clone(),
+                        parent: scope,
+                    });
+
+                    pending.push((body, Some(inner)));
+                }
+                Term::Apply { function, argument } => {
+                    pending.push((function, scope.clone()));
+                    pending.push((argument, scope));
+                }
+                Term::Constr { fields, .. } => {
+                    pending.extend(fields.iter().map(|field| (field, scope.clone())));
+                }
+                Term::Case { constr, branches } => {
+                    pending.push((constr, scope.clone()));
+                    pending.extend(branches.iter().map(|branch| (branch, scope.clone())));
+                }
+            }
+        }
+
+        scopes
+    }
+
+    /// Name each of `slots` environment values, innermost first, as the
+    /// variables referring to them are printed in `term`: `x_1`, `y_2`, ...
+    ///
+    /// Slots whose binder cannot be found fall back to Plutus' fake name `i`.
+    pub(super) fn names(&self, term: &Term<NamedDeBruijn>, slots: usize) -> Vec<String> {
+        let mut scope = identity(term)
+            .and_then(|key| self.scopes.get(&key))
+            .and_then(|scope| scope.as_deref());
+
+        (1..=slots)
+            .map(|index| {
+                let text = scope.map_or(FAKE_NAME, |s| s.name.text.as_str());
+
+                scope = scope.and_then(|s| s.parent.as_deref());
+
+                format!("{}_{}", text, index)
+            })
+            .collect()
+    }
+}
+
+/// The address of the first shared part of a term, if it has one.
+///
+/// A constr's fields and a case's scrutinee live in the same scope as the
+/// constr or case itself, so they can stand in for it.
+fn identity(term: &Term<NamedDeBruijn>) -> Option<usize> {
+    match term {
+        Term::Var(name) => Some(Rc::as_ptr(name) as usize),
+        Term::Lambda { parameter_name, .. } => Some(Rc::as_ptr(parameter_name) as usize),
+        Term::Delay(body) | Term::Force(body) => Some(Rc::as_ptr(body) as usize),
+        Term::Apply { function, .. } => Some(Rc::as_ptr(function) as usize),
+        Term::Constant(constant) => Some(Rc::as_ptr(constant) as usize),
+        Term::Case { constr, .. } => Some(Rc::as_ptr(constr) as usize),
+        Term::Constr { fields, .. } => fields.first().and_then(identity),
+        Term::Error | Term::Builtin(_) => None,
+    }
+}
@@ -0,0 +1,148 @@
+use super::{pretty_value, provenance::Provenance, saturating_builtin, Cursor, DebugSession, Origin};
+use crate::{
+    ast::{Constant, NamedDeBruijn, Term},
+    builtins::DefaultFunction,
+    machine::{cost_model::ExBudget, value::Value, Context, Error, MachineState, Trace},
+};
+
+/// The machine on the current step of a [`DebugSession`], as terms and
+/// values rather than rendered text, for tools that reason about the state
+/// itself.
+pub struct MachineView<'a> {
+    pub step: usize,
+    pub state: StateView<'a>,
+    /// Pending continuation frames, innermost first.
+    pub frames: Vec<FrameView<'a>>,
+    /// The builtin the next step evaluates, with all of its arguments.
+    pub saturating: Option<(DefaultFunction, Vec<&'a Value>)>,
+    pub spent: ExBudget,
+    pub remaining: ExBudget,
+    /// Traces emitted up to the current step.
+    pub traces: &'a [Trace],
+    /// How many of the last `traces` the previous step emitted.
+    pub new_traces: usize,
+    provenance: Option<&'a Provenance>,
+}
+
+pub enum StateView<'a> {
+    /// `term` is about to be computed; `env` is innermost first.
+    Compute {
+        term: &'a Term<NamedDeBruijn>,
+        env: Vec<&'a Value>,
+    },
+    /// `value` is being returned to the innermost frame.
+    Return { value: &'a Value },
+    Done { term: &'a Term<NamedDeBruijn> },
+    Failed { error: &'a Error },
+}
+
+/// One pending continuation frame; see [`super::ContinuationFrame`].
+pub enum FrameView<'a> {
+    AwaitArg { function: &'a Value },
+    AwaitFunTerm { argument: &'a Term<NamedDeBruijn> },
+    AwaitFunValue { argument: &'a Value },
+    Force,
+    Constr {
+        tag: usize,
+        fields: &'a [Value],
+        pending: &'a [Term<NamedDeBruijn>],
+    },
+    Cases { branches: &'a [Term<NamedDeBruijn>] },
+}
+
+impl<'a> MachineView<'a> {
+    /// The traces the previous step emitted.
+    pub fn emitted(&self) -> &[Trace] {
+        &self.traces[self.traces.len() - self.new_traces..]
+    }
+
+    /// Budget the evaluation started with.
+    pub fn initial(&self) -> ExBudget {
+        ExBudget {
+            cpu: self.spent.cpu + self.remaining.cpu,
+            mem: self.spent.mem + self.remaining.mem,
+        }
+    }
+
+    /// Where `value` comes from; see [`DebugSession::track_provenance`].
+    pub fn origin(&self, value: &Value) -> Option<&'a Origin> {
+        self.provenance?.get(value)
+    }
+
+    /// The argument a provenance path starts from.
+    pub fn provenance_root(&self, name: &str) -> Option<&'a Constant> {
+        self.provenance?.root(name)
+    }
+}
+
+/// `value` the way terms are printed.
+pub fn render_value(value: &Value) -> String {
+    pretty_value(value)
+}
+
+impl DebugSession {
+    /// The machine on the current step.
+    pub fn view(&self) -> MachineView<'_> {
+        let (state, context) = match &self.cursor {
+            Cursor::Running(MachineState::Compute(context, env, term)) => (
+                StateView::Compute {
+                    term,
+                    env: env.iter().rev().collect(),
+                },
+                Some(context),
+            ),
+            Cursor::Running(MachineState::Return(context, value)) => {
+                (StateView::Return { value }, Some(context))
+            }
+            Cursor::Running(MachineState::Done(term)) => (StateView::Done { term }, None),
+            Cursor::Failed(error) => (StateView::Failed { error }, None),
+        };
+
+        let saturating = match &self.cursor {
+            Cursor::Running(state) => saturating_builtin(state),
+            Cursor::Failed(_) => None,
+        };
+
+        MachineView {
+            step: self.step,
+            state,
+            frames: context.map(frame_views).unwrap_or_default(),
+            saturating,
+            spent: self.spent(),
+            remaining: self.machine.ex_budget,
+            traces: &self.machine.traces,
+            new_traces: self.machine.traces.len().saturating_sub(self.previous.traces),
+            provenance: self.provenance.as_ref(),
+        }
+    }
+}
+
+fn frame_views(mut context: &Context) -> Vec<FrameView<'_>> {
+    let mut frames = vec![];
+
+    loop {
+        let (frame, rest) = match context {
+            Context::NoFrame => return frames,
+            Context::FrameAwaitArg(function, rest) => (FrameView::AwaitArg { function }, rest),
+            Context::FrameAwaitFunTerm(_, argument, rest) => {
+                (FrameView::AwaitFunTerm { argument }, rest)
+            }
+            Context::FrameAwaitFunValue(argument, rest) => {
+                (FrameView::AwaitFunValue { argument }, rest)
+            }
+            Context::FrameForce(rest) => (FrameView::Force, rest),
+            Context::FrameConstr(_, tag, pending, fields, rest) => (
+                FrameView::Constr {
+                    tag: *tag,
+                    fields,
+                    pending,
+                },
+                rest,
+            ),
+            Context::FrameCases(_, branches, rest) => (FrameView::Cases { branches }, rest),
+        };
+
+        frames.push(frame);
+        context = rest;
+    }
+}
@@ -1114,10 +1114,8 @@ impl Term<Name> {
             Term::Lambda {
                 parameter_name,
                 body,
-            } => {
-                if *parameter_name != original {
-                    Rc::make_mut(body).replace_identity_usage(original.clone());
-                }
+            } if *parameter_name != original => {
+                Rc::make_mut(body).replace_identity_usage(original.clone());
             }
             Term::Apply { function, argument } => {
                 let func = Rc::make_mut(function);
@@ -2053,16 +2051,16 @@ impl Term<Name> {
     ) -> bool {
         let mut changed = false;
         match self {
-            Term::Builtin(d @ DefaultFunction::SubtractInteger) => {
-                if arg_stack.len() == d.arity() {
-                    let Some(Args::Apply(apply_id, Term::Constant(_))) = arg_stack.last() else {
-                        return false;
-                    };
-                    changed = true;
-                    context.constants_to_flip.push(*apply_id);
+            Term::Builtin(d @ DefaultFunction::SubtractInteger)
+                if arg_stack.len() == d.arity() =>
+            {
+                let Some(Args::Apply(apply_id, Term::Constant(_))) = arg_stack.last() else {
+                    return false;
+                };
+                changed = true;
+                context.constants_to_flip.push(*apply_id);
 
-                    *self = Term::Builtin(DefaultFunction::AddInteger);
-                }
+                *self = Term::Builtin(DefaultFunction::AddInteger);
             }
             Term::Constr { .. } => todo!(),
             Term::Case { .. } => todo!(),
@@ -2471,89 +2469,89 @@ impl Program<Name> {
         let (step_a, _) = self.traverse_uplc_with(
             false,
             &mut |_id, term, arg_stack, scope, _context| match term {
-                Term::Builtin(func) => {
-                    if func.can_curry_builtin() && arg_stack.len() == func.arity() {
-                        let arg_stack = arg_stack
-                            .into_iter()
-                            .map(|item| {
-                                let Args::Apply(arg_id, arg) = item else {
-                                    unreachable!()
-                                };
-                                (arg_id, arg)
-                            })
-                            .collect_vec();
-                        // In the case of order agnostic builtins we want to sort the args by constant first
-                        // This gives us the opportunity to curry constants that often pop up in the code
+                Term::Builtin(func)
+                    if func.can_curry_builtin() && arg_stack.len() == func.arity() =>
+                {
+                    let arg_stack = arg_stack
+                        .into_iter()
+                        .map(|item| {
+                            let Args::Apply(arg_id, arg) = item else {
+                                unreachable!()
+                            };
+                            (arg_id, arg)
+                        })
+                        .collect_vec();
+                    // In the case of order agnostic builtins we want to sort the args by constant first
+                    // This gives us the opportunity to curry constants that often pop up in the code
 
-                        let builtin_args = BuiltinArgs::args_from_arg_stack(arg_stack, *func);
+                    let builtin_args = BuiltinArgs::args_from_arg_stack(arg_stack, *func);
 
-                        // First we see if we have already curried this builtin before
-                        let mut id_vec = if let Some((index, _)) =
-                            curried_terms.iter_mut().find_position(
-                                |curried_term: &&mut CurriedBuiltin| curried_term.func == *func,
-                            ) {
-                            // We found it the builtin was curried before
-                            // So now we merge the new args into the existing curried builtin
-                            let curried_builtin = curried_terms.swap_remove(index);
+                    // First we see if we have already curried this builtin before
+                    let mut id_vec = if let Some((index, _)) =
+                        curried_terms.iter_mut().find_position(
+                            |curried_term: &&mut CurriedBuiltin| curried_term.func == *func,
+                        ) {
+                        // We found it the builtin was curried before
+                        // So now we merge the new args into the existing curried builtin
+                        let curried_builtin = curried_terms.swap_remove(index);
 
-                            let curried_builtin =
-                                curried_builtin.merge_node_by_path(builtin_args.clone());
+                        let curried_builtin =
+                            curried_builtin.merge_node_by_path(builtin_args.clone());
 
-                            flipped_terms
-                                .insert(scope.clone(), curried_builtin.is_flipped(&builtin_args));
+                        flipped_terms
+                            .insert(scope.clone(), curried_builtin.is_flipped(&builtin_args));
 
-                            let Some(id_vec) = curried_builtin.get_id_args(builtin_args) else {
-                                unreachable!();
-                            };
+                        let Some(id_vec) = curried_builtin.get_id_args(builtin_args) else {
+                            unreachable!();
+                        };
 
-                            curried_terms.push(curried_builtin);
+                        curried_terms.push(curried_builtin);
 
-                            id_vec
-                        } else {
-                            // Brand new builtin so we add it to the list
-                            let curried_builtin = builtin_args.clone().args_to_curried_args(*func);
+                        id_vec
+                    } else {
+                        // Brand new builtin so we add it to the list
+                        let curried_builtin = builtin_args.clone().args_to_curried_args(*func);
 
-                            let Some(id_vec) = curried_builtin.get_id_args(builtin_args) else {
-                                unreachable!();
-                            };
+                        let Some(id_vec) = curried_builtin.get_id_args(builtin_args) else {
+                            unreachable!();
+                        };
 
-                            curried_terms.push(curried_builtin);
+                        curried_terms.push(curried_builtin);
 
-                            id_vec
-                        };
+                        id_vec
+                    };
 
-                        while let Some(node) = id_vec.pop() {
-                            let mut id_only_vec =
-                                id_vec.iter().map(|item| item.curried_id).collect_vec();
+                    while let Some(node) = id_vec.pop() {
+                        let mut id_only_vec =
+                            id_vec.iter().map(|item| item.curried_id).collect_vec();
 
-                            id_only_vec.push(node.curried_id);
+                        id_only_vec.push(node.curried_id);
 
-                            let curry_name = CurriedName {
-                                func_name: func.aiken_name(),
-                                id_vec: id_only_vec,
-                            };
+                        let curry_name = CurriedName {
+                            func_name: func.aiken_name(),
+                            id_vec: id_only_vec,
+                        };
 
-                            if let Some((map_scope, _, occurrences)) =
-                                id_mapped_curry_terms.get_mut(&curry_name)
-                            {
-                                *map_scope = map_scope.common_ancestor(scope);
-                                *occurrences += 1;
-                            } else if id_vec.is_empty() {
-                                id_mapped_curry_terms.insert(
-                                    curry_name,
-                                    (scope.clone(), Term::Builtin(*func).apply(node.term), 1),
-                                );
-                            } else {
-                                let var_name = id_vec_function_to_var(
-                                    &func.aiken_name(),
-                                    &id_vec.iter().map(|item| item.curried_id).collect_vec(),
-                                );
-
-                                id_mapped_curry_terms.insert(
-                                    curry_name,
-                                    (scope.clone(), Term::var(var_name).apply(node.term), 1),
-                                );
-                            }
+                        if let Some((map_scope, _, occurrences)) =
+                            id_mapped_curry_terms.get_mut(&curry_name)
+                        {
+                            *map_scope = map_scope.common_ancestor(scope);
+                            *occurrences += 1;
+                        } else if id_vec.is_empty() {
+                            id_mapped_curry_terms.insert(
+                                curry_name,
+                                (scope.clone(), Term::Builtin(*func).apply(node.term), 1),
+                            );
+                        } else {
+                            let var_name = id_vec_function_to_var(
+                                &func.aiken_name(),
+                                &id_vec.iter().map(|item| item.curried_id).collect_vec(),
+                            );
+
+                            id_mapped_curry_terms.insert(
+                                curry_name,
+                                (scope.clone(), Term::var(var_name).apply(node.term), 1),
+                            );
                         }
                     }
                 }
@@ -2997,6 +2995,38 @@ mod tests {
         });
     }
 
+    #[test]
+    fn identity_reduce_shadowed_usage() {
+        // The inner `identity` is a different function, so its call stays.
+        let program: Program<Name> = Program {
+            version: (1, 0, 0),
+            term: Term::var("identity")
+                .apply(Term::var("x"))
+                .lambda("identity")
+                .apply(Term::integer(2.into()).lambda("z"))
+                .lambda("x")
+                .apply(Term::integer(1.into()))
+                .lambda("identity")
+                .apply(Term::var("y").lambda("y")),
+        };
+
+        let expected = Program {
+            version: (1, 0, 0),
+            term: Term::var("identity")
+                .apply(Term::var("x"))
+                .lambda("identity")
+                .apply(Term::integer(2.into()).lambda("z"))
+                .lambda("x")
+                .apply(Term::integer(1.into())),
+        };
+
+        compare_optimization(expected, program, |p| {
+            p.run_one_opt(true, &mut |id, term, arg_stack, scope, context| {
+                term.identity_reducer(id, arg_stack, scope, context);
+            })
+        });
+    }
+
     #[test]
     fn identity_reduce_0_occurrence() {
         let program: Program<Name> = Program {
@@ -3818,6 +3848,50 @@ mod tests {
         compare_optimization(expected, program, |p| p.builtin_curry_reducer());
     }
 
+    #[test]
+    fn convert_arithmetic_ops_flips_constant_subtraction() {
+        let program: Program<Name> = Program {
+            version: (1, 0, 0),
+            term: Term::subtract_integer()
+                .apply(Term::var("x"))
+                .apply(Term::integer(5.into()))
+                .lambda("x"),
+        };
+
+        let expected = Program {
+            version: (1, 0, 0),
+            term: Term::add_integer()
+                .apply(Term::var("x"))
+                .apply(Term::integer((-5).into()))
+                .lambda("x"),
+        };
+
+        compare_optimization(expected, program, |p| {
+            p.run_one_opt(true, &mut |id, term, arg_stack, scope, context| {
+                term.convert_arithmetic_ops(id, arg_stack, scope, context);
+            })
+        });
+    }
+
+    #[test]
+    fn convert_arithmetic_ops_keeps_other_subtractions() {
+        // A variable operand, and a partial application, are left alone.
+        let program: Program<Name> = Program {
+            version: (1, 0, 0),
+            term: Term::subtract_integer()
+                .apply(Term::integer(5.into()))
+                .apply(Term::var("x"))
+                .lambda("x")
+                .apply(Term::subtract_integer().apply(Term::integer(1.into()))),
+        };
+
+        compare_optimization(program.clone(), program, |p| {
+            p.run_one_opt(true, &mut |id, term, arg_stack, scope, context| {
+                term.convert_arithmetic_ops(id, arg_stack, scope, context);
+            })
+        });
+    }
+
     #[test]
     fn case_constr_apply_test_1() {
         let program: Program<Name> = Program {
@@ -180,6 +180,17 @@ where
     }
 }
 
+/// Render Plutus data as it appears inside `(con data ...)`.
+pub(crate) fn data_to_pretty(data: &PlutusData) -> String {
+    let mut w = Vec::new();
+
+    Constant::to_doc_list_plutus_data(data)
+        .render(80, &mut w)
+        .unwrap();
+
+    String::from_utf8(w).unwrap()
+}
+
 impl Constant {
     pub fn to_pretty(&self) -> String {
         let mut w = Vec::new();
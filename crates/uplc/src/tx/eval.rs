@@ -13,32 +13,46 @@ use crate::{
     },
 };
 use pallas_codec::utils::Bytes;
-use pallas_primitives::conway::{CostModel, CostModels, ExUnits, Language, MintedTx, Redeemer};
+use pallas_traverse::ComputeHash;
+use pallas_primitives::conway::{
+    CostModel, CostModels, ExUnits, Language, MintedTx, Redeemer, ScriptHash,
+};
 
-pub fn eval_redeemer(
+/// A redeemer's script with the arguments the ledger passes it applied.
+#[derive(Debug, Clone)]
+pub struct PreparedRedeemer {
+    pub script_hash: ScriptHash,
+    pub language: Language,
+    pub program: Program<NamedDeBruijn>,
+    /// The datum of the spent output, for spending redeemers.
+    pub datum: Option<PlutusData>,
+    pub script_context: ScriptContext,
+}
+
+/// Build the program the ledger runs for `redeemer`, without evaluating it:
+/// the script is resolved with [`find_script`] and applied to the datum (if
+/// any), the redeemer and the script context as its language expects.
+pub fn prepare_redeemer(
     tx: &MintedTx,
     utxos: &[ResolvedInput],
     slot_config: &SlotConfig,
     redeemer: &Redeemer,
     lookup_table: &DataLookupTable,
-    cost_mdls_opt: Option<&CostModels>,
-    initial_budget: &ExBudget,
-) -> Result<(Redeemer, EvalResult), Error> {
-    fn do_eval_redeemer(
-        cost_mdl_opt: Option<&CostModel>,
-        initial_budget: &ExBudget,
-        lang: &Language,
+) -> Result<PreparedRedeemer, Error> {
+    fn do_prepare_redeemer(
+        script_hash: ScriptHash,
+        language: Language,
         datum: Option<PlutusData>,
         redeemer: &Redeemer,
         tx_info: TxInfo,
         program: Program<NamedDeBruijn>,
-    ) -> Result<(Redeemer, EvalResult), Error> {
+    ) -> PreparedRedeemer {
         let script_context = tx_info
             .into_script_context(redeemer, datum.as_ref())
             .expect("couldn't create script context from transaction?");
 
         let program = match script_context {
-            ScriptContext::V1V2 { .. } => if let Some(datum) = datum {
+            ScriptContext::V1V2 { .. } => if let Some(datum) = datum.clone() {
                 program.apply_data(datum)
             } else {
                 program
@@ -49,10 +63,91 @@ pub fn eval_redeemer(
             ScriptContext::V3 { .. } => program.apply_data(script_context.to_plutus_data()),
         };
 
+        PreparedRedeemer {
+            script_hash,
+            language,
+            program,
+            datum,
+            script_context,
+        }
+    }
+
+    let program = |script: Bytes| {
+        let mut buffer = Vec::new();
+        Program::<FakeNamedDeBruijn>::from_cbor(&script, &mut buffer)
+            .map(Into::<Program<NamedDeBruijn>>::into)
+    };
+
+    let prepare = || -> Result<PreparedRedeemer, Error> {
+        Ok(match find_script(redeemer, tx, utxos, lookup_table)? {
+            (PlutusScript::V1(script), datum) => do_prepare_redeemer(
+                script.compute_hash(),
+                Language::PlutusV1,
+                datum,
+                redeemer,
+                TxInfoV1::from_transaction(tx, utxos, slot_config)?,
+                program(script.0)?,
+            ),
+
+            (PlutusScript::V2(script), datum) => do_prepare_redeemer(
+                script.compute_hash(),
+                Language::PlutusV2,
+                datum,
+                redeemer,
+                TxInfoV2::from_transaction(tx, utxos, slot_config)?,
+                program(script.0)?,
+            ),
+
+            (PlutusScript::V3(script), datum) => do_prepare_redeemer(
+                script.compute_hash(),
+                Language::PlutusV3,
+                datum,
+                redeemer,
+                TxInfoV3::from_transaction(tx, utxos, slot_config)?,
+                program(script.0)?,
+            ),
+        })
+    };
+
+    prepare().map_err(|err| redeemer_error(redeemer, err))
+}
+
+pub fn eval_redeemer(
+    tx: &MintedTx,
+    utxos: &[ResolvedInput],
+    slot_config: &SlotConfig,
+    redeemer: &Redeemer,
+    lookup_table: &DataLookupTable,
+    cost_mdls_opt: Option<&CostModels>,
+    initial_budget: &ExBudget,
+) -> Result<(Redeemer, EvalResult), Error> {
+    let prepared = prepare_redeemer(tx, utxos, slot_config, redeemer, lookup_table)?;
+
+    eval_prepared_redeemer(prepared, redeemer, cost_mdls_opt, initial_budget)
+}
+
+/// Evaluate a redeemer [`prepare_redeemer`] has already built, as
+/// [`eval_redeemer`] does.
+pub fn eval_prepared_redeemer(
+    prepared: PreparedRedeemer,
+    redeemer: &Redeemer,
+    cost_mdls_opt: Option<&CostModels>,
+    initial_budget: &ExBudget,
+) -> Result<(Redeemer, EvalResult), Error> {
+    fn do_eval_redeemer(
+        cost_mdl_opt: Option<&CostModel>,
+        initial_budget: &ExBudget,
+        redeemer: &Redeemer,
+        prepared: PreparedRedeemer,
+    ) -> Result<(Redeemer, EvalResult), Error> {
         let eval_result = if let Some(costs) = cost_mdl_opt {
-            program.eval_as(lang, costs, Some(initial_budget))
+            prepared
+                .program
+                .eval_as(&prepared.language, costs, Some(initial_budget))
         } else {
-            program.eval_version(ExBudget::default(), lang)
+            prepared
+                .program
+                .eval_version(*initial_budget, &prepared.language)
         };
 
         let cost = eval_result.cost();
@@ -74,67 +169,34 @@ pub fn eval_redeemer(
         Ok((new_redeemer, eval_result))
     }
 
-    let program = |script: Bytes| {
-        let mut buffer = Vec::new();
-        Program::<FakeNamedDeBruijn>::from_cbor(&script, &mut buffer)
-            .map(Into::<Program<NamedDeBruijn>>::into)
-    };
+    let cost_mdl = cost_mdls_opt
+        .map(|cost_mdls| {
+            match prepared.language {
+                Language::PlutusV1 => cost_mdls
+                    .plutus_v1
+                    .as_ref()
+                    .ok_or(Error::CostModelNotFound(Language::PlutusV1)),
+                Language::PlutusV2 => cost_mdls
+                    .plutus_v2
+                    .as_ref()
+                    .ok_or(Error::CostModelNotFound(Language::PlutusV2)),
+                Language::PlutusV3 => cost_mdls
+                    .plutus_v3
+                    .as_ref()
+                    .ok_or(Error::CostModelNotFound(Language::PlutusV3)),
+            }
+        })
+        .transpose()
+        .map_err(|err| redeemer_error(redeemer, err))?;
 
-    match find_script(redeemer, tx, utxos, lookup_table)? {
-        (PlutusScript::V1(script), datum) => do_eval_redeemer(
-            cost_mdls_opt
-                .map(|cost_mdls| {
-                    cost_mdls
-                        .plutus_v1
-                        .as_ref()
-                        .ok_or(Error::CostModelNotFound(Language::PlutusV2))
-                })
-                .transpose()?,
-            initial_budget,
-            &Language::PlutusV1,
-            datum,
-            redeemer,
-            TxInfoV1::from_transaction(tx, utxos, slot_config)?,
-            program(script.0)?,
-        ),
-
-        (PlutusScript::V2(script), datum) => do_eval_redeemer(
-            cost_mdls_opt
-                .map(|cost_mdls| {
-                    cost_mdls
-                        .plutus_v2
-                        .as_ref()
-                        .ok_or(Error::CostModelNotFound(Language::PlutusV2))
-                })
-                .transpose()?,
-            initial_budget,
-            &Language::PlutusV2,
-            datum,
-            redeemer,
-            TxInfoV2::from_transaction(tx, utxos, slot_config)?,
-            program(script.0)?,
-        ),
-
-        (PlutusScript::V3(script), datum) => do_eval_redeemer(
-            cost_mdls_opt
-                .map(|cost_mdls| {
-                    cost_mdls
-                        .plutus_v3
-                        .as_ref()
-                        .ok_or(Error::CostModelNotFound(Language::PlutusV3))
-                })
-                .transpose()?,
-            initial_budget,
-            &Language::PlutusV3,
-            datum,
-            redeemer,
-            TxInfoV3::from_transaction(tx, utxos, slot_config)?,
-            program(script.0)?,
-        ),
-    }
-    .map_err(|err| Error::RedeemerError {
+    do_eval_redeemer(cost_mdl, initial_budget, redeemer, prepared)
+        .map_err(|err| redeemer_error(redeemer, err))
+}
+
+fn redeemer_error(redeemer: &Redeemer, err: Error) -> Error {
+    Error::RedeemerError {
         tag: redeemer_tag_to_string(&redeemer.tag),
         index: redeemer.index,
         err: Box::new(err),
-    })
+    }
 }
@@ -38,6 +38,13 @@ pub fn output_address(output: &TransactionOutput) -> Address {
     }
 }
 
+pub fn output_value(output: &TransactionOutput) -> Value {
+    match output {
+        TransactionOutput::Legacy(x) => from_alonzo_value(&x.amount),
+        TransactionOutput::PostAlonzo(x) => x.value.clone(),
+    }
+}
+
 pub fn output_datum(output: &TransactionOutput) -> Option<DatumOption> {
     match output {
         TransactionOutput::Legacy(x) => x.datum_hash.map(DatumOption::Hash),
@@ -445,6 +452,63 @@ impl TxInfo {
         }
     }
 
+    pub fn reference_inputs(&self) -> &[TxInInfo] {
+        match self {
+            TxInfo::V1(_) => &[],
+            TxInfo::V2(info) => &info.reference_inputs,
+            TxInfo::V3(info) => &info.reference_inputs,
+        }
+    }
+
+    pub fn redeemers(&self) -> &KeyValuePairs<ScriptPurpose, Redeemer> {
+        match self {
+            TxInfo::V1(info) => &info.redeemers,
+            TxInfo::V2(info) => &info.redeemers,
+            TxInfo::V3(info) => &info.redeemers,
+        }
+    }
+
+    pub fn outputs(&self) -> &[TransactionOutput] {
+        match self {
+            TxInfo::V1(info) => &info.outputs,
+            TxInfo::V2(info) => &info.outputs,
+            TxInfo::V3(info) => &info.outputs,
+        }
+    }
+
+    pub fn fee(&self) -> Coin {
+        match self {
+            TxInfo::V1(TxInfoV1 { fee, .. }) | TxInfo::V2(TxInfoV2 { fee, .. }) => match fee {
+                Value::Coin(coin) | Value::Multiasset(coin, _) => *coin,
+            },
+            TxInfo::V3(info) => info.fee,
+        }
+    }
+
+    pub fn valid_range(&self) -> &TimeRange {
+        match self {
+            TxInfo::V1(info) => &info.valid_range,
+            TxInfo::V2(info) => &info.valid_range,
+            TxInfo::V3(info) => &info.valid_range,
+        }
+    }
+
+    pub fn signatories(&self) -> &[AddrKeyhash] {
+        match self {
+            TxInfo::V1(info) => &info.signatories,
+            TxInfo::V2(info) => &info.signatories,
+            TxInfo::V3(info) => &info.signatories,
+        }
+    }
+
+    pub fn id(&self) -> Hash<32> {
+        match self {
+            TxInfo::V1(info) => info.id,
+            TxInfo::V2(info) => info.id,
+            TxInfo::V3(info) => info.id,
+        }
+    }
+
     pub fn mint(&self) -> &MintValue {
         match self {
             TxInfo::V1(info) => &info.mint,
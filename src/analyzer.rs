@@ -1,54 +1,437 @@
-pub struct ValidatorAnalyzer {
-    snapshots: Vec<StepSnapshot>,
-    source_map: BTreeMap<u64, String>,
+//! Root-cause analysis of a failed evaluation.
+//!
+//! A validator usually fails because a check returned `False` and an
+//! `ifThenElse` on that result picked the branch that evaluates `(error)`.
+//! [`analyze`] runs a session once from the start and follows boolean
+//! results by identity: the machine hands the very constant a builtin
+//! returned to whatever consumes it, so the check whose result reached the
+//! deciding branch is known rather than guessed.
+
+use std::{fmt, rc::Rc};
+
+use serde::{Serialize, Serializer};
+
+use crate::provenance;
+
+use uplc::{
+    ast::{Constant, Term},
+    builtins::DefaultFunction,
+    machine::{
+        debug::{render_value, DebugSession, FrameView, MachineView, StateView},
+        value::Value,
+    },
+};
+
+/// Why an evaluation failed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RootCause {
+    pub failure: Failure,
+    /// The last branch taken before `(error)`.
+    pub decision: Option<Decision>,
+    /// The check behind `decision`.
+    pub check: Option<Check>,
+    /// The last trace emitted before the failure.
+    pub trace: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Failure {
+    /// `(error)` was evaluated at `step`.
+    ErrorTerm { step: usize },
+    /// A builtin call failed.
+    Builtin {
+        step: usize,
+        #[serde(serialize_with = "display")]
+        function: DefaultFunction,
+        arguments: Vec<String>,
+        /// Where each argument comes from, when provenance is tracked.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        origins: Vec<Option<String>>,
+        error: String,
+    },
+    /// Any other machine error, such as running out of budget.
+    Machine { step: usize, error: String },
+}
+
+/// A branch chosen by the program.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Decision {
+    IfThenElse { step: usize, condition: bool },
+    Case { step: usize, tag: usize },
+}
+
+/// A builtin that returned a boolean.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub step: usize,
+    #[serde(serialize_with = "display")]
+    pub function: DefaultFunction,
+    pub arguments: Vec<String>,
+    /// Where each argument comes from, when provenance is tracked.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub origins: Vec<Option<String>>,
+    pub result: bool,
+    pub flow: Flow,
 }
 
-impl ValidatorAnalyzer {
-    pub fn analyze_failure(&self) -> ValidationFailure {
-        let failing_step = self.find_failing_step();
-        let intent = self.reconstruct_intent(failing_step);
-        let mismatch = self.identify_mismatch(failing_step);
-        
-        ValidationFailure {
-            intent,
-            provided: self.extract_tx_data(failing_step),
-            root_cause: mismatch,
-            fix_suggestion: self.generate_fix(mismatch),
+impl Check {
+    /// Argument `i` as the path it was read from, with its value, e.g.
+    /// `ctx.tx_info.fee (150000000)`, or just its value.
+    pub fn operand(&self, i: usize) -> String {
+        let argument = self.arguments.get(i).map(String::as_str).unwrap_or("?");
+
+        match self.origins.get(i).and_then(Option::as_deref) {
+            Some(origin) => format!("{} ({})", origin, argument),
+            None => argument.to_string(),
+        }
+    }
+
+    /// The arguments, each followed by where it comes from if known.
+    pub fn annotated_arguments(&self) -> Vec<String> {
+        provenance::annotate_all(&self.arguments, &self.origins)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flow {
+    /// The check's own result was the branch condition.
+    Direct,
+    /// The condition was built some other way (a literal `False` from `&&`,
+    /// for instance); this is the last check with the same result before
+    /// the branch.
+    Nearest,
+}
+
+/// Run `session` from the start and explain why it fails, or `None` if it
+/// does not. The session is left on the step it was on.
+pub fn analyze(session: &mut DebugSession) -> Option<RootCause> {
+    let current = session.current_step();
+    session.goto(0);
+
+    let mut tracker = Tracker::default();
+    loop {
+        tracker.observe(&session.view());
+
+        if !session.step() {
+            break;
         }
     }
-    
-    fn reconstruct_intent(&self, step: &StepSnapshot) -> String {
-        // Pattern match on UPLC terms to identify checks
-        match &step.term {
-            // Example: "equalsInteger" suggests value check
-            term if term.contains("equalsInteger") => {
-                "Validator requires exact integer match".to_string()
+
+    let cause = tracker.root_cause(&session.view());
+    session.goto(current);
+    cause
+}
+
+/// A check, with its result kept alive so the pointer stays unique.
+struct Observed {
+    step: usize,
+    function: DefaultFunction,
+    arguments: Vec<Value>,
+    origins: Vec<Option<String>>,
+    result: Rc<Constant>,
+}
+
+struct Pending {
+    step: usize,
+    function: DefaultFunction,
+    arguments: Vec<Value>,
+    origins: Vec<Option<String>>,
+}
+
+enum Branch {
+    IfThenElse { step: usize, condition: Rc<Constant> },
+    Case { step: usize, tag: usize },
+}
+
+#[derive(Default)]
+struct Tracker {
+    checks: Vec<Observed>,
+    branch: Option<Branch>,
+    /// The builtin saturated on the previous step.
+    pending: Option<Pending>,
+    /// `(error)` was computed on the previous step.
+    error_term: Option<usize>,
+}
+
+impl Tracker {
+    fn observe(&mut self, view: &MachineView) {
+        // The failure itself is read by `root_cause`.
+        if let StateView::Failed { .. } = view.state {
+            return;
+        }
+
+        if let (Some(pending), StateView::Return { value: Value::Con(result) }) =
+            (self.pending.take(), &view.state)
+        {
+            if returns_bool(pending.function) && matches!(result.as_ref(), Constant::Bool(_)) {
+                self.checks.push(Observed {
+                    step: pending.step,
+                    function: pending.function,
+                    arguments: pending.arguments,
+                    origins: pending.origins,
+                    result: result.clone(),
+                });
             }
-            term if term.contains("verifySignature") => {
-                "Validator requires valid signature".to_string()
+        }
+
+        if let StateView::Return { value: Value::Constr { tag, .. } } = &view.state {
+            if matches!(view.frames.first(), Some(FrameView::Cases { .. })) {
+                self.branch = Some(Branch::Case { step: view.step, tag: *tag });
             }
-            // Add more patterns...
-            _ => "Unknown validation logic".to_string()
         }
+
+        if let Some((function, arguments)) = &view.saturating {
+            if let (DefaultFunction::IfThenElse, Some(Value::Con(condition))) = (function, arguments.first()) {
+                self.branch = Some(Branch::IfThenElse {
+                    step: view.step,
+                    condition: condition.clone(),
+                });
+            }
+
+            self.pending = Some(Pending {
+                step: view.step,
+                function: *function,
+                arguments: arguments.iter().map(|&value| value.clone()).collect(),
+                origins: origins(view, arguments),
+            });
+        }
+
+        self.error_term = match view.state {
+            StateView::Compute { term: Term::Error, .. } => Some(view.step),
+            _ => None,
+        };
     }
-    
-    fn generate_fix(&self, cause: &MismatchCause) -> String {
-        match cause {
-            MismatchCause::ValueMismatch { expected, actual } => {
-                format!("Change transaction value from {} to {}", actual, expected)
+
+    /// The cause of failure once `view`, the last step, is reached.
+    fn root_cause(self, view: &MachineView) -> Option<RootCause> {
+        let StateView::Failed { error } = view.state else {
+            return None;
+        };
+        let trace = view.traces.last().map(|trace| trace.to_string());
+
+        if let Some(step) = self.error_term {
+            let (decision, check) = match &self.branch {
+                Some(Branch::IfThenElse { step, condition }) => {
+                    let decision = Decision::IfThenElse {
+                        step: *step,
+                        condition: matches!(condition.as_ref(), Constant::Bool(true)),
+                    };
+                    (Some(decision), self.deciding_check(*step, condition))
+                }
+                Some(Branch::Case { step, tag }) => (Some(Decision::Case { step: *step, tag: *tag }), None),
+                None => (None, None),
+            };
+
+            return Some(RootCause {
+                failure: Failure::ErrorTerm { step },
+                decision,
+                check,
+                trace,
+            });
+        }
+
+        let failure = match self.pending {
+            Some(pending) => Failure::Builtin {
+                step: pending.step,
+                function: pending.function,
+                arguments: pending.arguments.iter().map(argument).collect(),
+                origins: pending.origins,
+                error: error.to_string(),
+            },
+            None => Failure::Machine {
+                step: view.step.saturating_sub(1),
+                error: error.to_string(),
+            },
+        };
+
+        Some(RootCause {
+            failure,
+            decision: None,
+            check: None,
+            trace,
+        })
+    }
+
+    /// The check whose result is `condition`, or the last one before
+    /// `before` that returned the same.
+    fn deciding_check(&self, before: usize, condition: &Rc<Constant>) -> Option<Check> {
+        let direct = self.checks.iter().rev().find(|check| Rc::ptr_eq(&check.result, condition));
+
+        let (observed, flow) = match direct {
+            Some(observed) => (observed, Flow::Direct),
+            None => (
+                self.checks
+                    .iter()
+                    .rev()
+                    .find(|check| check.step < before && check.result == *condition)?,
+                Flow::Nearest,
+            ),
+        };
+
+        Some(Check {
+            step: observed.step,
+            function: observed.function,
+            arguments: observed.arguments.iter().map(argument).collect(),
+            origins: observed.origins.clone(),
+            result: matches!(observed.result.as_ref(), Constant::Bool(true)),
+            flow,
+        })
+    }
+}
+
+fn returns_bool(function: DefaultFunction) -> bool {
+    use DefaultFunction::*;
+
+    matches!(
+        function,
+        EqualsInteger
+            | LessThanInteger
+            | LessThanEqualsInteger
+            | EqualsByteString
+            | LessThanByteString
+            | LessThanEqualsByteString
+            | EqualsString
+            | EqualsData
+            | NullList
+            | VerifyEd25519Signature
+            | VerifyEcdsaSecp256k1Signature
+            | VerifySchnorrSecp256k1Signature
+            | Bls12_381_G1_Equal
+            | Bls12_381_G2_Equal
+            | Bls12_381_FinalVerify
+            | ReadBit
+    )
+}
+
+/// Constants as they are written inside terms, anything else in full.
+fn argument(value: &Value) -> String {
+    match value {
+        Value::Con(constant) => match constant.as_ref() {
+            Constant::Integer(i) => i.to_string(),
+            Constant::ByteString(bytes) => format!("#{}", hex::encode(bytes)),
+            Constant::String(s) => format!("{:?}", s),
+            Constant::Bool(b) => bool_name(*b).to_string(),
+            _ => render_value(value),
+        },
+        _ => render_value(value),
+    }
+}
+
+/// Where each of `arguments` comes from, or nothing if none is known.
+fn origins(view: &MachineView, arguments: &[&Value]) -> Vec<Option<String>> {
+    let origins: Vec<_> = arguments.iter().map(|value| provenance::describe(view, value)).collect();
+
+    if origins.iter().all(Option::is_none) {
+        vec![]
+    } else {
+        origins
+    }
+}
+
+/// Builtins by their UPLC name.
+fn display<S: Serializer>(function: &DefaultFunction, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(function)
+}
+
+fn bool_name(b: bool) -> &'static str {
+    if b { "True" } else { "False" }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "step {}: {}({}) returned {}",
+            self.step,
+            self.function,
+            self.annotated_arguments().join(", "),
+            bool_name(self.result)
+        )
+    }
+}
+
+impl fmt::Display for RootCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.failure, &self.decision, &self.check) {
+            (Failure::ErrorTerm { .. }, _, Some(check)) => {
+                write!(f, "{} → error branch", check)?;
+                if check.flow == Flow::Nearest {
+                    write!(f, " (nearest check before the branch)")?;
+                }
+                Ok(())
             }
-            MismatchCause::MissingSignature { required } => {
-                format!("Add signature from address: {}", required)
+            (Failure::ErrorTerm { .. }, Some(Decision::IfThenElse { step, condition }), None) => {
+                write!(f, "step {}: ifThenElse on {} → error branch", step, bool_name(*condition))
+            }
+            (Failure::ErrorTerm { .. }, Some(Decision::Case { step, tag }), None) => {
+                write!(f, "step {}: case on constructor {} → error branch", step, tag)
+            }
+            (Failure::ErrorTerm { step }, None, None) => {
+                write!(f, "step {}: (error) reached without a branch", step)
+            }
+            (Failure::Builtin { step, function, arguments, origins, error }, _, _) => write!(
+                f,
+                "step {}: {}({}) failed: {}",
+                step,
+                function,
+                provenance::annotate_all(arguments, origins).join(", "),
+                error.lines().next().unwrap_or_default()
+            ),
+            (Failure::Machine { step, error }, _, _) => {
+                write!(f, "step {}: {}", step, error.lines().next().unwrap_or_default())
             }
-            // More cases...
         }
     }
 }
 
-#[derive(Debug)]
-pub struct ValidationFailure {
-    pub intent: String,
-    pub provided: TxData,
-    pub root_cause: MismatchCause,
-    pub fix_suggestion: String,
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze_program(code: &str) -> Option<RootCause> {
+        let mut session = crate::executor::start_session(uplc::parser::program(code).unwrap().try_into().unwrap());
+        analyze(&mut session)
+    }
+
+    #[test]
+    fn test_finds_the_check_behind_the_error_branch() {
+        let cause = analyze_program(
+            "(program 1.0.0 [(lam amount (force [(force (builtin ifThenElse)) [(builtin lessThanInteger) amount (con integer 100000000)] (delay (con unit ())) (delay (error))])) (con integer 150000000)])",
+        )
+        .unwrap();
+
+        let check = cause.check.as_ref().unwrap();
+        assert_eq!(check.function, DefaultFunction::LessThanInteger);
+        assert_eq!(check.flow, Flow::Direct);
+        assert!(matches!(cause.decision, Some(Decision::IfThenElse { condition: false, .. })));
+        assert_eq!(
+            cause.to_string(),
+            format!("step {}: lessThanInteger(150000000, 100000000) returned False → error branch", check.step)
+        );
+    }
+
+    #[test]
+    fn test_literal_conditions_fall_back_to_the_nearest_check() {
+        // `a && b` with a false `a` branches on a literal False.
+        let cause = analyze_program(
+            "(program 1.0.0 (force [(force (builtin ifThenElse)) (force [(force (builtin ifThenElse)) [(builtin equalsInteger) (con integer 1) (con integer 2)] (delay (con bool True)) (delay (con bool False))]) (delay (con unit ())) (delay (error))]))",
+        )
+        .unwrap();
+
+        let check = cause.check.unwrap();
+        assert_eq!(check.function, DefaultFunction::EqualsInteger);
+        assert_eq!(check.flow, Flow::Nearest);
+    }
+
+    #[test]
+    fn test_builtin_failures_and_successes() {
+        let cause = analyze_program("(program 1.0.0 [(builtin unBData) (con data (I 42))])").unwrap();
+        assert!(matches!(cause.failure, Failure::Builtin { function: DefaultFunction::UnBData, .. }));
+        assert!(cause.to_string().contains("unBData((con data (I 42))) failed"));
+
+        assert!(analyze_program("(program 1.0.0 [(builtin addInteger) (con integer 1) (con integer 2)])").is_none());
+    }
+}
@@ -0,0 +1,684 @@
+//! CIP-57 blueprints (Aiken's `plutus.json`): selecting a validator and
+//! turning arguments into the data its schemas describe.
+//!
+//! Arguments are JSON shaped by the schema: integers as numbers (or strings
+//! when large), byte arrays as hex strings, lists as arrays, maps as arrays
+//! of `[key, value]` pairs or objects, and constructors as
+//! `{"<Constructor>": {"<field>": ...}}` or just `"<Constructor>"` when it
+//! has no fields. A type with a single constructor takes its fields
+//! directly, `Bool` takes `true` and `false`, and `null` is `None`. Anything
+//! else is read as data in any notation of [`crate::parameters`] and checked
+//! against the schema.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow, bail};
+use num_bigint::BigInt;
+use pallas_primitives::conway::Language;
+use serde::Deserialize;
+use serde_json::Value;
+use uplc::{
+    PlutusData,
+    ast::{Constant, Data, NamedDeBruijn, Program, Term, Type},
+    machine::runtime::convert_tag_to_constr,
+};
+
+use crate::{loader, parameters};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Blueprint {
+    pub preamble: Preamble,
+    pub validators: Vec<Validator>,
+    #[serde(default)]
+    pub definitions: BTreeMap<String, Value>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preamble {
+    pub title: String,
+    #[serde(default)]
+    pub plutus_version: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validator {
+    pub title: String,
+    #[serde(default)]
+    pub datum: Option<Argument>,
+    #[serde(default)]
+    pub redeemer: Option<Argument>,
+    #[serde(default)]
+    pub parameters: Vec<Argument>,
+    pub compiled_code: String,
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Argument {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub schema: Value,
+}
+
+/// Whether `json` looks like a blueprint rather than a single compiled
+/// program.
+pub fn is_blueprint(json: &Value) -> bool {
+    json.get("validators").is_some_and(Value::is_array)
+}
+
+impl Blueprint {
+    pub fn read(path: &Path) -> Result<Blueprint> {
+        let raw = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+        let json: Value = serde_json::from_slice(&raw).with_context(|| format!("{} is not valid JSON", path.display()))?;
+
+        Blueprint::from_value(json).with_context(|| format!("invalid blueprint {}", path.display()))
+    }
+
+    pub fn from_value(json: Value) -> Result<Blueprint> {
+        serde_json::from_value(json).context("could not parse blueprint")
+    }
+
+    /// The validator titled `title`, or the only one when no title is given.
+    pub fn validator(&self, title: Option<&str>) -> Result<&Validator> {
+        let available = || {
+            self.validators
+                .iter()
+                .map(|v| v.title.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match title {
+            Some(title) => self
+                .validators
+                .iter()
+                .find(|v| v.title == title)
+                .ok_or_else(|| anyhow!("the blueprint has no validator '{}' (it has: {})", title, available())),
+            None => match self.validators.as_slice() {
+                [] => bail!("the blueprint has no validators"),
+                [validator] => Ok(validator),
+                _ => bail!("the blueprint has several validators, pick one with --validator ({})", available()),
+            },
+        }
+    }
+
+    /// The language of every validator, from `preamble.plutusVersion`.
+    pub fn language(&self) -> Result<Option<Language>> {
+        self.preamble
+            .plutus_version
+            .as_deref()
+            .map(crate::executor::parse_language)
+            .transpose()
+    }
+
+    /// What each argument `args` supplies to `validator` stands for, with
+    /// its schema: the parameters, then for PlutusV1 and V2 the datum (if
+    /// any) and redeemer. Later arguments, such as the script context, have
+    /// no schema.
+    pub fn arguments<'a>(&self, validator: &'a Validator) -> Result<Vec<(String, &'a Value)>> {
+        let mut arguments: Vec<(String, &Value)> = validator
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (format!("parameter {}", p.title.clone().unwrap_or_else(|| i.to_string())), &p.schema))
+            .collect();
+
+        if !matches!(self.language()?, Some(Language::PlutusV3)) {
+            if let Some(datum) = &validator.datum {
+                arguments.push(("datum".to_string(), &datum.schema));
+            }
+            if let Some(redeemer) = &validator.redeemer {
+                arguments.push(("redeemer".to_string(), &redeemer.schema));
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// Turn command-line arguments into data, or constants for non-Data
+    /// types: typed by the validator's schemas where it has them, read as
+    /// plain arguments otherwise.
+    pub fn apply_arguments(&self, validator: &Validator, args: &[String]) -> Result<Vec<parameters::Argument>> {
+        let arguments = self.arguments(validator)?;
+
+        args.iter()
+            .enumerate()
+            .map(|(i, arg)| match arguments.get(i) {
+                Some((name, schema)) => {
+                    let argument = if is_builtin(self.resolve(schema)?) {
+                        self.builtin_argument(schema, arg)
+                    } else {
+                        self.argument(schema, arg).map(parameters::Argument::Data)
+                    };
+                    argument.with_context(|| format!("invalid {}", name))
+                }
+                None => loader::parse_argument(i, arg.clone()),
+            })
+            .collect()
+    }
+
+    /// An argument of a non-Data type (`#integer`, `#list`, ...), as JSON
+    /// shaped by `schema` or as a UPLC term.
+    pub fn builtin_argument(&self, schema: &Value, arg: &str) -> Result<parameters::Argument> {
+        let arg = parameters::dereference(arg)?;
+
+        match serde_json::from_str::<Value>(&arg) {
+            Ok(json) => Ok(parameters::Argument::Term(Term::Constant(self.constant_at(schema, &json, "$")?.into()))),
+            Err(_) => match parameters::parse_argument(&arg)? {
+                parameters::Argument::Term(term) => Ok(parameters::Argument::Term(term)),
+                parameters::Argument::Data(_) => bail!("expected JSON matching the schema or a UPLC term, got data"),
+            },
+        }
+    }
+
+    fn constant_at(&self, schema: &Value, input: &Value, at: &str) -> Result<Constant> {
+        let schema = self.resolve(schema)?;
+
+        match data_type(schema) {
+            Some("#unit") if input.is_null() => Ok(Constant::Unit),
+            Some("#unit") => bail!("{}: expected null, got {}", at, input),
+            Some("#boolean") => input
+                .as_bool()
+                .map(Constant::Bool)
+                .ok_or_else(|| anyhow!("{}: expected true or false, got {}", at, input)),
+            Some("#string") => input
+                .as_str()
+                .map(|s| Constant::String(s.to_string()))
+                .ok_or_else(|| anyhow!("{}: expected a string, got {}", at, input)),
+            Some("#integer") => Ok(Constant::Integer(integer(input, at)?)),
+            Some("#bytes") => Ok(Constant::ByteString(bytes(input, at)?)),
+            Some("#list") => {
+                let items = input
+                    .as_array()
+                    .ok_or_else(|| anyhow!("{}: expected an array, got {}", at, input))?;
+
+                Ok(Constant::ProtoList(
+                    self.constant_type(&schema["items"])?,
+                    items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| self.constant_at(&schema["items"], item, &format!("{}[{}]", at, i)))
+                        .collect::<Result<_>>()?,
+                ))
+            }
+            Some("#pair") => {
+                let Some([left, right]) = input.as_array().map(Vec::as_slice) else {
+                    bail!("{}: expected an array of two elements, got {}", at, input);
+                };
+
+                Ok(Constant::ProtoPair(
+                    self.constant_type(&schema["left"])?,
+                    self.constant_type(&schema["right"])?,
+                    self.constant_at(&schema["left"], left, &format!("{}[0]", at))?.into(),
+                    self.constant_at(&schema["right"], right, &format!("{}[1]", at))?.into(),
+                ))
+            }
+            Some(other) if other.starts_with('#') => bail!("{}: unknown dataType '{}'", at, other),
+            _ => Ok(Constant::Data(self.encode_at(schema, input, at)?)),
+        }
+    }
+
+    /// The UPLC type of the constants `schema` describes.
+    fn constant_type(&self, schema: &Value) -> Result<Type> {
+        let schema = self.resolve(schema)?;
+
+        Ok(match data_type(schema) {
+            Some("#unit") => Type::Unit,
+            Some("#boolean") => Type::Bool,
+            Some("#integer") => Type::Integer,
+            Some("#bytes") => Type::ByteString,
+            Some("#string") => Type::String,
+            Some("#list") => Type::List(self.constant_type(&schema["items"])?.into()),
+            Some("#pair") => Type::Pair(
+                self.constant_type(&schema["left"])?.into(),
+                self.constant_type(&schema["right"])?.into(),
+            ),
+            Some(other) if other.starts_with('#') => bail!("unknown dataType '{}'", other),
+            _ => Type::Data,
+        })
+    }
+
+    /// One argument, as JSON shaped by `schema` or as data in any notation
+    /// [`parameters`] reads that fits it.
+    pub fn argument(&self, schema: &Value, arg: &str) -> Result<PlutusData> {
+        let arg = parameters::dereference(arg)?;
+        let json = serde_json::from_str::<Value>(&arg);
+
+        let encoded = match &json {
+            Ok(json) => self.encode(schema, json),
+            Err(_) => Err(anyhow!("not JSON")),
+        };
+
+        match encoded {
+            Ok(data) => Ok(data),
+            Err(error) => match parameters::parse(&arg) {
+                Ok(data) => {
+                    self.validate(schema, &data)?;
+                    Ok(data)
+                }
+                // JSON that does not fit the schema explains more than the
+                // failure to read it as data.
+                Err(_) if json.is_ok() => Err(error),
+                Err(error) => Err(error.context("expected JSON matching the schema or data")),
+            },
+        }
+    }
+
+    /// Encode JSON `input` as the data `schema` describes.
+    pub fn encode(&self, schema: &Value, input: &Value) -> Result<PlutusData> {
+        self.encode_at(schema, input, "$")
+    }
+
+    /// Check that `data` has the shape `schema` describes.
+    pub fn validate(&self, schema: &Value, data: &PlutusData) -> Result<()> {
+        self.validate_at(schema, data, "$")
+    }
+
+    fn resolve<'a>(&'a self, schema: &'a Value) -> Result<&'a Value> {
+        let mut schema = schema;
+
+        // References may chain, but not loop forever.
+        for _ in 0..64 {
+            let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+                return Ok(schema);
+            };
+            let name = reference
+                .strip_prefix("#/definitions/")
+                .ok_or_else(|| anyhow!("unsupported reference '{}'", reference))?
+                .replace("~1", "/")
+                .replace("~0", "~");
+
+            schema = self
+                .definitions
+                .get(&name)
+                .ok_or_else(|| anyhow!("the blueprint has no definition '{}'", name))?;
+        }
+
+        bail!("too many nested references")
+    }
+
+    fn encode_at(&self, schema: &Value, input: &Value, at: &str) -> Result<PlutusData> {
+        let schema = self.resolve(schema)?;
+
+        if let Some(constructors) = schema.get("anyOf").and_then(Value::as_array) {
+            let (constructor, fields) = self.pick_constructor(constructors, input, at)?;
+            return self.encode_constructor(constructor, fields, at);
+        }
+
+        match data_type(schema) {
+            Some("integer") => Ok(Data::integer(integer(input, at)?)),
+            Some("bytes") => Ok(Data::bytestring(bytes(input, at)?)),
+            Some("list") => {
+                let items = input
+                    .as_array()
+                    .ok_or_else(|| anyhow!("{}: expected an array, got {}", at, input))?;
+
+                match &schema["items"] {
+                    Value::Array(tuple) => {
+                        if tuple.len() != items.len() {
+                            bail!("{}: expected {} elements, got {}", at, tuple.len(), items.len());
+                        }
+                        Ok(Data::list(
+                            tuple
+                                .iter()
+                                .zip(items)
+                                .enumerate()
+                                .map(|(i, (schema, item))| self.encode_at(schema, item, &format!("{}[{}]", at, i)))
+                                .collect::<Result<_>>()?,
+                        ))
+                    }
+                    item => Ok(Data::list(
+                        items
+                            .iter()
+                            .enumerate()
+                            .map(|(i, value)| self.encode_at(item, value, &format!("{}[{}]", at, i)))
+                            .collect::<Result<_>>()?,
+                    )),
+                }
+            }
+            Some("map") => {
+                let entries: Vec<(Value, &Value)> = match input {
+                    Value::Object(fields) => fields.iter().map(|(k, v)| (Value::String(k.clone()), v)).collect(),
+                    Value::Array(pairs) => pairs
+                        .iter()
+                        .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                            Some([k, v]) => Ok((k.clone(), v)),
+                            _ => Err(anyhow!("{}: expected [key, value] pairs, got {}", at, pair)),
+                        })
+                        .collect::<Result<_>>()?,
+                    _ => bail!("{}: expected an object or an array of [key, value] pairs, got {}", at, input),
+                };
+
+                Ok(Data::map(
+                    entries
+                        .iter()
+                        .map(|(k, v)| {
+                            let at = format!("{}[{}]", at, k);
+                            Ok((
+                                self.encode_at(&schema["keys"], k, &at)?,
+                                self.encode_at(&schema["values"], v, &at)?,
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                ))
+            }
+            Some("constructor") => self.encode_constructor(schema, input, at),
+            Some(other) if other.starts_with('#') => {
+                bail!("{}: {} is not a Data type, it can only be a whole argument", at, other)
+            }
+            Some(other) => bail!("{}: unknown dataType '{}'", at, other),
+            // Opaque data: any Plutus data, in the detailed JSON schema.
+            None => match input {
+                Value::String(cbor) => loader::parse_parameter(0, cbor.clone()).with_context(|| at.to_string()),
+                json => loader::data_from_json(json).with_context(|| at.to_string()),
+            },
+        }
+    }
+
+    /// The constructor of `constructors` that `input` names, and its fields.
+    fn pick_constructor<'a>(
+        &'a self,
+        constructors: &'a [Value],
+        input: &'a Value,
+        at: &str,
+    ) -> Result<(&'a Value, &'a Value)> {
+        let constructors = constructors
+            .iter()
+            .map(|c| self.resolve(c))
+            .collect::<Result<Vec<_>>>()?;
+        let titled = |title: &str| constructors.iter().copied().find(|c| c["title"] == title);
+        let titles = || {
+            constructors
+                .iter()
+                .map(|c| c["title"].as_str().unwrap_or("?"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let picked = match input {
+            Value::Bool(b) => titled(if *b { "True" } else { "False" }).map(|c| (c, &Value::Null)),
+            Value::Null => titled("None").map(|c| (c, &Value::Null)),
+            Value::String(title) => titled(title).map(|c| (c, &Value::Null)),
+            Value::Object(fields) if fields.len() == 1 => {
+                let (title, value) = fields.iter().next().expect("one field");
+                titled(title).map(|c| (c, value))
+            }
+            _ => None,
+        };
+
+        match (picked, constructors.as_slice()) {
+            (Some(picked), _) => Ok(picked),
+            (None, [only]) => Ok((only, input)),
+            (None, _) => bail!("{}: expected one of {}, got {}", at, titles(), input),
+        }
+    }
+
+    fn encode_constructor(&self, constructor: &Value, input: &Value, at: &str) -> Result<PlutusData> {
+        let index = constructor["index"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("{}: the schema's constructor has no index", at))?;
+        let title = constructor["title"].as_str().unwrap_or("constructor");
+        let schemas = constructor["fields"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        let field_name = |i: usize, schema: &Value| {
+            schema["title"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| i.to_string())
+        };
+
+        let values: Vec<&Value> = match input {
+            Value::Null => vec![],
+            Value::Array(values) => values.iter().collect(),
+            Value::Object(fields) => {
+                if let Some(unknown) = fields
+                    .keys()
+                    .find(|k| !schemas.iter().enumerate().any(|(i, s)| field_name(i, s) == **k))
+                {
+                    bail!("{}: {} has no field '{}'", at, title, unknown);
+                }
+
+                schemas
+                    .iter()
+                    .enumerate()
+                    .map(|(i, schema)| {
+                        fields
+                            .get(&field_name(i, schema))
+                            .ok_or_else(|| anyhow!("{}: {} is missing field '{}'", at, title, field_name(i, schema)))
+                    })
+                    .collect::<Result<_>>()?
+            }
+            // A constructor with a single field may take its value directly.
+            value if schemas.len() == 1 => vec![value],
+            other => bail!("{}: expected the fields of {}, got {}", at, title, other),
+        };
+
+        if values.len() != schemas.len() {
+            bail!("{}: {} has {} fields, got {}", at, title, schemas.len(), values.len());
+        }
+
+        let fields = schemas
+            .iter()
+            .zip(values)
+            .enumerate()
+            .map(|(i, (schema, value))| self.encode_at(schema, value, &format!("{}.{}", at, field_name(i, schema))))
+            .collect::<Result<_>>()?;
+
+        Ok(Data::constr(index, fields))
+    }
+
+    fn validate_at(&self, schema: &Value, data: &PlutusData, at: &str) -> Result<()> {
+        let schema = self.resolve(schema)?;
+
+        let constructors: Vec<&Value> = match (schema.get("anyOf").and_then(Value::as_array), data_type(schema)) {
+            (Some(constructors), _) => constructors.iter().map(|c| self.resolve(c)).collect::<Result<_>>()?,
+            (None, Some("constructor")) => vec![schema],
+            (None, Some("integer")) => {
+                return match data {
+                    PlutusData::BigInt(_) => Ok(()),
+                    _ => bail!("{}: expected an integer", at),
+                }
+            }
+            (None, Some("bytes")) => {
+                return match data {
+                    PlutusData::BoundedBytes(_) => Ok(()),
+                    _ => bail!("{}: expected bytes", at),
+                }
+            }
+            (None, Some("list")) => {
+                let PlutusData::Array(items) = data else {
+                    bail!("{}: expected a list", at);
+                };
+                return match &schema["items"] {
+                    Value::Array(tuple) if tuple.len() != items.len() => {
+                        bail!("{}: expected {} elements, got {}", at, tuple.len(), items.len())
+                    }
+                    Value::Array(tuple) => tuple
+                        .iter()
+                        .zip(items.iter())
+                        .enumerate()
+                        .try_for_each(|(i, (schema, item))| self.validate_at(schema, item, &format!("{}[{}]", at, i))),
+                    item => items
+                        .iter()
+                        .enumerate()
+                        .try_for_each(|(i, value)| self.validate_at(item, value, &format!("{}[{}]", at, i))),
+                };
+            }
+            (None, Some("map")) => {
+                let PlutusData::Map(entries) = data else {
+                    bail!("{}: expected a map", at);
+                };
+                return entries.iter().enumerate().try_for_each(|(i, (k, v))| {
+                    let at = format!("{}[{}]", at, i);
+                    self.validate_at(&schema["keys"], k, &at)?;
+                    self.validate_at(&schema["values"], v, &at)
+                });
+            }
+            (None, Some(other)) => bail!("{}: cannot check data against dataType '{}'", at, other),
+            (None, None) => return Ok(()),
+        };
+
+        let PlutusData::Constr(constr) = data else {
+            bail!("{}: expected a constructor", at);
+        };
+        let index = convert_tag_to_constr(constr.tag).or(constr.any_constructor);
+
+        let constructor = constructors
+            .iter()
+            .find(|c| c["index"].as_u64() == index)
+            .ok_or_else(|| anyhow!("{}: no constructor with index {:?} in the schema", at, index))?;
+        let schemas = constructor["fields"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        if schemas.len() != constr.fields.len() {
+            bail!(
+                "{}: {} has {} fields, got {}",
+                at,
+                constructor["title"].as_str().unwrap_or("constructor"),
+                schemas.len(),
+                constr.fields.len()
+            );
+        }
+
+        schemas
+            .iter()
+            .zip(constr.fields.iter())
+            .enumerate()
+            .try_for_each(|(i, (schema, field))| {
+                let name = schema["title"].as_str().map(str::to_string).unwrap_or_else(|| i.to_string());
+                self.validate_at(schema, field, &format!("{}.{}", at, name))
+            })
+    }
+}
+
+impl Validator {
+    /// The validator before any parameter is applied.
+    pub fn program(&self) -> Result<Program<NamedDeBruijn>> {
+        loader::program_from_compiled_code(&self.compiled_code)
+            .with_context(|| format!("invalid compiledCode for validator {}", self.title))
+    }
+}
+
+fn data_type(schema: &Value) -> Option<&str> {
+    schema.get("dataType").and_then(Value::as_str)
+}
+
+/// Whether `schema` describes a builtin UPLC type rather than Data.
+fn is_builtin(schema: &Value) -> bool {
+    data_type(schema).is_some_and(|t| t.starts_with('#'))
+}
+
+/// A JSON number, or a string for integers too large for one.
+fn integer(input: &Value, at: &str) -> Result<BigInt> {
+    let int = match input {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => String::new(),
+    };
+
+    int.parse().map_err(|_| anyhow!("{}: expected an integer, got {}", at, input))
+}
+
+fn bytes(input: &Value, at: &str) -> Result<Vec<u8>> {
+    input
+        .as_str()
+        .and_then(|s| hex::decode(s).ok())
+        .ok_or_else(|| anyhow!("{}: expected a hex string, got {}", at, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn blueprint() -> Blueprint {
+        Blueprint::from_value(json!({
+            "preamble": { "title": "test", "plutusVersion": "v2" },
+            "validators": [],
+            "definitions": {
+                "Int": { "dataType": "integer" },
+                "ByteArray": { "dataType": "bytes" },
+                "Bool": { "anyOf": [
+                    { "title": "False", "dataType": "constructor", "index": 0, "fields": [] },
+                    { "title": "True", "dataType": "constructor", "index": 1, "fields": [] }
+                ]},
+                "Option$Int": { "anyOf": [
+                    { "title": "Some", "dataType": "constructor", "index": 0, "fields": [{ "$ref": "#/definitions/Int" }] },
+                    { "title": "None", "dataType": "constructor", "index": 1, "fields": [] }
+                ]},
+                "types/Datum": { "anyOf": [{
+                    "title": "Datum", "dataType": "constructor", "index": 0, "fields": [
+                        { "title": "owner", "$ref": "#/definitions/ByteArray" },
+                        { "title": "amounts", "dataType": "list", "items": { "$ref": "#/definitions/Int" } },
+                        { "title": "locked", "$ref": "#/definitions/Bool" },
+                        { "title": "limit", "$ref": "#/definitions/Option$Int" }
+                    ]
+                }]}
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_encode_and_validate_by_schema() {
+        let blueprint = blueprint();
+        let datum = json!({ "$ref": "#/definitions/types~1Datum" });
+
+        let encoded = blueprint
+            .encode(&datum, &json!({ "owner": "abcd", "amounts": [1, 2], "locked": true, "limit": { "Some": 5 } }))
+            .unwrap();
+        let expected = Data::constr(0, vec![
+            Data::bytestring(vec![0xab, 0xcd]),
+            Data::list(vec![Data::integer(1.into()), Data::integer(2.into())]),
+            Data::constr(1, vec![]),
+            Data::constr(0, vec![Data::integer(5.into())]),
+        ]);
+        assert_eq!(encoded, expected);
+        assert!(blueprint.validate(&datum, &encoded).is_ok());
+
+        let positional = blueprint.encode(&datum, &json!(["abcd", [1, 2], true, { "Some": 5 }])).unwrap();
+        assert_eq!(positional, expected);
+
+        let error = |input: Value| format!("{:#}", blueprint.encode(&datum, &input).unwrap_err());
+        assert!(error(json!({ "owner": "abcd", "amounts": [1, "x"], "locked": true, "limit": null })).contains("$.amounts[1]"));
+        assert!(error(json!({ "owner": "abcd", "amounts": [], "locked": "Maybe", "limit": null })).contains("False, True"));
+        assert!(error(json!({ "owner": "abcd" })).contains("missing field 'amounts'"));
+
+        let wrong = Data::constr(0, vec![Data::integer(1.into())]);
+        assert!(blueprint.validate(&datum, &wrong).is_err());
+
+        let hex = Data::to_hex(expected.clone());
+        assert_eq!(blueprint.argument(&datum, &hex).unwrap(), expected);
+        assert!(blueprint.argument(&json!({ "$ref": "#/definitions/Int" }), &hex).is_err());
+        assert_eq!(
+            blueprint.argument(&json!({ "$ref": "#/definitions/Int" }), "10").unwrap(),
+            Data::integer(10.into())
+        );
+    }
+
+    #[test]
+    fn test_builtin_types_are_constants() {
+        let blueprint = blueprint();
+        let term = |schema: Value, arg: &str| match blueprint.builtin_argument(&schema, arg).unwrap() {
+            parameters::Argument::Term(term) => term.to_pretty(),
+            data => panic!("expected a term, got {:?}", data),
+        };
+
+        assert_eq!(term(json!({ "dataType": "#integer" }), "42"), "(con integer 42)");
+        assert_eq!(term(json!({ "dataType": "#string" }), r#""hi""#), "(con string \"hi\")");
+        assert_eq!(
+            term(json!({ "dataType": "#list", "items": { "dataType": "#bytes" } }), r#"["cafe"]"#),
+            "(con (list bytestring) [#cafe])"
+        );
+        assert_eq!(
+            term(json!({ "dataType": "#pair", "left": { "dataType": "#boolean" }, "right": { "$ref": "#/definitions/Int" } }), "[true, 3]"),
+            "(con (pair bool data) (True, I 3))"
+        );
+        assert_eq!(term(json!({ "dataType": "#integer" }), "(lam x x)"), "(lam x x)");
+
+        let error = blueprint.builtin_argument(&json!({ "dataType": "#list", "items": { "dataType": "#integer" } }), "[1, true]");
+        assert!(format!("{:#}", error.unwrap_err()).contains("$[1]"));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Result, anyhow};
+use uplc::builtins::DefaultFunction;
+pub use uplc::machine::debug::{Breakpoint, BreakpointHit, Condition};
+
+/// Breakpoints parsed from one spec, sharing an optional condition.
+#[derive(Debug, PartialEq)]
+pub struct ParsedBreakpoint {
+    pub breakpoints: Vec<Breakpoint>,
+    pub condition: Option<Condition>,
+}
+
+/// Parses a breakpoint spec typed at the debugger prompt.
+///
+/// Accepted forms:
+/// * `equalsInteger` or `builtin:equalsInteger`
+/// * `error`, `trace`
+/// * `cpu:<amount>`, `mem:<amount>` (total spent)
+/// * `step:<n>`
+/// * `at:<file>:<line>` — every step whose source-map entry is on that line of
+///   a file whose path ends with `<file>` (or that `<file>` ends with)
+///
+/// Any of these may be followed by `if <condition>`, e.g.
+/// `equalsInteger if arg[0] == 42` or `unConstrData if hits == 3`.
+pub fn parse_breakpoint(spec: &str, source_map: &BTreeMap<u64, String>) -> Result<ParsedBreakpoint> {
+    let (spec, condition) = match spec.split_once(" if ") {
+        Some((spec, condition)) => {
+            let condition = Condition::parse(condition)
+                .map_err(|e| anyhow!("invalid condition '{}': {}", condition.trim(), e))?;
+            (spec, Some(condition))
+        }
+        None => (spec, None),
+    };
+
+    Ok(ParsedBreakpoint {
+        breakpoints: parse_trigger(spec, source_map)?,
+        condition,
+    })
+}
+
+fn parse_trigger(spec: &str, source_map: &BTreeMap<u64, String>) -> Result<Vec<Breakpoint>> {
+    let spec = spec.trim();
+    let (kind, arg) = match spec.split_once(':') {
+        Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+        None => (spec, None),
+    };
+
+    let number = |what: &str| -> Result<i64> {
+        arg.ok_or_else(|| anyhow!("{} needs a value, e.g. {}:1000", what, what))?
+            .parse::<i64>()
+            .map_err(|e| anyhow!("invalid {} threshold: {}", what, e))
+    };
+
+    let breakpoint = match (kind, arg) {
+        ("error", None) => Breakpoint::Error,
+        ("trace", None) => Breakpoint::Trace,
+        ("cpu", _) => Breakpoint::Cpu(number("cpu")?),
+        ("mem", _) => Breakpoint::Mem(number("mem")?),
+        ("step", _) => Breakpoint::Step(
+            usize::try_from(number("step")?).map_err(|_| anyhow!("step must be positive"))?,
+        ),
+        ("builtin", Some(name)) => Breakpoint::Builtin(parse_builtin(name)?),
+        ("at", Some(location)) => {
+            let (file, line) = file_and_line(location)
+                .filter(|(file, _)| !file.is_empty())
+                .ok_or_else(|| anyhow!("location '{}' is not <file>:<line>", location))?;
+
+            let steps: Vec<Breakpoint> = source_map
+                .iter()
+                .filter(|(_, entry)| {
+                    file_and_line(entry).is_some_and(|(entry_file, entry_line)| {
+                        let (requested, entry_file) = (Path::new(file), Path::new(entry_file));
+                        entry_line == line && (entry_file.ends_with(requested) || requested.ends_with(entry_file))
+                    })
+                })
+                .map(|(step, _)| Breakpoint::Step(*step as usize))
+                .collect();
+
+            if steps.is_empty() {
+                return Err(anyhow!("no source-map entry matches '{}'", location));
+            }
+
+            return Ok(steps);
+        }
+        (name, None) => Breakpoint::Builtin(parse_builtin(name)?),
+        _ => return Err(anyhow!("unknown breakpoint '{}'", spec)),
+    };
+
+    Ok(vec![breakpoint])
+}
+
+/// Split `file:line`, or `file:line:column` with the column ignored.
+pub(crate) fn file_and_line(location: &str) -> Option<(&str, u64)> {
+    let (rest, last) = location.trim().rsplit_once(':')?;
+    let last = last.parse().ok()?;
+
+    match rest.rsplit_once(':').map(|(file, line)| (file, line.parse())) {
+        Some((file, Ok(line))) => Some((file, line)),
+        _ => Some((rest, last)),
+    }
+}
+
+fn parse_builtin(name: &str) -> Result<DefaultFunction> {
+    name.parse::<DefaultFunction>()
+        .map_err(|_| anyhow!("unknown builtin '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_breakpoints() {
+        let source_map = BTreeMap::from([
+            (3, "validator.ak:12".to_string()),
+            (7, "validator.ak:20".to_string()),
+        ]);
+
+        assert_eq!(
+            parse_breakpoint("equalsInteger", &source_map).unwrap().breakpoints,
+            vec![Breakpoint::Builtin(DefaultFunction::EqualsInteger)]
+        );
+        assert_eq!(
+            parse_breakpoint("cpu: 5000", &source_map).unwrap().breakpoints,
+            vec![Breakpoint::Cpu(5000)]
+        );
+        assert_eq!(
+            parse_breakpoint("at:validator.ak:20", &source_map).unwrap().breakpoints,
+            vec![Breakpoint::Step(7)]
+        );
+        assert!(parse_breakpoint("at:validator.ak", &source_map).is_err());
+        assert!(parse_breakpoint("notABuiltin", &source_map).is_err());
+        assert!(parse_breakpoint("mem", &source_map).is_err());
+    }
+
+    #[test]
+    fn test_at_matches_the_exact_line_of_the_file() {
+        let source_map = BTreeMap::from([
+            (1, "validators/vault.ak:2".to_string()),
+            (2, "validators/vault.ak:20".to_string()),
+            (3, "validators/vault.ak:2:14".to_string()),
+            (4, "lib/vault.ak:2".to_string()),
+            (5, "validators/my_vault.ak:2".to_string()),
+        ]);
+        let steps = |spec: &str| parse_breakpoint(spec, &source_map).map(|parsed| parsed.breakpoints);
+
+        assert_eq!(steps("at:vault.ak:20").unwrap(), vec![Breakpoint::Step(2)]);
+        assert_eq!(
+            steps("at:validators/vault.ak:2").unwrap(),
+            vec![Breakpoint::Step(1), Breakpoint::Step(3)]
+        );
+        assert_eq!(
+            steps("at:vault.ak:2").unwrap(),
+            vec![Breakpoint::Step(1), Breakpoint::Step(3), Breakpoint::Step(4)]
+        );
+        assert_eq!(
+            steps("at:/home/dev/project/lib/vault.ak:2").unwrap(),
+            vec![Breakpoint::Step(4)]
+        );
+        assert!(steps("at:vault.ak:200").is_err());
+        assert!(steps("at:ault.ak:2").is_err());
+    }
+
+    #[test]
+    fn test_parse_conditional_breakpoints() {
+        let source_map = BTreeMap::new();
+
+        let parsed = parse_breakpoint("unConstrData if hits == 3", &source_map).unwrap();
+        assert_eq!(parsed.breakpoints, vec![Breakpoint::Builtin(DefaultFunction::UnConstrData)]);
+        assert_eq!(parsed.condition.unwrap().to_string(), "hits == 3");
+
+        let error = parse_breakpoint("equalsInteger if arg[0] ==", &source_map).unwrap_err();
+        assert!(error.to_string().contains("1:"));
+    }
+}
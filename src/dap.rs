@@ -0,0 +1,645 @@
+//! Debug Adapter Protocol server over stdio.
+//! Lets VS Code or any other DAP client drive a `DebugSession`.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context as _, Result, anyhow};
+use pallas_primitives::conway::Language;
+use serde_json::{Value, json};
+use uplc::machine::debug::{BreakpointHit, DebugSession, StepCost};
+
+use crate::{
+    analyzer,
+    breakpoints::{self, Condition},
+    data_view,
+    executor::{self, MachineConfig},
+    loader,
+    provenance,
+};
+
+const THREAD_ID: i64 = 1;
+const ENVIRONMENT_SCOPE: i64 = 1;
+const MACHINE_SCOPE: i64 = 2;
+/// Variables that unfold into data have references from here on; see
+/// `Server::data_nodes`.
+const FIRST_DATA_REFERENCE: i64 = 1000;
+const MAX_NAME_WIDTH: usize = 120;
+
+/// Which DAP request a group of session breakpoints came from, so that each
+/// `set*Breakpoints` request only replaces its own kind.
+#[derive(Clone, Copy, PartialEq)]
+enum BreakpointOrigin {
+    Source,
+    Function,
+    Exception,
+}
+
+/// A breakpoint as the client set it. The spec and request are kept so it
+/// can be applied to a session launched after it was set.
+struct DapBreakpoint {
+    id: i64,
+    origin: BreakpointOrigin,
+    spec: String,
+    requested: Value,
+    session_ids: Vec<usize>,
+}
+
+struct Server<W: Write> {
+    output: W,
+    seq: i64,
+    session: Option<DebugSession>,
+    source_map: BTreeMap<u64, String>,
+    breakpoints: Vec<DapBreakpoint>,
+    next_breakpoint_id: i64,
+    stop_on_entry: bool,
+    emitted_traces: usize,
+    /// Data shown by the variables of the current stop. The variable that
+    /// unfolds into `data_nodes[i]` has reference `FIRST_DATA_REFERENCE + i`.
+    data_nodes: Vec<data_view::Node>,
+    /// The reference given to the variable at each position of a scope or
+    /// variable, so that listing it again during a stop reuses them.
+    data_references: BTreeMap<(i64, usize), i64>,
+}
+
+/// Serve DAP requests read from `input` until the client disconnects.
+pub async fn run<R: BufRead, W: Write>(mut input: R, output: W) -> Result<()> {
+    let mut server = Server {
+        output,
+        seq: 0,
+        session: None,
+        source_map: BTreeMap::new(),
+        breakpoints: vec![],
+        next_breakpoint_id: 1,
+        stop_on_entry: true,
+        emitted_traces: 0,
+        data_nodes: vec![],
+        data_references: BTreeMap::new(),
+    };
+
+    while let Some(request) = read_message(&mut input)? {
+        let command = request["command"].as_str().unwrap_or_default().to_string();
+        let arguments = request.get("arguments").cloned().unwrap_or(Value::Null);
+
+        let result = server.handle(&command, &arguments).await;
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match &result {
+            Ok(body) => response["body"] = body.clone(),
+            Err(e) => response["message"] = json!(e.to_string()),
+        }
+        server.send(response)?;
+
+        if result.is_ok() {
+            if let Err(e) = server.after(&command) {
+                server.event("output", json!({ "category": "stderr", "output": format!("{}\n", e) }))?;
+            }
+        }
+
+        if command == "disconnect" {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read one `Content-Length` framed message, `None` at end of input.
+pub fn read_message<R: BufRead>(input: &mut R) -> Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().context("invalid Content-Length")?);
+        }
+    }
+
+    let length = length.ok_or_else(|| anyhow!("missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Write one `Content-Length` framed message.
+pub fn write_message<W: Write>(output: &mut W, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn session(&mut self) -> Result<&mut DebugSession> {
+        self.session.as_mut().ok_or_else(|| anyhow!("no program launched"))
+    }
+
+    async fn handle(&mut self, command: &str, arguments: &Value) -> Result<Value> {
+        match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsStepBack": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsConditionalBreakpoints": true,
+                "supportsHitConditionalBreakpoints": true,
+                "exceptionBreakpointFilters": [
+                    { "filter": "error", "label": "Error term", "default": false },
+                    { "filter": "trace", "label": "Trace emitted", "default": false },
+                ],
+            })),
+            "launch" => self.launch(arguments).await,
+            "setBreakpoints" => self.set_source_breakpoints(arguments),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(arguments),
+            "setExceptionBreakpoints" => self.set_exception_breakpoints(arguments),
+            "configurationDone" | "pause" | "disconnect" => Ok(json!({})),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CEK machine" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Environment", "variablesReference": ENVIRONMENT_SCOPE, "expensive": false },
+                    { "name": "Machine", "variablesReference": MACHINE_SCOPE, "expensive": false },
+                ],
+            })),
+            "variables" => self.variables(arguments),
+            "next" | "stepIn" => {
+                self.session()?.step();
+                Ok(json!({}))
+            }
+            "stepOut" => {
+                let session = self.session()?;
+                let depth = session.context_depth();
+                while session.step() && session.context_depth() >= depth {}
+                Ok(json!({}))
+            }
+            "stepBack" => {
+                self.session()?.step_back();
+                Ok(json!({}))
+            }
+            "continue" | "reverseContinue" => Ok(json!({ "allThreadsContinued": true })),
+            other => Err(anyhow!("unsupported request '{}'", other)),
+        }
+    }
+
+    /// Events that must follow the response of `command`.
+    fn after(&mut self, command: &str) -> Result<()> {
+        match command {
+            "initialize" => self.event("initialized", json!({})),
+            "launch" => self.apply_pending_breakpoints(),
+            "configurationDone" if self.stop_on_entry => self.stopped("entry", None),
+            "configurationDone" | "continue" => {
+                let hit = self.session()?.continue_forward();
+                self.stopped_after_run(hit)
+            }
+            "reverseContinue" => {
+                let hit = self.session()?.continue_backward();
+                self.stopped_after_run(hit)
+            }
+            "next" | "stepIn" | "stepOut" | "stepBack" => self.stopped("step", None),
+            "disconnect" => self.event("terminated", json!({})),
+            _ => Ok(()),
+        }
+    }
+
+    fn stopped_after_run(&mut self, hit: Option<BreakpointHit>) -> Result<()> {
+        match hit {
+            Some(hit) => {
+                let ids: Vec<i64> = self
+                    .breakpoints
+                    .iter()
+                    .filter(|b| b.session_ids.contains(&hit.id))
+                    .map(|b| b.id)
+                    .collect();
+                self.stopped("breakpoint", Some(ids))
+            }
+            None => self.stopped("step", None),
+        }
+    }
+
+    fn stopped(&mut self, reason: &str, hit_breakpoint_ids: Option<Vec<i64>>) -> Result<()> {
+        self.flush_traces()?;
+        self.data_nodes.clear();
+        self.data_references.clear();
+
+        let session = self.session()?;
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+
+        if let Some(ids) = hit_breakpoint_ids {
+            body["hitBreakpointIds"] = json!(ids);
+        }
+
+        match session.outcome() {
+            Some(Ok(term)) => {
+                body["description"] = json!("Evaluation finished");
+                let result = format!("Result: {}\n", term);
+                self.event("output", json!({ "category": "console", "output": result }))?;
+            }
+            Some(Err(e)) => {
+                body["reason"] = json!("exception");
+                body["description"] = json!("Evaluation failed");
+                body["text"] = match analyzer::analyze(session) {
+                    Some(cause) => json!(format!("{}\nRoot cause: {}", e, cause)),
+                    None => json!(e.to_string()),
+                };
+            }
+            None => {}
+        }
+
+        self.event("stopped", body)
+    }
+
+    /// Send traces emitted since the last stop as output events.
+    fn flush_traces(&mut self) -> Result<()> {
+        let emitted = self.emitted_traces;
+        let session = self.session()?;
+        let traces: Vec<String> = session
+            .traces()
+            .iter()
+            .skip(emitted)
+            .map(|trace| format!("{}\n", trace))
+            .collect();
+        let total = session.traces().len();
+
+        for trace in traces {
+            self.event("output", json!({ "category": "stdout", "output": trace }))?;
+        }
+        self.emitted_traces = total;
+
+        Ok(())
+    }
+
+    async fn launch(&mut self, arguments: &Value) -> Result<Value> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or_else(|| anyhow!("launch requires a 'program' path"))?;
+        let params: Vec<String> = arguments["params"]
+            .as_array()
+            .map(|params| {
+                params.iter()
+                    .filter_map(|p| p.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(true);
+
+        let validator = arguments["validator"].as_str();
+
+        let selected = loader::load_program(&PathBuf::from(path), validator, &params).await?;
+        let program = loader::apply_arguments(selected.program, selected.arguments)?;
+
+        self.source_map = program.source_map.clone();
+        let roots = provenance::argument_roots(params.len());
+        let mut session = executor::start_session_with(program.program, &machine_config(arguments, selected.language)?)?;
+        if arguments["provenance"].as_bool().unwrap_or(false) {
+            session.track_provenance(roots);
+        }
+        self.session = Some(session);
+        self.emitted_traces = 0;
+        self.data_nodes.clear();
+        self.data_references.clear();
+
+        Ok(json!({}))
+    }
+
+    fn set_source_breakpoints(&mut self, arguments: &Value) -> Result<Value> {
+        self.clear_breakpoints(BreakpointOrigin::Source)?;
+
+        // The full path: `at:` matches it against the source map by suffix.
+        let file = arguments["source"]["path"].as_str().unwrap_or_default();
+
+        let mut result = vec![];
+        for requested in arguments["breakpoints"].as_array().cloned().unwrap_or_default() {
+            let line = requested["line"].as_i64().unwrap_or_default();
+            let spec = format!("at:{}:{}", file, line);
+            result.push(self.add_breakpoint(BreakpointOrigin::Source, &spec, &requested));
+        }
+
+        Ok(json!({ "breakpoints": result }))
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Value) -> Result<Value> {
+        self.clear_breakpoints(BreakpointOrigin::Function)?;
+
+        let mut result = vec![];
+        for requested in arguments["breakpoints"].as_array().cloned().unwrap_or_default() {
+            let name = requested["name"].as_str().unwrap_or_default().to_string();
+            result.push(self.add_breakpoint(BreakpointOrigin::Function, &name, &requested));
+        }
+
+        Ok(json!({ "breakpoints": result }))
+    }
+
+    fn set_exception_breakpoints(&mut self, arguments: &Value) -> Result<Value> {
+        self.clear_breakpoints(BreakpointOrigin::Exception)?;
+
+        let mut result = vec![];
+        for filter in arguments["filters"].as_array().cloned().unwrap_or_default() {
+            let filter = filter.as_str().unwrap_or_default().to_string();
+            result.push(self.add_breakpoint(BreakpointOrigin::Exception, &filter, &Value::Null));
+        }
+
+        Ok(json!({ "breakpoints": result }))
+    }
+
+    /// Register the breakpoints described by `spec` and the DAP `condition`
+    /// and `hitCondition` fields, returning the DAP `Breakpoint` body.
+    /// Before `launch` they stay pending and unverified.
+    fn add_breakpoint(&mut self, origin: BreakpointOrigin, spec: &str, requested: &Value) -> Value {
+        let mut breakpoint = DapBreakpoint {
+            id: self.next_breakpoint_id,
+            origin,
+            spec: spec.to_string(),
+            requested: requested.clone(),
+            session_ids: vec![],
+        };
+        self.next_breakpoint_id += 1;
+
+        let body = self.apply_breakpoint(&mut breakpoint);
+        self.breakpoints.push(breakpoint);
+
+        body
+    }
+
+    /// Add `breakpoint` to the launched session, returning its DAP
+    /// `Breakpoint` body.
+    fn apply_breakpoint(&mut self, breakpoint: &mut DapBreakpoint) -> Value {
+        let mut body = json!({ "id": breakpoint.id, "verified": false });
+        if breakpoint.origin == BreakpointOrigin::Source {
+            body["line"] = breakpoint.requested["line"].clone();
+        }
+
+        let parsed = breakpoint_condition(&breakpoint.requested).and_then(|condition| {
+            let mut parsed = breakpoints::parse_breakpoint(&breakpoint.spec, &self.source_map)?;
+            parsed.condition = match (parsed.condition, condition) {
+                (Some(a), Some(b)) => Some(Condition::parse(&format!("({}) && ({})", a, b))?),
+                (a, b) => a.or(b),
+            };
+            Ok(parsed)
+        });
+
+        let (parsed, session) = match (parsed, self.session.as_mut()) {
+            (Ok(parsed), Some(session)) => (parsed, session),
+            (Err(e), _) => {
+                body["message"] = json!(e.to_string());
+                return body;
+            }
+            (_, None) => {
+                body["message"] = json!("pending until the program is launched");
+                return body;
+            }
+        };
+
+        breakpoint.session_ids = parsed
+            .breakpoints
+            .into_iter()
+            .map(|b| session.add_conditional_breakpoint(b, parsed.condition.clone()))
+            .collect();
+        body["verified"] = json!(true);
+
+        body
+    }
+
+    /// Apply every breakpoint set so far to the session just launched, which
+    /// is the usual order: clients send breakpoints once `initialized`
+    /// arrives, often before `launch` has finished.
+    fn apply_pending_breakpoints(&mut self) -> Result<()> {
+        let mut breakpoints = std::mem::take(&mut self.breakpoints);
+        let bodies: Vec<Value> = breakpoints.iter_mut().map(|b| self.apply_breakpoint(b)).collect();
+        self.breakpoints = breakpoints;
+
+        for body in bodies {
+            self.event("breakpoint", json!({ "reason": "changed", "breakpoint": body }))?;
+        }
+
+        Ok(())
+    }
+
+    fn clear_breakpoints(&mut self, origin: BreakpointOrigin) -> Result<()> {
+        let (removed, kept) = std::mem::take(&mut self.breakpoints)
+            .into_iter()
+            .partition(|b| b.origin == origin);
+        self.breakpoints = kept;
+
+        if let Some(session) = self.session.as_mut() {
+            for breakpoint in removed {
+                for id in breakpoint.session_ids {
+                    session.remove_breakpoint(id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stack_trace(&mut self) -> Result<Value> {
+        let step = self.session()?.current_step() as u64;
+        let location = self.source_map.get(&step).cloned();
+        let session = self.session()?;
+        let snapshot = session.snapshot();
+
+        let mut frames = vec![json!({
+            "id": 0,
+            "name": truncate(&format!("{} {}", snapshot.state_type, snapshot.term)),
+            "line": 0,
+            "column": 0,
+        })];
+
+        if let Some((path, line)) = location.as_deref().and_then(breakpoints::file_and_line) {
+            frames[0]["source"] = json!({ "path": path });
+            frames[0]["line"] = json!(line);
+        }
+
+        for (i, frame) in session.continuation().into_iter().enumerate() {
+            frames.push(json!({
+                "id": i + 1,
+                "name": truncate(&frame.to_string()),
+                "line": 0,
+                "column": 0,
+            }));
+        }
+
+        let total = frames.len();
+        Ok(json!({ "stackFrames": frames, "totalFrames": total }))
+    }
+
+    fn variables(&mut self, arguments: &Value) -> Result<Value> {
+        let reference = arguments["variablesReference"].as_i64().unwrap_or_default();
+        let session = self.session()?;
+
+        let variables: Vec<Value> = match reference {
+            ENVIRONMENT_SCOPE => {
+                let network = data_view::network(session.script_context());
+                let view = session.view();
+                let bindings: Vec<_> = data_view::environment(session, &view)
+                    .into_iter()
+                    .map(|(name, text, value)| (name, text, data_view::tree(&view, value, network)))
+                    .collect();
+
+                bindings
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (name, text, tree))| match tree {
+                        Some(node) => self.data_variable((reference, i), &name, node),
+                        None => variable(&name, &text),
+                    })
+                    .collect()
+            }
+            MACHINE_SCOPE => {
+                let budget = session.ex_budget();
+                let spent = session.spent();
+                let snapshot = session.snapshot();
+                vec![
+                    variable("step", &session.current_step().to_string()),
+                    variable("state", &snapshot.state_type),
+                    variable("cpu remaining", &budget.cpu.to_string()),
+                    variable("mem remaining", &budget.mem.to_string()),
+                    variable("cpu spent", &spent.cpu.to_string()),
+                    variable("mem spent", &spent.mem.to_string()),
+                    variable("step cost", &step_cost(&snapshot.cost)),
+                    variable("traces", &session.traces().len().to_string()),
+                ]
+            }
+            reference => {
+                let node = usize::try_from(reference - FIRST_DATA_REFERENCE)
+                    .ok()
+                    .and_then(|i| self.data_nodes.get(i))
+                    .cloned();
+
+                node.map(|node| {
+                    node.children
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, child)| self.data_variable((reference, i), &child.label.clone(), child))
+                        .collect()
+                })
+                .unwrap_or_default()
+            }
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// A variable showing `node`, which unfolds into its children. `position`
+    /// is the reference it is listed under and its index there.
+    fn data_variable(&mut self, position: (i64, usize), name: &str, node: data_view::Node) -> Value {
+        let reference = match self.data_references.get(&position) {
+            _ if node.children.is_empty() => 0,
+            Some(reference) => *reference,
+            None => {
+                self.data_nodes.push(node.clone());
+                let reference = FIRST_DATA_REFERENCE + self.data_nodes.len() as i64 - 1;
+                self.data_references.insert(position, reference);
+                reference
+            }
+        };
+
+        json!({ "name": name, "value": node.summary, "variablesReference": reference })
+    }
+}
+
+fn step_cost(cost: &StepCost) -> String {
+    match &cost.charge {
+        Some(charge) => format!("cpu {}, mem {} ({})", cost.cpu, cost.mem, charge),
+        None => "free".to_string(),
+    }
+}
+
+/// The `language`, `costModel` (a protocol-parameters JSON file), `cpuBudget`
+/// and `memBudget` launch arguments.
+/// `language` is what the program's file declares, if anything.
+fn machine_config(arguments: &Value, language: Option<Language>) -> Result<MachineConfig> {
+    let language = match arguments["language"].as_str() {
+        Some(language) => executor::parse_language(language)?,
+        None => language.unwrap_or(MachineConfig::default().language),
+    };
+
+    let mut budget = MachineConfig::default().budget;
+    if let Some(cpu) = arguments["cpuBudget"].as_i64() {
+        budget.cpu = cpu;
+    }
+    if let Some(mem) = arguments["memBudget"].as_i64() {
+        budget.mem = mem;
+    }
+
+    let config = MachineConfig::new(language.clone()).with_budget(budget);
+    match arguments["costModel"].as_str() {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("could not read cost model {}", path))?;
+            config.with_cost_model(executor::parse_cost_model(&json, &language)?)
+        }
+        None => Ok(config),
+    }
+}
+
+/// Turn the DAP `condition` and `hitCondition` fields into one condition.
+fn breakpoint_condition(requested: &Value) -> Result<Option<Condition>> {
+    let condition = requested["condition"].as_str().filter(|c| !c.trim().is_empty());
+    let hit_condition = requested["hitCondition"]
+        .as_str()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            if c.starts_with(|ch: char| ch.is_ascii_digit()) {
+                format!("hits == {}", c)
+            } else {
+                format!("hits {}", c)
+            }
+        });
+
+    let source = match (condition, hit_condition) {
+        (Some(c), Some(h)) => format!("({}) && ({})", c, h),
+        (Some(c), None) => c.to_string(),
+        (None, Some(h)) => h,
+        (None, None) => return Ok(None),
+    };
+
+    Condition::parse(&source)
+        .map(Some)
+        .map_err(|e| anyhow!("invalid condition '{}': {}", source, e))
+}
+
+fn variable(name: &str, value: &str) -> Value {
+    json!({ "name": name, "value": value, "variablesReference": 0 })
+}
+
+fn truncate(text: &str) -> String {
+    let single_line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if single_line.chars().count() > MAX_NAME_WIDTH {
+        let cut: String = single_line.chars().take(MAX_NAME_WIDTH).collect();
+        format!("{}…", cut)
+    } else {
+        single_line
+    }
+}
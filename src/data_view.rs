@@ -0,0 +1,480 @@
+//! Data as a tree rather than as `(con data ...)` text.
+//!
+//! Constructors, maps and lists become nodes that a front end can fold and
+//! unfold. Where data is of a shape [`crate::schema`] knows, such as a script
+//! context, fields are named, addresses read as bech32 and POSIX times as
+//! dates.
+
+use pallas_addresses::{Network, Pointer, ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart};
+use uplc::{
+    ast::Constant,
+    machine::{
+        debug::{DebugSession, MachineView, Origin, ScriptContextSnapshot, StateView},
+        value::{from_pallas_bigint, Value},
+    },
+    Hash, PlutusData,
+};
+
+use crate::schema::{self, Shape};
+
+/// One node of a data tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    /// Field name, `[index]` of a list element or `[key]` of a map entry;
+    /// empty for the root.
+    pub label: String,
+    /// The node on one line: its constructor, its size or its value.
+    pub summary: String,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn leaf(summary: String) -> Node {
+        Node {
+            label: String::new(),
+            summary,
+            children: vec![],
+        }
+    }
+
+    fn labelled(mut self, label: String) -> Node {
+        self.label = label;
+        self
+    }
+
+    /// The tree as indented text, unfolded `depth` levels deep. Folded
+    /// nodes end in `▸`.
+    pub fn render(&self, depth: usize) -> String {
+        let mut text = String::new();
+        self.write(&mut text, 0, depth);
+        text
+    }
+
+    fn write(&self, text: &mut String, indent: usize, depth: usize) {
+        text.push_str(&"  ".repeat(indent));
+        if !self.label.is_empty() {
+            text.push_str(&self.label);
+            text.push_str(": ");
+        }
+        text.push_str(&self.summary);
+        if depth == 0 && !self.children.is_empty() {
+            text.push_str(" ▸");
+        }
+        text.push('\n');
+
+        if depth > 0 {
+            for child in &self.children {
+                child.write(text, indent + 1, depth - 1);
+            }
+        }
+    }
+
+    /// The node `selector` leads to from this one, following labels, e.g.
+    /// `tx_info.outputs[0].value`.
+    pub fn find(&self, selector: &str) -> Option<&Node> {
+        selector_labels(selector).iter().try_fold(self, |node, label| {
+            node.children.iter().find(|child| &child.label == label)
+        })
+    }
+}
+
+/// `data` as a tree, with the fields of `shape` named and addresses written
+/// for `network`.
+pub fn decode(data: &PlutusData, shape: Shape, network: Network) -> Node {
+    match data {
+        PlutusData::Constr(constr) => {
+            let index = schema::constructor_index(constr);
+            let named = index.and_then(|index| shape.constructor(index, constr.fields.len()));
+
+            let children = constr
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match named.and_then(|(_, fields)| fields.get(i)) {
+                    Some((name, shape)) if !name.is_empty() => {
+                        decode(field, *shape, network).labelled(name.to_string())
+                    }
+                    Some((_, shape)) => decode(field, *shape, network).labelled(format!("[{}]", i)),
+                    None => decode(field, Shape::Unknown, network).labelled(format!("[{}]", i)),
+                })
+                .collect();
+
+            let summary = match (shape, named, index) {
+                (Shape::Address, Some(_), _) => {
+                    address(&constr.fields, network).unwrap_or_else(|| "Address".to_string())
+                }
+                (_, Some((name, _)), _) => name.to_string(),
+                (_, None, Some(index)) => format!("Constr {}", index),
+                (_, None, None) => "Constr".to_string(),
+            };
+
+            Node {
+                label: String::new(),
+                summary,
+                children,
+            }
+        }
+        PlutusData::Map(entries) if shape == Shape::Lovelace => match entries.first() {
+            Some((_, amount)) => decode(amount, Shape::Integer, network),
+            None => Node::leaf("0".to_string()),
+        },
+        PlutusData::Map(entries) => {
+            let key_shape = shape.entry().0;
+            let children: Vec<Node> = entries
+                .iter()
+                .enumerate()
+                .map(|(i, (key, value))| {
+                    let node = decode(value, shape.entry_value(key), network);
+                    match schema::key_label(shape, key) {
+                        // Labels name children, so `.lovelace` is `lovelace`.
+                        Some(label) => node.labelled(label.trim_start_matches('.').to_string()),
+                        None => Node {
+                            label: format!("[{}]", i),
+                            summary: "entry".to_string(),
+                            children: vec![
+                                decode(key, key_shape, network).labelled("key".to_string()),
+                                node.labelled("value".to_string()),
+                            ],
+                        },
+                    }
+                })
+                .collect();
+
+            let summary = match shape {
+                Shape::Value => value_summary(&children),
+                _ => count(children.len(), "entry", "entries"),
+            };
+
+            Node {
+                label: String::new(),
+                summary,
+                children,
+            }
+        }
+        PlutusData::Array(items) => Node {
+            label: String::new(),
+            summary: count(items.len(), "item", "items"),
+            children: items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| decode(item, shape.element(), network).labelled(format!("[{}]", i)))
+                .collect(),
+        },
+        PlutusData::BigInt(n) => {
+            let n = from_pallas_bigint(n);
+            match (shape, i64::try_from(&n)) {
+                (Shape::PosixTime, Ok(ms)) => Node::leaf(format!("{} ({})", date(ms), n)),
+                _ => Node::leaf(n.to_string()),
+            }
+        }
+        PlutusData::BoundedBytes(bytes) => {
+            let hex = format!("#{}", hex::encode(bytes.as_slice()));
+            match (shape, schema::printable(bytes)) {
+                (Shape::Unknown, Some(text)) if !text.is_empty() => Node::leaf(format!("{} {:?}", hex, text)),
+                _ => Node::leaf(hex),
+            }
+        }
+    }
+}
+
+/// `value` as a tree, if it is or holds data.
+///
+/// When the session tracks provenance, a value taken from one of the
+/// program's arguments is decoded where it sits in that argument, so that
+/// the fields of a script context are named however deep the value is.
+pub fn tree(view: &MachineView, value: &Value, network: Network) -> Option<Node> {
+    let Value::Con(constant) = value else {
+        return None;
+    };
+
+    view.origin(value)
+        .and_then(|origin| located(view, origin, network))
+        .or_else(|| constant_tree(constant, network))
+}
+
+/// The environment of the current step, innermost first: the name of each
+/// binding, its value as text and the value itself.
+pub fn environment<'a>(session: &DebugSession, view: &MachineView<'a>) -> Vec<(String, String, &'a Value)> {
+    let StateView::Compute { env, .. } = &view.state else {
+        return vec![];
+    };
+
+    session
+        .snapshot()
+        .environment
+        .into_iter()
+        .zip(env.iter().copied())
+        .map(|(binding, value)| match binding.split_once(" = ") {
+            Some((name, text)) => (name.to_string(), text.to_string(), value),
+            None => (String::new(), binding, value),
+        })
+        .collect()
+}
+
+/// The network of the transaction being debugged, judged from the addresses
+/// it involves, since addresses in data do not say; mainnet without one.
+pub fn network(context: Option<&ScriptContextSnapshot>) -> Network {
+    let testnet = context.is_some_and(|context| {
+        let inputs = context.inputs.iter().chain(&context.reference_inputs).map(|input| &input.address);
+        let outputs = context.outputs.iter().map(|output| &output.address);
+        inputs.chain(outputs).any(|address| address.starts_with("addr_test"))
+    });
+
+    if testnet {
+        Network::Testnet
+    } else {
+        Network::Mainnet
+    }
+}
+
+/// The data `origin` points to, decoded within its argument.
+fn located(view: &MachineView, origin: &Origin, network: Network) -> Option<Node> {
+    let root = match view.provenance_root(&origin.path().root)? {
+        Constant::Data(data) => data,
+        _ => return None,
+    };
+    let (data, shape) = schema::locate(origin.path(), Some(root))?;
+    let mut node = decode(data, shape, network);
+
+    // The rest of a list, or of the fields of a constructor, after `tailList`.
+    if let Origin::List { from, .. } = origin {
+        if *from > 0 {
+            node.children.drain(..(*from).min(node.children.len()));
+            node.summary = format!("{} [{}..]", node.summary, from);
+        }
+    }
+
+    Some(node)
+}
+
+fn constant_tree(constant: &Constant, network: Network) -> Option<Node> {
+    match constant {
+        Constant::Data(data) => Some(decode(data, schema::recognise(data), network)),
+        Constant::ProtoList(_, items) if !items.is_empty() => {
+            let children = items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| constant_tree(item, network).map(|node| node.labelled(format!("[{}]", i))))
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(Node {
+                label: String::new(),
+                summary: count(children.len(), "item", "items"),
+                children,
+            })
+        }
+        Constant::ProtoPair(_, _, first, second) => {
+            let (first_tree, second_tree) = (constant_tree(first, network), constant_tree(second, network));
+            if first_tree.is_none() && second_tree.is_none() {
+                return None;
+            }
+
+            let first = first_tree.unwrap_or_else(|| Node::leaf(first.to_pretty()));
+            let second = second_tree.unwrap_or_else(|| Node::leaf(second.to_pretty()));
+
+            Some(Node {
+                label: String::new(),
+                summary: "pair".to_string(),
+                children: vec![first.labelled("fst".to_string()), second.labelled("snd".to_string())],
+            })
+        }
+        _ => None,
+    }
+}
+
+fn value_summary(children: &[Node]) -> String {
+    let lovelace = children
+        .iter()
+        .find(|child| child.label == "lovelace")
+        .map(|child| child.summary.as_str())
+        .unwrap_or("0");
+    let assets: usize = children
+        .iter()
+        .filter(|child| child.label != "lovelace")
+        .map(|child| child.children.len())
+        .sum();
+
+    match assets {
+        0 => format!("{} lovelace", lovelace),
+        _ => format!("{} lovelace + {}", lovelace, count(assets, "asset", "assets")),
+    }
+}
+
+fn count(n: usize, one: &str, many: &str) -> String {
+    format!("{} {}", n, if n == 1 { one } else { many })
+}
+
+/// The bech32 form of an address with `fields`, a payment credential and an
+/// optional staking credential, on `network`.
+fn address(fields: &[PlutusData], network: Network) -> Option<String> {
+    let [payment, stake] = fields else {
+        return None;
+    };
+
+    let payment = match credential(payment)? {
+        (0, hash) => ShelleyPaymentPart::key_hash(hash),
+        (_, hash) => ShelleyPaymentPart::script_hash(hash),
+    };
+
+    let delegation = match constructor(stake)? {
+        (1, []) => ShelleyDelegationPart::Null,
+        (0, [staking]) => match constructor(staking)? {
+            (0, [inline]) => match credential(inline)? {
+                (0, hash) => ShelleyDelegationPart::key_hash(hash),
+                (_, hash) => ShelleyDelegationPart::script_hash(hash),
+            },
+            (1, [slot, transaction, certificate]) => ShelleyDelegationPart::Pointer(Pointer::new(
+                natural(slot)?,
+                natural(transaction)?,
+                natural(certificate)?,
+            )),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    ShelleyAddress::new(network, payment, delegation).to_bech32().ok()
+}
+
+fn constructor(data: &PlutusData) -> Option<(u64, &[PlutusData])> {
+    match data {
+        PlutusData::Constr(constr) => Some((schema::constructor_index(constr)?, &constr.fields[..])),
+        _ => None,
+    }
+}
+
+/// A key (0) or script (1) credential and its hash.
+fn credential(data: &PlutusData) -> Option<(u64, Hash<28>)> {
+    match constructor(data)? {
+        (index @ (0 | 1), [PlutusData::BoundedBytes(hash)]) => {
+            Some((index, <[u8; 28]>::try_from(hash.as_slice()).ok()?.into()))
+        }
+        _ => None,
+    }
+}
+
+fn natural(data: &PlutusData) -> Option<u64> {
+    match data {
+        PlutusData::BigInt(n) => u64::try_from(&from_pallas_bigint(n)).ok(),
+        _ => None,
+    }
+}
+
+/// `ms` milliseconds after the Unix epoch as an ISO 8601 date in UTC.
+fn date(ms: i64) -> String {
+    let seconds = ms.div_euclid(1000);
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // The civil calendar from a day count, after Howard Hinnant's
+    // `civil_from_days`: years run from March so that leap days come last.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    let mut date = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+    if ms.rem_euclid(1000) != 0 {
+        date.push_str(&format!(".{:03}", ms.rem_euclid(1000)));
+    }
+    date.push('Z');
+    date
+}
+
+/// The labels a selector such as `outputs[0].value` goes through.
+fn selector_labels(selector: &str) -> Vec<String> {
+    let mut labels = vec![];
+    let mut label = String::new();
+    let mut in_brackets = false;
+
+    for c in selector.chars() {
+        match c {
+            '.' if !in_brackets => labels.push(std::mem::take(&mut label)),
+            '[' if !in_brackets => {
+                labels.push(std::mem::take(&mut label));
+                label.push(c);
+                in_brackets = true;
+            }
+            ']' if in_brackets => {
+                label.push(c);
+                labels.push(std::mem::take(&mut label));
+                in_brackets = false;
+            }
+            c => label.push(c),
+        }
+    }
+    labels.push(label);
+
+    labels.into_iter().filter(|label| !label.trim().is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(code: &str) -> PlutusData {
+        crate::parameters::parse(&format!("(con data ({}))", code)).unwrap()
+    }
+
+    #[test]
+    fn test_dates_are_calendar_dates() {
+        assert_eq!(date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(date(1_714_564_800_000), "2024-05-01T12:00:00Z");
+        assert_eq!(date(951_782_400_123), "2000-02-29T00:00:00.123Z");
+        assert_eq!(date(-1_500), "1969-12-31T23:59:58.500Z");
+    }
+
+    #[test]
+    fn test_script_context_is_decoded_with_names() {
+        let key = "00".repeat(28);
+        let output = format!(
+            "Constr 0 [Constr 0 [Constr 0 [B #{}], Constr 1 []], Map [(B #, Map [(B #, I 5)]), (B #cafe, Map [(B #544f4b, I 2)])], Constr 0 [], Constr 1 []]",
+            key
+        );
+        let range = "Constr 0 [Constr 0 [Constr 1 [I 1714564800000], Constr 1 []], Constr 0 [Constr 2 [], Constr 1 []]]";
+        let ctx = data(&format!(
+            "Constr 0 [Constr 0 [List [], List [], List [{}], Map [], Map [], List [], Map [], {}, List [], Map [], Map [], Constr 0 [B #00]], Constr 0 [B #00]]",
+            output, range
+        ));
+
+        let tree = decode(&ctx, schema::recognise(&ctx), Network::Testnet);
+        assert_eq!(tree.summary, "ScriptContext");
+
+        let output = tree.find("tx_info.outputs[0]").unwrap();
+        assert_eq!(output.summary, "TxOut");
+        assert!(output.find("address").unwrap().summary.starts_with("addr_test1"));
+        assert_eq!(output.find("value").unwrap().summary, "5 lovelace + 1 asset");
+        assert_eq!(output.find("value.lovelace").unwrap().summary, "5");
+        assert_eq!(output.find("value[#cafe][\"TOK\"]").unwrap().summary, "2");
+        assert_eq!(output.find("datum").unwrap().summary, "NoOutputDatum");
+
+        let lower = tree.find("tx_info.valid_range.lower_bound.bound_type.time").unwrap();
+        assert_eq!(lower.summary, "2024-05-01T12:00:00Z (1714564800000)");
+
+        assert_eq!(
+            tree.find("tx_info.valid_range").unwrap().render(1),
+            "valid_range: Interval\n  lower_bound: Bound ▸\n  upper_bound: Bound ▸\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_data_keeps_positions() {
+        let tree = decode(&data("Constr 2 [I 1, B #6869, List [Map [(Constr 0 [], I 3)]]]"), Shape::Unknown, Network::Mainnet);
+        assert_eq!(tree.summary, "Constr 2");
+        assert_eq!(tree.find("[1]").unwrap().summary, "#6869 \"hi\"");
+        assert_eq!(tree.find("[2][0][0].key").unwrap().summary, "Constr 0");
+        assert_eq!(tree.find("[2][0][0].value").unwrap().summary, "3");
+    }
+}
@@ -1,10 +1,30 @@
-/// Human-readable diagnostic messages for validators
-/// Translates CEK machine states into plain English debugging insights
-
-use crate::frames::Frame;
+//! Human-readable diagnostic messages for validators
+//! Translates CEK machine states into plain English debugging insights
+//!
+//! Diagnostics come from [`Rule`]s that inspect the machine itself through a
+//! [`MachineView`]: which builtin is about to run and with which values, the
+//! term being computed, the pending frames and the budget. [`Engine::default`]
+//! holds the built-in rules; teams add their own with [`Engine::add`].
+
+use std::cmp::Reverse;
+
+use uplc::{
+    ast::{Constant, Term},
+    builtins::DefaultFunction,
+    machine::{
+        debug::{render_value, MachineView, StateView},
+        value::Value,
+        Error,
+    },
+    PlutusData,
+};
+
+use crate::provenance;
 
 #[derive(Debug, Clone)]
 pub struct Diagnostic {
+    /// Name of the rule that produced it.
+    pub rule: &'static str,
     pub title: String,
     pub status: DiagnosticStatus,
     pub explanation: String,
@@ -20,7 +40,7 @@ pub enum DiagnosticStatus {
     Success,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Severity {
     Debug,    // Information only
     Hint,     // Helpful suggestion
@@ -52,256 +72,493 @@ impl DiagnosticStatus {
     }
 }
 
-pub fn analyze_frame(frame: &Frame, _previous_frame: Option<&Frame>) -> Option<Diagnostic> {
-    let term_lower = frame.term.to_lowercase();
+/// A check over one step of the machine.
+pub trait Rule {
+    /// Unique name, used to remove the rule and to tag its diagnostics.
+    fn name(&self) -> &'static str;
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic>;
+}
+
+/// An ordered set of rules run against each step.
+pub struct Engine {
+    rules: Vec<Box<dyn Rule>>,
+}
 
-    // Check for error states
-    if term_lower.contains("error") {
-        return Some(diagnose_error(&frame.term));
+impl Default for Engine {
+    /// The built-in rules.
+    fn default() -> Self {
+        Engine::new()
+            .with(MachineFailure)
+            .with(ErrorTerm)
+            .with(DivisionByZero)
+            .with(EmptyListAccess)
+            .with(DataShape)
+            .with(Comparison)
+            .with(Branch)
+            .with(LowBudget)
+            .with(NewTraces)
+            .with(Finished)
     }
+}
 
-    // Check for missing signatures
-    if term_lower.contains("unConstrData") || term_lower.contains("signature") {
-        return Some(Diagnostic {
-            title: "Checking Signature Validation".to_string(),
-            status: DiagnosticStatus::Info,
-            explanation: "The validator is checking if data has been properly signed. Make sure:\n  • Redeemer is correctly signed with the validator's key\n  • Datum contains the expected signature".to_string(),
-            next_steps: vec![
-                "Verify you're using the correct signing key".to_string(),
-                "Check that the transaction signature matches the validator expectations".to_string(),
-                "Ensure datum contains all required signature fields".to_string(),
-            ],
-            severity: Severity::Hint,
-        });
+impl Engine {
+    /// An engine without any rules.
+    pub fn new() -> Self {
+        Engine { rules: vec![] }
     }
 
-    // Check for signature comparison
-    if term_lower.contains("equalsByteString") {
-        return Some(Diagnostic {
-            title: "Comparing Signatures or Data".to_string(),
-            status: DiagnosticStatus::Info,
-            explanation: "The validator is comparing byte strings - likely checking:\n  • Cryptographic signatures match\n  • Public keys are valid\n  • Hashes are identical".to_string(),
-            next_steps: vec![
-                "If this fails, signatures don't match".to_string(),
-                "Check that both signatures use the same key".to_string(),
-                "Verify no data was modified between signing and validation".to_string(),
-            ],
-            severity: Severity::Warning,
-        });
+    pub fn with(mut self, rule: impl Rule + 'static) -> Self {
+        self.add(rule);
+        self
     }
 
-    // Check for redeemer validation
-    if term_lower.contains("unMapData") || term_lower.contains("unConstrData") {
-        return Some(Diagnostic {
-            title: "Processing Validator Input".to_string(),
-            status: DiagnosticStatus::Info,
-            explanation: "The validator is unpacking the redeemer or datum structure. This is where:\n  • Redeemer data is extracted from the transaction\n  • Datum constraints are read\n  • Parameters are prepared for validation logic".to_string(),
-            next_steps: vec![
-                "Ensure redeemer structure matches what validator expects".to_string(),
-                "Check that datum contains all required fields".to_string(),
-                "Verify data types match (integers, bytes, etc.)".to_string(),
-            ],
-            severity: Severity::Hint,
-        });
+    /// Add `rule`, replacing any rule of the same name.
+    pub fn add(&mut self, rule: impl Rule + 'static) {
+        self.remove(rule.name());
+        self.rules.push(Box::new(rule));
     }
 
-    // Check for boolean decisions
-    if term_lower.contains("ifThenElse") {
-        return Some(Diagnostic {
-            title: "Validation Decision Point".to_string(),
-            status: DiagnosticStatus::Info,
-            explanation: "The validator is making a critical decision:\n  • If the condition is TRUE → ✓ Validation PASSES\n  • If the condition is FALSE → ✗ Validation FAILS\n\nThis is where your validator logic decides accept or reject.".to_string(),
-            next_steps: vec![
-                "Check if the condition matches your expectations".to_string(),
-                "If it fails, your inputs don't satisfy the validator's rules".to_string(),
-                "Review the logic before this decision".to_string(),
-            ],
-            severity: Severity::Warning,
-        });
+    /// Remove the rule called `name`, returning whether there was one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|rule| rule.name() != name);
+        self.rules.len() != before
     }
 
-    // Check for force/delay (lazy evaluation)
-    if term_lower.contains("force") || term_lower.contains("delay") {
-        return Some(Diagnostic {
-            title: "Lazy Evaluation".to_string(),
-            status: DiagnosticStatus::Info,
-            explanation: "The validator is using lazy evaluation. Think of it like:\n  • DELAY = 'wait to calculate this'\n  • FORCE = 'calculate it now'\n\nThis helps validators avoid unnecessary computation.".to_string(),
-            next_steps: vec![
-                "No action needed - this is normal validator behavior".to_string(),
-                "If stuck here, the validator may have circular dependencies".to_string(),
-            ],
-            severity: Severity::Debug,
-        });
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
     }
 
-    // Check for computations
-    if term_lower.contains("builtin") {
-        if term_lower.contains("addInteger") || term_lower.contains("subtractInteger") {
-            return Some(Diagnostic {
-                title: "Performing Math Calculation".to_string(),
-                status: DiagnosticStatus::Info,
-                explanation: "The validator is performing arithmetic. Make sure:\n  • Input numbers are in the correct range\n  • No integer overflow\n  • Result is as expected".to_string(),
-                next_steps: vec![
-                    "Check your input values".to_string(),
-                    "Verify calculation matches your logic".to_string(),
+    /// Every diagnostic for the step `view` shows, most severe first.
+    pub fn analyze(&self, view: &MachineView) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self.rules.iter().filter_map(|rule| rule.check(view)).collect();
+        diagnostics.sort_by_key(|diagnostic| Reverse(diagnostic.severity));
+        diagnostics
+    }
+}
+
+/// The evaluation stopped with a machine error.
+pub struct MachineFailure;
+
+impl Rule for MachineFailure {
+    fn name(&self) -> &'static str {
+        "machine-error"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let StateView::Failed { error } = view.state else {
+            return None;
+        };
+
+        let (title, explanation, next_steps) = match error {
+            Error::EvaluationFailure => (
+                "VALIDATOR REJECTION".to_string(),
+                format!("The validator evaluated (error).{}", last_trace(view)),
+                vec![
+                    "Step back to the check that chose the (error) branch".to_string(),
+                    "Compare its inputs with what the validator expects".to_string(),
+                ],
+            ),
+            Error::OutOfExError(remaining) => (
+                "OUT OF BUDGET".to_string(),
+                format!(
+                    "The validator needed more than the {} CPU and {} MEM it was given (short by {} CPU, {} MEM).",
+                    view.initial().cpu,
+                    view.initial().mem,
+                    (-remaining.cpu).max(0),
+                    (-remaining.mem).max(0),
+                ),
+                vec![
+                    "Raise it with --cpu-budget / --mem-budget if the work is expected".to_string(),
+                    "Otherwise set a cpu:N breakpoint to find where the budget goes".to_string(),
                 ],
-                severity: Severity::Debug,
-            });
+            ),
+            Error::TypeMismatch(expected, got) => (
+                "TYPE MISMATCH".to_string(),
+                format!("A builtin expected a {} but was given a {}.{}", expected, got, unwrap_hint(&expected.to_string(), &got.to_string())),
+                vec!["Step back to the builtin call to see the offending argument".to_string()],
+            ),
+            Error::DeserialisationError(builtin, value) => (
+                "WRONG DATA SHAPE".to_string(),
+                format!("{} cannot unpack {}.", builtin, render_value(value)),
+                vec!["Check that the datum or redeemer matches the validator's type".to_string()],
+            ),
+            error => (
+                "MACHINE ERROR".to_string(),
+                error.to_string(),
+                vec!["Step back to see the state that led to the error".to_string()],
+            ),
+        };
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title,
+            status: DiagnosticStatus::Error,
+            explanation,
+            next_steps,
+            severity: Severity::Critical,
+        })
+    }
+}
+
+/// The machine is about to compute `(error)`.
+pub struct ErrorTerm;
+
+impl Rule for ErrorTerm {
+    fn name(&self) -> &'static str {
+        "error-term"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let StateView::Compute { term: Term::Error, .. } = view.state else {
+            return None;
+        };
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: "Reached (error)".to_string(),
+            status: DiagnosticStatus::Error,
+            explanation: format!("The next step fails the evaluation.{}", last_trace(view)),
+            next_steps: vec!["Step back to the branch that led here".to_string()],
+            severity: Severity::Error,
+        })
+    }
+}
+
+/// An integer division whose divisor is zero.
+pub struct DivisionByZero;
+
+impl Rule for DivisionByZero {
+    fn name(&self) -> &'static str {
+        "division-by-zero"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let (fun, args) = view.saturating.as_ref()?;
+
+        if !matches!(
+            fun,
+            DefaultFunction::DivideInteger
+                | DefaultFunction::QuotientInteger
+                | DefaultFunction::RemainderInteger
+                | DefaultFunction::ModInteger
+        ) {
+            return None;
         }
 
-        if term_lower.contains("appendByteString") || term_lower.contains("sliceByteString") {
-            return Some(Diagnostic {
-                title: "Processing Byte Data".to_string(),
-                status: DiagnosticStatus::Info,
-                explanation: "The validator is manipulating byte strings:\n  • Concatenating data\n  • Slicing/extracting portions\n  • Building composite data structures".to_string(),
-                next_steps: vec![
-                    "Verify byte string lengths are correct".to_string(),
-                    "Check slice indices are within bounds".to_string(),
-                ],
-                severity: Severity::Debug,
-            });
+        match constant(args.get(1)?)? {
+            Constant::Integer(divisor) if *divisor == 0.into() => Some(Diagnostic {
+                rule: self.name(),
+                title: "Division by zero".to_string(),
+                status: DiagnosticStatus::Error,
+                explanation: format!("{} is about to divide {} by zero.", fun, render_value(args[0])),
+                next_steps: vec!["Check where the divisor comes from".to_string()],
+                severity: Severity::Error,
+            }),
+            _ => None,
         }
     }
+}
 
-    // Check for lambda (function) application
-    if term_lower.contains("lam") {
-        return Some(Diagnostic {
-            title: "Executing Validator Function".to_string(),
-            status: DiagnosticStatus::Info,
-            explanation: "A validator function is being executed with parameters. The validator is:\n  • Receiving transaction data\n  • Processing your redeemer\n  • Applying validation rules".to_string(),
-            next_steps: vec![
-                "Continue stepping to see validation logic".to_string(),
-                "Watch for decision points (ifThenElse)".to_string(),
-            ],
-            severity: Severity::Debug,
-        });
-    }
-
-    // Check for constants (data literals)
-    if term_lower.contains("con") {
-        if term_lower.contains("unit") {
-            return Some(Diagnostic {
-                title: "Unit Value (Placeholder)".to_string(),
-                status: DiagnosticStatus::Info,
-                explanation: "Unit is like a placeholder or 'nothing' value in Plutus. Used when:\n  • A function returns nothing\n  • Placeholder in tuples\n  • Void/null equivalent".to_string(),
-                next_steps: vec!["This is normal - continue execution".to_string()],
-                severity: Severity::Debug,
-            });
+/// `headList` or `tailList` of an empty list.
+pub struct EmptyListAccess;
+
+impl Rule for EmptyListAccess {
+    fn name(&self) -> &'static str {
+        "empty-list"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let (fun, args) = view.saturating.as_ref()?;
+
+        if !matches!(fun, DefaultFunction::HeadList | DefaultFunction::TailList) {
+            return None;
+        }
+
+        match constant(args.first()?)? {
+            Constant::ProtoList(_, items) if items.is_empty() => Some(Diagnostic {
+                rule: self.name(),
+                title: "Empty list".to_string(),
+                status: DiagnosticStatus::Error,
+                explanation: format!("{} is about to be applied to an empty list.", fun),
+                next_steps: vec![
+                    "Check that the list (inputs, outputs, fields...) has the elements the validator expects".to_string(),
+                ],
+                severity: Severity::Error,
+            }),
+            _ => None,
         }
     }
+}
 
-    // Final success
-    if frame.step > 0 && term_lower.contains("con") && !term_lower.contains("error") {
-        return Some(Diagnostic {
-            title: "Execution Complete".to_string(),
-            status: DiagnosticStatus::Success,
-            explanation: "The validator has finished executing and produced a result:\n  • ✓ Validation logic completed\n  • ✓ All checks passed\n  • ✓ Result generated successfully".to_string(),
-            next_steps: vec![
-                "Validator accepted the transaction".to_string(),
-                "Check result value above".to_string(),
-            ],
-            severity: Severity::Debug,
-        });
+/// An `un*Data` builtin applied to data of another shape.
+pub struct DataShape;
+
+impl Rule for DataShape {
+    fn name(&self) -> &'static str {
+        "data-shape"
     }
 
-    None
-}
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let (fun, args) = view.saturating.as_ref()?;
 
-fn diagnose_error(term: &str) -> Diagnostic {
-    let term_lower = term.to_lowercase();
+        let expected = match fun {
+            DefaultFunction::UnConstrData => "Constr",
+            DefaultFunction::UnMapData => "Map",
+            DefaultFunction::UnListData => "List",
+            DefaultFunction::UnIData => "I",
+            DefaultFunction::UnBData => "B",
+            _ => return None,
+        };
 
-    if term_lower.contains("signature") {
-        return Diagnostic {
-            title: "❌ SIGNATURE VALIDATION FAILED".to_string(),
-            status: DiagnosticStatus::Error,
-            explanation: "The validator rejected because:\n  ✗ Signature doesn't match validator expectations\n  ✗ Signing key is incorrect\n  ✗ Data was modified after signing".to_string(),
-            next_steps: vec![
-                "1️⃣ Check you're using the correct key pair".to_string(),
-                "2️⃣ Verify the signature matches the validator's public key".to_string(),
-                "3️⃣ Ensure no data changed between signing and submission".to_string(),
-                "4️⃣ Re-sign the transaction with the correct key".to_string(),
-            ],
-            severity: Severity::Critical,
+        let Constant::Data(data) = constant(args.first()?)? else {
+            return None;
         };
-    }
 
-    if term_lower.contains("redeemer") {
-        return Diagnostic {
-            title: "❌ INVALID REDEEMER".to_string(),
+        let got = data_shape(data);
+        if got == expected {
+            return None;
+        }
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: "Wrong data shape".to_string(),
             status: DiagnosticStatus::Error,
-            explanation: "The validator rejected because:\n  ✗ Redeemer format is incorrect\n  ✗ Redeemer doesn't contain required fields\n  ✗ Redeemer values don't match validator expectations".to_string(),
+            explanation: format!(
+                "{} expects {} data but is given {}:\n  {}",
+                fun,
+                expected,
+                got,
+                render_value(args[0])
+            ),
             next_steps: vec![
-                "1️⃣ Check redeemer structure matches validator requirements".to_string(),
-                "2️⃣ Verify all required fields are present".to_string(),
-                "3️⃣ Check data types (integers, bytes, etc.)".to_string(),
-                "4️⃣ Use correct redeemer for this endpoint".to_string(),
+                "Check that the datum or redeemer matches the validator's type".to_string(),
+                "Constructor fields are numbered from 0".to_string(),
             ],
-            severity: Severity::Critical,
+            severity: Severity::Error,
+        })
+    }
+}
+
+/// An equality or ordering check, with both sides and its outcome.
+pub struct Comparison;
+
+impl Rule for Comparison {
+    fn name(&self) -> &'static str {
+        "comparison"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let (fun, args) = view.saturating.as_ref()?;
+        let (left, right) = (constant(args.first()?)?, constant(args.get(1)?)?);
+
+        let (operator, holds) = match (fun, left, right) {
+            (DefaultFunction::EqualsInteger, Constant::Integer(a), Constant::Integer(b)) => ("==", a == b),
+            (DefaultFunction::LessThanInteger, Constant::Integer(a), Constant::Integer(b)) => ("<", a < b),
+            (DefaultFunction::LessThanEqualsInteger, Constant::Integer(a), Constant::Integer(b)) => ("<=", a <= b),
+            (DefaultFunction::EqualsByteString, Constant::ByteString(a), Constant::ByteString(b)) => ("==", a == b),
+            (DefaultFunction::LessThanByteString, Constant::ByteString(a), Constant::ByteString(b)) => ("<", a < b),
+            (DefaultFunction::LessThanEqualsByteString, Constant::ByteString(a), Constant::ByteString(b)) => {
+                ("<=", a <= b)
+            }
+            (DefaultFunction::EqualsString, Constant::String(a), Constant::String(b)) => ("==", a == b),
+            (DefaultFunction::EqualsData, Constant::Data(a), Constant::Data(b)) => ("==", a == b),
+            _ => return None,
         };
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: format!("Comparison {}", if holds { "holds" } else { "fails" }),
+            status: if holds { DiagnosticStatus::Info } else { DiagnosticStatus::Warning },
+            explanation: format!(
+                "{}:\n  {}\n  {}\n  {}\nis {}.",
+                fun,
+                side(view, args[0]),
+                operator,
+                side(view, args[1]),
+                holds
+            ),
+            next_steps: if holds {
+                vec![]
+            } else {
+                vec!["If the validator requires this, one side is not what it expects".to_string()]
+            },
+            severity: if holds { Severity::Debug } else { Severity::Warning },
+        })
     }
+}
 
-    if term_lower.contains("datum") {
-        return Diagnostic {
-            title: "❌ INVALID DATUM".to_string(),
-            status: DiagnosticStatus::Error,
-            explanation: "The validator rejected because:\n  ✗ Datum is missing required fields\n  ✗ Datum data doesn't match expectations\n  ✗ Datum wasn't signed correctly".to_string(),
-            next_steps: vec![
-                "1️⃣ Check datum contains all required data".to_string(),
-                "2️⃣ Verify datum structure matches UTXO specification".to_string(),
-                "3️⃣ Ensure datum is properly encoded in the UTXO".to_string(),
-                "4️⃣ Check transaction includes this UTXO".to_string(),
-            ],
-            severity: Severity::Critical,
+/// One side of a comparison, with where it comes from when that is known.
+fn side(view: &MachineView, value: &Value) -> String {
+    provenance::annotate(&render_value(value), provenance::describe(view, value).as_deref())
+}
+
+/// `ifThenElse` choosing a branch.
+pub struct Branch;
+
+impl Rule for Branch {
+    fn name(&self) -> &'static str {
+        "branch"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let (DefaultFunction::IfThenElse, args) = view.saturating.as_ref()? else {
+            return None;
         };
+
+        let Constant::Bool(condition) = constant(args.first()?)? else {
+            return None;
+        };
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: "Validation Decision Point".to_string(),
+            status: DiagnosticStatus::Info,
+            explanation: format!(
+                "The condition is {}, so ifThenElse takes the {} branch:\n  {}",
+                condition,
+                if *condition { "then" } else { "else" },
+                render_value(args[if *condition { 1 } else { 2 }])
+            ),
+            next_steps: vec![],
+            severity: Severity::Hint,
+        })
     }
+}
 
-    if term_lower.contains("equalsByteString") {
-        return Diagnostic {
-            title: "❌ DATA COMPARISON FAILED".to_string(),
-            status: DiagnosticStatus::Error,
-            explanation: "The validator rejected because:\n  ✗ Two pieces of data don't match\n  ✗ Usually: expected value ≠ actual value\n  ✗ Could be: signature, hash, or constraint mismatch".to_string(),
-            next_steps: vec![
-                "1️⃣ Compare the expected vs actual values".to_string(),
-                "2️⃣ Check for typos in keys or addresses".to_string(),
-                "3️⃣ Verify cryptographic hashes match".to_string(),
-                "4️⃣ Review transaction data".to_string(),
-            ],
-            severity: Severity::Critical,
+/// Less than a tenth of the budget is left.
+pub struct LowBudget;
+
+impl Rule for LowBudget {
+    fn name(&self) -> &'static str {
+        "budget"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        if matches!(view.state, StateView::Failed { .. }) {
+            return None;
+        }
+
+        let initial = view.initial();
+        let low = |remaining: i64, initial: i64| remaining < initial / 10;
+
+        let resource = match (low(view.remaining.cpu, initial.cpu), low(view.remaining.mem, initial.mem)) {
+            (true, true) => "CPU and MEM",
+            (true, false) => "CPU",
+            (false, true) => "MEM",
+            (false, false) => return None,
         };
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: "Budget nearly spent".to_string(),
+            status: DiagnosticStatus::Warning,
+            explanation: format!(
+                "Less than 10% of the {} budget is left ({} CPU, {} MEM remaining).",
+                resource, view.remaining.cpu, view.remaining.mem
+            ),
+            next_steps: vec!["Raise it with --cpu-budget / --mem-budget if the work is expected".to_string()],
+            severity: Severity::Warning,
+        })
     }
+}
 
-    if term_lower.contains("equalsInteger") {
-        return Diagnostic {
-            title: "❌ VALIDATION CHECK FAILED".to_string(),
-            status: DiagnosticStatus::Error,
-            explanation: "The validator rejected because:\n  ✗ Two numbers don't match\n  ✗ Usually: transaction amount, count, or ID mismatch\n  ✗ Constraint violation: expected X, got Y".to_string(),
-            next_steps: vec![
-                "1️⃣ Check transaction amounts/counts".to_string(),
-                "2️⃣ Verify input values match validator expectations".to_string(),
-                "3️⃣ Review numeric constraints".to_string(),
-            ],
-            severity: Severity::Critical,
+/// Traces emitted by the previous step.
+pub struct NewTraces;
+
+impl Rule for NewTraces {
+    fn name(&self) -> &'static str {
+        "trace"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let emitted = view.emitted();
+        if emitted.is_empty() {
+            return None;
+        }
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: "Trace".to_string(),
+            status: DiagnosticStatus::Info,
+            explanation: emitted.iter().map(|trace| format!("  {}", trace)).collect::<Vec<_>>().join("\n"),
+            next_steps: vec![],
+            severity: Severity::Hint,
+        })
+    }
+}
+
+/// The evaluation finished.
+pub struct Finished;
+
+impl Rule for Finished {
+    fn name(&self) -> &'static str {
+        "result"
+    }
+
+    fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+        let StateView::Done { term } = view.state else {
+            return None;
         };
+
+        Some(Diagnostic {
+            rule: self.name(),
+            title: "Execution Complete".to_string(),
+            status: DiagnosticStatus::Success,
+            explanation: format!(
+                "The validator finished with {} after {} steps, spending {} CPU and {} MEM.",
+                term.to_pretty(),
+                view.step,
+                view.spent.cpu,
+                view.spent.mem
+            ),
+            next_steps: vec![],
+            severity: Severity::Debug,
+        })
+    }
+}
+
+fn constant(value: &Value) -> Option<&Constant> {
+    match value {
+        Value::Con(constant) => Some(constant),
+        _ => None,
     }
+}
 
-    // Generic error
-    Diagnostic {
-        title: "❌ VALIDATOR REJECTION".to_string(),
-        status: DiagnosticStatus::Error,
-        explanation: format!("The validator rejected the transaction.\n\nError term: {}", term),
-        next_steps: vec![
-            "1️⃣ Review the error term above".to_string(),
-            "2️⃣ Check all redeemer and datum fields".to_string(),
-            "3️⃣ Verify cryptographic signatures".to_string(),
-            "4️⃣ Ensure transaction data matches validator rules".to_string(),
-        ],
-        severity: Severity::Critical,
+fn data_shape(data: &PlutusData) -> &'static str {
+    match data {
+        PlutusData::Constr(_) => "Constr",
+        PlutusData::Map(_) => "Map",
+        PlutusData::Array(_) => "List",
+        PlutusData::BigInt(_) => "I",
+        PlutusData::BoundedBytes(_) => "B",
     }
 }
 
+fn last_trace(view: &MachineView) -> String {
+    view.traces
+        .last()
+        .map(|trace| format!("\nLast trace before it: {}", trace))
+        .unwrap_or_default()
+}
+
+/// A builtin given data where it expects what the data holds usually misses
+/// the matching `un*Data`.
+fn unwrap_hint(expected: &str, got: &str) -> String {
+    let unwrap = match (expected, got) {
+        ("bytestring", "data") => "unBData",
+        ("integer", "data") => "unIData",
+        (_, "data") => "the matching un*Data",
+        _ => return String::new(),
+    };
+
+    format!("\nThe value is still wrapped as data: unwrap it with {} first.", unwrap)
+}
+
 pub fn print_diagnostic(diag: &Diagnostic) {
     println!("\n{}", "═".repeat(80));
-    println!("{} {}  [{}]", 
+    println!("{} {}  [{}]",
         diag.severity.symbol(),
         diag.title,
         match diag.status {
@@ -328,22 +585,120 @@ pub fn print_diagnostic(diag: &Diagnostic) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use uplc::machine::debug::DebugSession;
+
+    fn session(code: &str) -> DebugSession {
+        crate::executor::start_session(uplc::parser::program(code).unwrap().try_into().unwrap())
+    }
+
+    /// Diagnostics from `rule` on every step up to the end.
+    fn fired(code: &str, rule: &str) -> Vec<Diagnostic> {
+        let engine = Engine::default();
+        let mut session = session(code);
+        let mut found = vec![];
+
+        loop {
+            found.extend(engine.analyze(&session.view()).into_iter().filter(|d| d.rule == rule));
+            if !session.step() {
+                return found;
+            }
+        }
+    }
 
     #[test]
     fn test_error_detection() {
-        let frame = Frame {
-            step: 0,
-            state_type: "Error".to_string(),
-            term: "(error \"invalid signature\")".to_string(),
-            environment: vec![],
-            context_depth: 0,
-            cpu: 0,
-            mem: 0,
-            source_location: None,
-        };
+        let code = "(program 1.0.0 [(lam x [(force (builtin trace)) (con string \"bad signature\") (error)]) (con integer 1)])";
+
+        let failed = fired(code, "machine-error");
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, DiagnosticStatus::Error);
+        assert_eq!(failed[0].severity, Severity::Critical);
+
+        assert_eq!(fired(code, "error-term").len(), 1);
+        assert!(fired(code, "result").is_empty());
+    }
+
+    #[test]
+    fn test_rules_read_builtin_arguments() {
+        let shape = fired("(program 1.0.0 [(builtin unBData) (con data (I 42))])", "data-shape");
+        assert_eq!(shape.len(), 1);
+        assert!(shape[0].explanation.contains("unBData expects B data but is given I"));
+        assert!(fired("(program 1.0.0 [(builtin unIData) (con data (I 42))])", "data-shape").is_empty());
+
+        let comparison = fired(
+            "(program 1.0.0 [(builtin equalsInteger) (con integer 1) (con integer 2)])",
+            "comparison",
+        );
+        assert_eq!(comparison[0].title, "Comparison fails");
+        assert_eq!(comparison[0].severity, Severity::Warning);
+
+        let branch = fired(
+            "(program 1.0.0 [(force (builtin ifThenElse)) (con bool False) (con integer 1) (con integer 2)])",
+            "branch",
+        );
+        assert!(branch[0].explanation.contains("takes the else branch:\n  (con integer 2)"));
+
+        assert_eq!(fired("(program 1.0.0 [(builtin divideInteger) (con integer 1) (con integer 0)])", "division-by-zero").len(), 1);
+        assert_eq!(fired("(program 1.0.0 [(force (builtin headList)) (con (list integer) [])])", "empty-list").len(), 1);
+    }
+
+    #[test]
+    fn test_substrings_do_not_trigger_rules() {
+        // Mentions of "error", "lam" and "ifThenElse" in strings are just data.
+        let code = "(program 1.0.0 [(lam x (con string \"error lam ifThenElse\")) (con unit ())])";
+        let engine = Engine::default();
+        let mut session = session(code);
+        let mut rules = vec![];
+
+        loop {
+            rules.extend(engine.analyze(&session.view()).into_iter().map(|d| d.rule));
+            if !session.step() {
+                break;
+            }
+        }
+
+        assert_eq!(rules, vec!["result"]);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        struct Additions;
+
+        impl Rule for Additions {
+            fn name(&self) -> &'static str {
+                "additions"
+            }
+
+            fn check(&self, view: &MachineView) -> Option<Diagnostic> {
+                match view.saturating {
+                    Some((DefaultFunction::AddInteger, _)) => Some(Diagnostic {
+                        rule: self.name(),
+                        title: "Addition".to_string(),
+                        status: DiagnosticStatus::Info,
+                        explanation: String::new(),
+                        next_steps: vec![],
+                        severity: Severity::Hint,
+                    }),
+                    _ => None,
+                }
+            }
+        }
+
+        let mut engine = Engine::new().with(Additions).with(Finished);
+        assert_eq!(engine.rule_names(), vec!["additions", "result"]);
+
+        let mut session = session("(program 1.0.0 [(builtin addInteger) (con integer 1) (con integer 2)])");
+        let mut titles = vec![];
+        loop {
+            titles.extend(engine.analyze(&session.view()).into_iter().map(|d| d.title));
+            if !session.step() {
+                break;
+            }
+        }
+        assert_eq!(titles, vec!["Addition", "Execution Complete"]);
 
-        let diag = analyze_frame(&frame, None);
-        assert!(diag.is_some());
-        assert_eq!(diag.unwrap().status, DiagnosticStatus::Error);
+        assert!(engine.remove("additions"));
+        assert!(!engine.remove("additions"));
+        assert_eq!(engine.rule_names(), vec!["result"]);
     }
 }
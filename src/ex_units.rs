@@ -0,0 +1,446 @@
+//! Fitting the ExUnits of a transaction's redeemers to what their scripts
+//! actually spend, and pricing them with the protocol parameters.
+
+use std::{collections::HashMap, fmt::Display, io::Write, str::FromStr};
+
+use anyhow::{Context, Result, anyhow, bail};
+use pallas_codec::{
+    minicbor::{self, Decoder, Encoder},
+    utils::{MaybeIndefArray, NonEmptyKeyValuePairs},
+};
+use pallas_primitives::conway::{CostModels, ExUnits, Redeemer, Redeemers, RedeemersValue, ScriptHash};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uplc::{
+    machine::cost_model::ExBudget,
+    tx::{self, ResolvedInput, SlotConfig, script_context::PlutusScript},
+};
+
+use crate::transaction::{self, RedeemerPointer};
+
+/// Where redeemers live in a transaction witness set.
+const REDEEMERS_KEY: u64 = 5;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FittedTx {
+    /// The transaction with every redeemer's ExUnits replaced.
+    #[serde(with = "hex_bytes")]
+    pub cbor: Vec<u8>,
+    pub redeemers: Vec<FittedRedeemer>,
+    /// Whether the body commits to the old redeemers through
+    /// `script_data_hash`, which then has to be recomputed.
+    pub has_script_data_hash: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FittedRedeemer {
+    /// `<purpose>:<index>`, as `--redeemer` takes it.
+    pub redeemer: String,
+    pub declared: ExBudget,
+    pub evaluated: ExBudget,
+    /// `evaluated` plus the safety margin, as written to the transaction.
+    pub fitted: ExBudget,
+}
+
+impl FittedTx {
+    /// The ExUnits of all redeemers together, which the script fee is paid on.
+    pub fn total(&self) -> ExBudget {
+        self.redeemers.iter().fold(ExBudget { cpu: 0, mem: 0 }, |total, r| ExBudget {
+            cpu: total.cpu + r.fitted.cpu,
+            mem: total.mem + r.fitted.mem,
+        })
+    }
+
+    /// The fitted transaction and its redeemers, with the script fee when
+    /// `prices` are known.
+    pub fn write_json<W: Write>(&self, mut output: W, prices: Option<&ScriptPrices>) -> Result<()> {
+        let mut json = serde_json::to_value(self)?;
+        if let Some(prices) = prices {
+            json["script_fee"] = prices.fee(&self.total()).into();
+        }
+
+        serde_json::to_writer_pretty(&mut output, &json)?;
+        writeln!(output)?;
+        Ok(())
+    }
+
+    pub fn write_table<W: Write>(&self, mut output: W, prices: Option<&ScriptPrices>) -> Result<()> {
+        writeln!(
+            output,
+            "{:<12} {:>25} {:>25} {:>25}",
+            "REDEEMER", "DECLARED CPU / MEM", "EVALUATED CPU / MEM", "FITTED CPU / MEM"
+        )?;
+
+        let budget = |b: &ExBudget| format!("{} / {}", b.cpu, b.mem);
+
+        for r in &self.redeemers {
+            writeln!(
+                output,
+                "{:<12} {:>25} {:>25} {:>25}",
+                r.redeemer,
+                budget(&r.declared),
+                budget(&r.evaluated),
+                budget(&r.fitted)
+            )?;
+        }
+
+        let total = self.total();
+        writeln!(output, "\nTotal: CPU {} │ MEM {}", total.cpu, total.mem)?;
+
+        if let Some(prices) = prices {
+            writeln!(
+                output,
+                "Script fee: {} lovelace (memory at {}, steps at {})",
+                prices.fee(&total),
+                prices.memory,
+                prices.steps
+            )?;
+        }
+
+        if self.has_script_data_hash {
+            writeln!(
+                output,
+                "⚠ The body's script_data_hash still covers the old redeemers, recompute it when balancing"
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Evaluate every redeemer of the transaction in `tx_bytes` and write its
+/// ExUnits back, raised by `margin_percent` and rounded up.
+///
+/// Only the redeemers change: the body and the rest of the witness set are
+/// copied byte for byte, so an unbalanced transaction can go straight back
+/// to the builder.
+pub fn fit_ex_units(
+    tx_bytes: &[u8],
+    utxos: &[ResolvedInput],
+    slot_config: &SlotConfig,
+    cost_models: Option<&CostModels>,
+    budget: &ExBudget,
+    margin_percent: u64,
+) -> Result<FittedTx> {
+    fit_ex_units_with_overrides(tx_bytes, utxos, slot_config, cost_models, budget, margin_percent, &HashMap::new())
+}
+
+/// Like [`fit_ex_units`], with scripts replaced by hash. Phase one still
+/// checks the original scripts.
+pub fn fit_ex_units_with_overrides(
+    tx_bytes: &[u8],
+    utxos: &[ResolvedInput],
+    slot_config: &SlotConfig,
+    cost_models: Option<&CostModels>,
+    budget: &ExBudget,
+    margin_percent: u64,
+    overrides: &HashMap<ScriptHash, PlutusScript>,
+) -> Result<FittedTx> {
+    let tx = transaction::decode_tx(tx_bytes)?;
+
+    let Some(redeemers) = tx.transaction_witness_set.redeemer.as_ref() else {
+        bail!("the transaction has no redeemers, there are no ExUnits to fit");
+    };
+
+    let evaluated = tx::eval_phase_two_with_override(
+        &tx,
+        utxos,
+        cost_models,
+        Some(budget),
+        slot_config,
+        overrides.clone(),
+        true,
+        |_| (),
+    )
+    .map_err(|e| anyhow!("{}", e))?;
+
+    let fitted: Vec<FittedRedeemer> = transaction::redeemers(&tx)
+        .iter()
+        .zip(&evaluated)
+        .map(|(declared, (evaluated, _))| {
+            let evaluated = ExBudget {
+                cpu: evaluated.ex_units.steps as i64,
+                mem: evaluated.ex_units.mem as i64,
+            };
+
+            FittedRedeemer {
+                redeemer: RedeemerPointer::from(declared).to_string(),
+                declared: ExBudget {
+                    cpu: declared.ex_units.steps as i64,
+                    mem: declared.ex_units.mem as i64,
+                },
+                evaluated,
+                fitted: ExBudget {
+                    cpu: with_margin(evaluated.cpu, margin_percent),
+                    mem: with_margin(evaluated.mem, margin_percent),
+                },
+            }
+        })
+        .collect();
+
+    let ex_units = |i: usize| ExUnits {
+        mem: fitted[i].fitted.mem as u64,
+        steps: fitted[i].fitted.cpu as u64,
+    };
+
+    let patched = match (**redeemers).clone() {
+        Redeemers::List(list) => {
+            let patch = |list: Vec<_>| {
+                list.into_iter()
+                    .enumerate()
+                    .map(|(i, mut redeemer): (usize, Redeemer)| {
+                        redeemer.ex_units = ex_units(i);
+                        redeemer
+                    })
+                    .collect()
+            };
+
+            Redeemers::List(match list {
+                MaybeIndefArray::Def(list) => MaybeIndefArray::Def(patch(list)),
+                MaybeIndefArray::Indef(list) => MaybeIndefArray::Indef(patch(list)),
+            })
+        }
+        Redeemers::Map(map) => {
+            let patch = |map: Vec<(_, _)>| {
+                map.into_iter()
+                    .enumerate()
+                    .map(|(i, (key, mut value)): (usize, (_, RedeemersValue))| {
+                        value.ex_units = ex_units(i);
+                        (key, value)
+                    })
+                    .collect()
+            };
+
+            Redeemers::Map(match map {
+                NonEmptyKeyValuePairs::Def(map) => NonEmptyKeyValuePairs::Def(patch(map)),
+                NonEmptyKeyValuePairs::Indef(map) => NonEmptyKeyValuePairs::Indef(patch(map)),
+            })
+        }
+    };
+
+    let redeemers = minicbor::to_vec(&patched).map_err(|e| anyhow!("could not encode the redeemers: {}", e))?;
+
+    Ok(FittedTx {
+        cbor: replace_redeemers(tx_bytes, &redeemers)?,
+        redeemers: fitted,
+        has_script_data_hash: tx.transaction_body.script_data_hash.is_some(),
+    })
+}
+
+fn with_margin(units: i64, margin_percent: u64) -> i64 {
+    let margin = margin_percent as i128;
+    let units = units as i128;
+
+    (units + (units * margin + 99) / 100) as i64
+}
+
+/// Swap the redeemers of the witness set in `tx_bytes` for the encoded
+/// `redeemers`, leaving every other byte as it was.
+fn replace_redeemers(tx_bytes: &[u8], redeemers: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |e: minicbor::decode::Error| anyhow!("not a Conway transaction: {}", e);
+
+    let mut decoder = Decoder::new(tx_bytes);
+    decoder.array().map_err(invalid)?;
+    decoder.skip().map_err(invalid)?;
+    let start = decoder.position();
+    decoder.skip().map_err(invalid)?;
+    let end = decoder.position();
+
+    let mut entries = vec![];
+    let mut witness_set = Decoder::new(&tx_bytes[start..end]);
+    let len = witness_set.map().map_err(invalid)?;
+    loop {
+        let more = match len {
+            Some(len) => (entries.len() as u64) < len,
+            None => witness_set.datatype().map_err(invalid)? != minicbor::data::Type::Break,
+        };
+        if !more {
+            break;
+        }
+
+        let key = witness_set.u64().map_err(invalid)?;
+        let value_start = witness_set.position();
+        witness_set.skip().map_err(invalid)?;
+        let value = &tx_bytes[start + value_start..start + witness_set.position()];
+
+        entries.push((key, if key == REDEEMERS_KEY { redeemers } else { value }));
+    }
+
+    let mut encoder = Encoder::new(tx_bytes[..start].to_vec());
+    encoder.map(entries.len() as u64).map_err(|e| anyhow!("{}", e))?;
+    for (key, value) in entries {
+        encoder.u64(key).map_err(|e| anyhow!("{}", e))?;
+        encoder.writer_mut().extend_from_slice(value);
+    }
+
+    let mut patched = encoder.into_writer();
+    patched.extend_from_slice(&tx_bytes[end..]);
+    Ok(patched)
+}
+
+/// A non-negative rational price, as the protocol parameters give it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Price {
+    pub numerator: u128,
+    pub denominator: u128,
+}
+
+impl FromStr for Price {
+    type Err = anyhow::Error;
+
+    /// `577/10000`, `0.0577` or `5.77e-2`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid price '{}', expected a decimal or <numerator>/<denominator>", s);
+        let digits = |n: &str| -> Result<u128> {
+            if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            n.parse().map_err(|_| invalid())
+        };
+
+        if let Some((numerator, denominator)) = s.trim().split_once('/') {
+            let price = Price {
+                numerator: digits(numerator.trim())?,
+                denominator: digits(denominator.trim())?,
+            };
+            if price.denominator == 0 {
+                bail!("invalid price '{}', the denominator is zero", s);
+            }
+            return Ok(price);
+        }
+
+        let (mantissa, exponent) = match s.trim().split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().map_err(|_| invalid())?),
+            None => (s.trim(), 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let mut numerator = digits(&format!("{}{}", whole, fraction))?;
+        let mut denominator = 1u128;
+        let scale = exponent - fraction.len() as i32;
+        let power = 10u128
+            .checked_pow(scale.unsigned_abs())
+            .ok_or_else(invalid)?;
+        if scale < 0 {
+            denominator = power;
+        } else {
+            numerator = numerator.checked_mul(power).ok_or_else(invalid)?;
+        }
+
+        Ok(Price { numerator, denominator })
+    }
+}
+
+impl Display for Price {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// The lovelace price of a unit of memory and of a CPU step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScriptPrices {
+    pub memory: Price,
+    pub steps: Price,
+}
+
+impl ScriptPrices {
+    /// Reads the prices from protocol-parameter JSON, as `cardano-cli`
+    /// (`executionUnitPrices`), Blockfrost and Koios (`price_mem`,
+    /// `price_step`) or Ogmios (`scriptExecutionPrices`) write them.
+    pub fn from_protocol_params(json: &str) -> Result<Self> {
+        let params: Value = serde_json::from_str(json).context("protocol parameters are not valid JSON")?;
+
+        let (memory, steps) = params
+            .get("executionUnitPrices")
+            .and_then(|prices| pair(prices, "priceMemory", "priceSteps"))
+            .or_else(|| {
+                params
+                    .get("scriptExecutionPrices")
+                    .and_then(|prices| pair(prices, "memory", "cpu"))
+            })
+            .or_else(|| pair(&params, "price_mem", "price_step"))
+            .ok_or_else(|| {
+                anyhow!("no script prices in protocol parameters, expected executionUnitPrices, price_mem and price_step, or scriptExecutionPrices")
+            })?;
+
+        Ok(ScriptPrices {
+            memory: price_from_value(memory).context("invalid memory price")?,
+            steps: price_from_value(steps).context("invalid steps price")?,
+        })
+    }
+
+    /// What the ledger charges for `units`, rounded up to the next lovelace.
+    pub fn fee(&self, units: &ExBudget) -> u64 {
+        let (memory, steps) = (self.memory, self.steps);
+        let numerator = units.mem.max(0) as u128 * memory.numerator * steps.denominator
+            + units.cpu.max(0) as u128 * steps.numerator * memory.denominator;
+        let denominator = memory.denominator * steps.denominator;
+
+        numerator.div_ceil(denominator) as u64
+    }
+}
+
+fn pair<'a>(object: &'a Value, memory: &str, steps: &str) -> Option<(&'a Value, &'a Value)> {
+    Some((object.get(memory)?, object.get(steps)?))
+}
+
+fn price_from_value(value: &Value) -> Result<Price> {
+    match value {
+        Value::Number(n) => n.to_string().parse(),
+        Value::String(s) => s.parse(),
+        Value::Object(fields) => match (fields.get("numerator"), fields.get("denominator")) {
+            (Some(Value::Number(n)), Some(Value::Number(d))) => format!("{}/{}", n, d).parse(),
+            _ => bail!("expected numerator and denominator, got {}", value),
+        },
+        _ => bail!("expected a number, a string or a rational, got {}", value),
+    }
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        hex::decode(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prices() {
+        let price = |s: &str| s.parse::<Price>().unwrap();
+        assert_eq!(price("577/10000"), Price { numerator: 577, denominator: 10000 });
+        assert_eq!(price("0.0577"), Price { numerator: 577, denominator: 10000 });
+        assert_eq!(price("7.21e-5"), Price { numerator: 721, denominator: 10000000 });
+        assert_eq!(price("2e2"), Price { numerator: 200, denominator: 1 });
+        assert!("1/0".parse::<Price>().is_err());
+        assert!("cheap".parse::<Price>().is_err());
+
+        let cli = r#"{"executionUnitPrices": {"priceMemory": 0.0577, "priceSteps": 0.0000721}}"#;
+        let blockfrost = r#"{"price_mem": 0.0577, "price_step": 7.21e-5}"#;
+        let ogmios = r#"{"scriptExecutionPrices": {"memory": "577/10000", "cpu": "721/10000000"}}"#;
+
+        let prices = ScriptPrices::from_protocol_params(cli).unwrap();
+        assert_eq!(ScriptPrices::from_protocol_params(blockfrost).unwrap(), prices);
+        assert_eq!(ScriptPrices::from_protocol_params(ogmios).unwrap(), prices);
+        assert!(ScriptPrices::from_protocol_params("{}").is_err());
+
+        // 0.0577 * 1_000_000 + 0.0000721 * 500_000_000 = 57_700 + 36_050
+        assert_eq!(prices.fee(&ExBudget { mem: 1_000_000, cpu: 500_000_000 }), 93_750);
+        assert_eq!(prices.fee(&ExBudget { mem: 1, cpu: 0 }), 1);
+    }
+
+    #[test]
+    fn test_margin_rounds_up() {
+        assert_eq!(with_margin(1000, 0), 1000);
+        assert_eq!(with_margin(1000, 10), 1100);
+        assert_eq!(with_margin(1001, 10), 1102);
+    }
+}
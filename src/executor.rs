@@ -1,23 +1,252 @@
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
+use pallas_primitives::conway::{CostModels, Language};
+use serde_json::Value;
 use uplc::ast::Program;
 use uplc::machine::Machine;
-use uplc::machine::debug::StepSnapshot;
-use uplc::machine::cost_model::{CostModel, ExBudget};
+use uplc::machine::debug::{DebugSession, StepSnapshot};
+use uplc::machine::cost_model::{initialize_cost_model, CostModel, ExBudget};
 use uplc::ast::NamedDeBruijn;
 
 
+/// How the debugging machine is set up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachineConfig {
+    pub language: Language,
+    /// Protocol-parameter cost model values; the built-in model when `None`.
+    pub cost_model: Option<Vec<i64>>,
+    pub budget: ExBudget,
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        MachineConfig {
+            language: Language::PlutusV2,
+            cost_model: None,
+            budget: ExBudget::default(),
+        }
+    }
+}
+
+impl MachineConfig {
+    pub fn new(language: Language) -> Self {
+        MachineConfig {
+            language,
+            ..MachineConfig::default()
+        }
+    }
+
+    pub fn with_cost_model(mut self, values: Vec<i64>) -> Result<Self> {
+        check_cost_model(&self.language, &values)?;
+        self.cost_model = Some(values);
+        Ok(self)
+    }
+
+    pub fn with_budget(mut self, budget: ExBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// The cost model as phase two evaluation takes it, for this language
+    /// only; `None` for the built-in model.
+    pub fn cost_models(&self) -> Option<CostModels> {
+        let values = self.cost_model.clone()?;
+
+        Some(match self.language {
+            Language::PlutusV1 => CostModels { plutus_v1: Some(values), plutus_v2: None, plutus_v3: None },
+            Language::PlutusV2 => CostModels { plutus_v1: None, plutus_v2: Some(values), plutus_v3: None },
+            Language::PlutusV3 => CostModels { plutus_v1: None, plutus_v2: None, plutus_v3: Some(values) },
+        })
+    }
+
+    pub fn machine(&self) -> Result<Machine> {
+        let costs = match &self.cost_model {
+            Some(values) => {
+                check_cost_model(&self.language, values)?;
+                initialize_cost_model(&self.language, values)
+            }
+            None => CostModel::for_language(&self.language),
+        };
+
+        Ok(Machine::new(self.language.clone(), costs, self.budget, 1u32))
+    }
+}
+
+/// How many values `initialize_cost_model` reads for a language; longer
+/// arrays from newer protocol versions are accepted.
+pub fn cost_model_len(language: &Language) -> usize {
+    match language {
+        Language::PlutusV1 => 166,
+        Language::PlutusV2 => 175,
+        Language::PlutusV3 => 297,
+    }
+}
+
+fn check_cost_model(language: &Language, values: &[i64]) -> Result<()> {
+    let expected = cost_model_len(language);
+
+    if values.len() < expected {
+        bail!(
+            "{:?} cost model needs at least {} values, got {}",
+            language,
+            expected,
+            values.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Parses `v1`, `v2`, `v3` or `PlutusV1`, `PlutusV2`, `PlutusV3`.
+pub fn parse_language(name: &str) -> Result<Language> {
+    let lower = name.trim().to_lowercase();
+
+    match lower.strip_prefix("plutus").unwrap_or(&lower) {
+        "v1" | "1" => Ok(Language::PlutusV1),
+        "v2" | "2" => Ok(Language::PlutusV2),
+        "v3" | "3" => Ok(Language::PlutusV3),
+        _ => Err(anyhow!("unknown Plutus language '{}', expected v1, v2 or v3", name)),
+    }
+}
+
+/// Reads the cost model for `language` from protocol-parameter JSON.
+///
+/// Accepts a bare array of integers, an object keyed by language as in
+/// `costModels` (`{"PlutusV2": [...]}`), or whole protocol parameters
+/// holding such an object under `costModels` or `cost_models_raw`.
+pub fn parse_cost_model(json: &str, language: &Language) -> Result<Vec<i64>> {
+    let value: Value = serde_json::from_str(json).context("cost model is not valid JSON")?;
+
+    cost_model_from_value(&value, language)
+}
+
+/// Reads the cost model of every language protocol-parameter JSON has one
+/// for, as the ledger passes them to phase two evaluation. `None` when it
+/// has none at all.
+pub fn parse_cost_models(json: &str) -> Result<Option<CostModels>> {
+    let value: Value = serde_json::from_str(json).context("protocol parameters are not valid JSON")?;
+    if value.is_array() {
+        bail!("a bare cost model array does not say its language, expected costModels or full protocol parameters");
+    }
+
+    let model = |language: Language| -> Result<Option<Vec<i64>>> {
+        match cost_model_from_value(&value, &language) {
+            Ok(values) => check_cost_model(&language, &values).map(|_| Some(values)),
+            Err(_) => Ok(None),
+        }
+    };
+
+    let cost_models = CostModels {
+        plutus_v1: model(Language::PlutusV1)?,
+        plutus_v2: model(Language::PlutusV2)?,
+        plutus_v3: model(Language::PlutusV3)?,
+    };
+
+    if cost_models.plutus_v1.is_none() && cost_models.plutus_v2.is_none() && cost_models.plutus_v3.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(cost_models))
+}
+
+fn cost_model_from_value(value: &Value, language: &Language) -> Result<Vec<i64>> {
+    let version = match language {
+        Language::PlutusV1 => 1,
+        Language::PlutusV2 => 2,
+        Language::PlutusV3 => 3,
+    };
+
+    match value {
+        Value::Array(values) => values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                v.as_i64()
+                    .ok_or_else(|| anyhow!("cost model value {} is not an integer: {}", i, v))
+            })
+            .collect(),
+        Value::Object(fields) => {
+            for key in ["costModels", "cost_models_raw"] {
+                if let Some(models) = fields.get(key) {
+                    return cost_model_from_value(models, language);
+                }
+            }
+
+            let keys = [
+                format!("PlutusV{}", version),
+                format!("PlutusScriptV{}", version),
+                format!("plutus:v{}", version),
+            ];
+
+            let model = keys
+                .iter()
+                .find_map(|key| fields.get(key))
+                .ok_or_else(|| anyhow!("no cost model for {:?} in protocol parameters", language))?;
+
+            if !model.is_array() {
+                bail!("the {:?} cost model must be an array of integers", language);
+            }
+
+            cost_model_from_value(model, language)
+        }
+        _ => Err(anyhow!("expected a cost model array or protocol parameters object")),
+    }
+}
+
+/// Execute the whole program, rendering every step up front.
 pub fn execute_program(program: Program<NamedDeBruijn>) -> Result<Vec<StepSnapshot>> {
-    let language = pallas_primitives::conway::Language::PlutusV2;
-    let mut machine = Machine::new(
-        language,
-        CostModel::default(),
-        ExBudget::default(),
-        1u32,
-    );
+    execute_program_with(program, &MachineConfig::default())
+}
+
+pub fn execute_program_with(program: Program<NamedDeBruijn>, config: &MachineConfig) -> Result<Vec<StepSnapshot>> {
+    let mut machine = config.machine()?;
 
     let (_final_term, snapshots) = machine
         .run_debug(program.term)
         .map_err(|e| anyhow::anyhow!("Failed to execute UPLC program: {}", e))?;
 
     Ok(snapshots)
-}
\ No newline at end of file
+}
+
+/// Start a lazy session; steps are only evaluated and rendered on demand.
+pub fn start_session(program: Program<NamedDeBruijn>) -> DebugSession {
+    MachineConfig::default()
+        .machine()
+        .expect("the default machine configuration is valid")
+        .debug_session(program.term)
+}
+
+pub fn start_session_with(program: Program<NamedDeBruijn>, config: &MachineConfig) -> Result<DebugSession> {
+    Ok(config.machine()?.debug_session(program.term))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cost_model() {
+        let values: Vec<i64> = (0..175).collect();
+        let array = serde_json::to_string(&values).unwrap();
+
+        assert_eq!(parse_cost_model(&array, &Language::PlutusV2).unwrap(), values);
+
+        let params = format!("{{\"costModels\": {{\"PlutusV1\": [1], \"PlutusV2\": {}}}}}", array);
+        assert_eq!(parse_cost_model(&params, &Language::PlutusV2).unwrap(), values);
+        assert!(parse_cost_model(&params, &Language::PlutusV3).is_err());
+
+        // The one-value PlutusV1 model is too short to run anything with.
+        assert!(parse_cost_models(&params).is_err());
+        let params = format!("{{\"costModels\": {{\"PlutusV2\": {}}}}}", array);
+        let cost_models = parse_cost_models(&params).unwrap().unwrap();
+        assert_eq!(cost_models.plutus_v2, Some(values.clone()));
+        assert_eq!(cost_models.plutus_v1, None);
+        assert!(parse_cost_models(&array).is_err());
+        assert_eq!(parse_cost_models("{\"price_mem\": 0.0577}").unwrap(), None);
+
+        assert!(MachineConfig::new(Language::PlutusV1).with_cost_model(vec![1]).is_err());
+        assert!(MachineConfig::new(Language::PlutusV2).with_cost_model(values).is_ok());
+
+        assert_eq!(parse_language("PlutusV3").unwrap(), Language::PlutusV3);
+        assert!(parse_language("v4").is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! Batch execution traces: the JSON and NDJSON formats written by
+//! `--format`, and the reader that loads them back.
+
+use std::{
+    io::{BufRead, Write},
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use uplc::machine::{
+    cost_model::ExBudget,
+    debug::{ContinuationFrame, DebugSession, ScriptContextSnapshot, StepSnapshot},
+};
+
+/// Version of the trace schema. Bump it whenever a field of
+/// [`ExecutionTrace`] or [`StepSnapshot`] changes meaning or is removed;
+/// adding optional fields keeps the version.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON document holding the summary and every step.
+    Json,
+    /// A header line, one line per step, then a summary line.
+    Ndjson,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            other => Err(anyhow!("unknown format '{}', expected json or ndjson", other)),
+        }
+    }
+}
+
+/// Which steps a trace keeps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepSelection {
+    All,
+    None,
+    Range(RangeInclusive<usize>),
+}
+
+impl StepSelection {
+    pub fn contains(&self, step: usize) -> bool {
+        match self {
+            StepSelection::All => true,
+            StepSelection::None => false,
+            StepSelection::Range(range) => range.contains(&step),
+        }
+    }
+}
+
+impl FromStr for StepSelection {
+    type Err = anyhow::Error;
+
+    /// `all`, `none`, `N`, `A..B` (inclusive), `A..` or `..B`.
+    fn from_str(s: &str) -> Result<Self> {
+        let number = |n: &str, default: usize| -> Result<usize> {
+            match n.trim() {
+                "" => Ok(default),
+                n => n.parse().map_err(|e| anyhow!("invalid step '{}': {}", n, e)),
+            }
+        };
+
+        match s.trim() {
+            "all" => Ok(StepSelection::All),
+            "none" => Ok(StepSelection::None),
+            range => match range.split_once("..") {
+                Some((from, to)) => Ok(StepSelection::Range(
+                    number(from, 0)?..=number(to, usize::MAX)?,
+                )),
+                None => {
+                    let step = number(range, 0)?;
+                    Ok(StepSelection::Range(step..=step))
+                }
+            },
+        }
+    }
+}
+
+/// How many characters of each rendered term, environment entry and frame
+/// a batch trace keeps unless `--full-terms` is given.
+pub const DEFAULT_TRUNCATION: usize = 200;
+
+/// How much of each step's terms and values a trace keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Detail {
+    Full,
+    /// Cut every rendering longer than this many characters, noting how
+    /// much was left out.
+    Truncated(usize),
+}
+
+impl Detail {
+    fn apply(self, snapshot: &mut StepSnapshot) {
+        let Detail::Truncated(limit) = self else {
+            return;
+        };
+
+        truncate(&mut snapshot.term, limit);
+        for entry in &mut snapshot.environment {
+            truncate(entry, limit);
+        }
+        for frame in &mut snapshot.continuation {
+            match frame {
+                ContinuationFrame::AwaitArg { function: text }
+                | ContinuationFrame::AwaitFunTerm { argument: text }
+                | ContinuationFrame::AwaitFunValue { argument: text } => truncate(text, limit),
+                ContinuationFrame::Constr { fields, pending, .. } => {
+                    for text in fields.iter_mut().chain(pending) {
+                        truncate(text, limit);
+                    }
+                }
+                ContinuationFrame::Force | ContinuationFrame::Cases { .. } => {}
+            }
+        }
+    }
+}
+
+fn truncate(text: &mut String, limit: usize) {
+    let Some((cut, _)) = text.char_indices().nth(limit) else {
+        return;
+    };
+
+    let rest = text[cut..].chars().count();
+    text.truncate(cut);
+    text.push_str(&format!("… ({} more characters)", rest));
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BudgetSummary {
+    pub spent: ExBudget,
+    pub remaining: ExBudget,
+}
+
+/// Everything a batch run reports.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub schema_version: u32,
+    pub program: String,
+    /// The transaction the program ran in, once for every step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_context: Option<ScriptContextSnapshot>,
+    /// The evaluated term, when the evaluation succeeded.
+    pub result: Option<String>,
+    /// The machine error, when the evaluation failed.
+    pub error: Option<String>,
+    pub budget: BudgetSummary,
+    pub traces: Vec<String>,
+    /// Number of steps the evaluation took, including the final one.
+    pub total_steps: usize,
+    pub steps: Vec<StepSnapshot>,
+}
+
+/// One line of an NDJSON trace.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Header {
+        schema_version: u32,
+        program: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        script_context: Option<ScriptContextSnapshot>,
+    },
+    Step(Box<StepSnapshot>),
+    Summary {
+        result: Option<String>,
+        error: Option<String>,
+        budget: BudgetSummary,
+        traces: Vec<String>,
+        total_steps: usize,
+    },
+}
+
+impl ExecutionTrace {
+    /// Run `session` to the end, keeping the selected steps in full.
+    pub fn collect(program: &str, session: &mut DebugSession, selection: &StepSelection) -> ExecutionTrace {
+        Self::collect_with(program, session, selection, Detail::Full)
+    }
+
+    /// Run `session` to the end, keeping the selected steps at `detail`.
+    pub fn collect_with(
+        program: &str,
+        session: &mut DebugSession,
+        selection: &StepSelection,
+        detail: Detail,
+    ) -> ExecutionTrace {
+        session.goto(0);
+
+        let mut steps = vec![];
+        loop {
+            if selection.contains(session.current_step()) {
+                let mut snapshot = session.snapshot();
+                detail.apply(&mut snapshot);
+                steps.push(snapshot);
+            }
+
+            if !session.step() {
+                break;
+            }
+        }
+
+        let (result, error) = match session.outcome() {
+            Some(Ok(term)) => (Some(term.to_string()), None),
+            Some(Err(e)) => (None, Some(e.to_string())),
+            None => (None, None),
+        };
+
+        ExecutionTrace {
+            schema_version: SCHEMA_VERSION,
+            program: program.to_string(),
+            script_context: session.script_context().cloned(),
+            result,
+            error,
+            budget: BudgetSummary {
+                spent: session.spent(),
+                remaining: session.ex_budget(),
+            },
+            traces: session.traces().iter().map(|trace| trace.to_string()).collect(),
+            total_steps: session.current_step() + 1,
+            steps,
+        }
+    }
+
+    pub fn write<W: Write>(&self, format: Format, mut output: W) -> Result<()> {
+        match format {
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut output, self)?;
+                writeln!(output)?;
+            }
+            Format::Ndjson => {
+                let mut line = |record: &Record| -> Result<()> {
+                    serde_json::to_writer(&mut output, record)?;
+                    writeln!(output)?;
+                    Ok(())
+                };
+
+                line(&Record::Header {
+                    schema_version: self.schema_version,
+                    program: self.program.clone(),
+                    script_context: self.script_context.clone(),
+                })?;
+                for step in &self.steps {
+                    line(&Record::Step(Box::new(step.clone())))?;
+                }
+                line(&Record::Summary {
+                    result: self.result.clone(),
+                    error: self.error.clone(),
+                    budget: self.budget.clone(),
+                    traces: self.traces.clone(),
+                    total_steps: self.total_steps,
+                })?;
+            }
+        }
+
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Read a trace written in either format.
+    pub fn read<R: BufRead>(mut input: R) -> Result<ExecutionTrace> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+
+        let first_line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+        let trace = if first_line.contains("\"record\"") {
+            read_ndjson(&text)?
+        } else {
+            serde_json::from_str(&text).context("invalid JSON trace")?
+        };
+
+        if trace.schema_version > SCHEMA_VERSION {
+            bail!(
+                "trace uses schema version {}, this debugger reads up to version {}",
+                trace.schema_version,
+                SCHEMA_VERSION
+            );
+        }
+
+        Ok(trace)
+    }
+}
+
+fn read_ndjson(text: &str) -> Result<ExecutionTrace> {
+    let mut header = None;
+    let mut summary = None;
+    let mut steps = vec![];
+
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let record: Record = serde_json::from_str(line)
+            .with_context(|| format!("invalid NDJSON trace record on line {}", i + 1))?;
+
+        match record {
+            Record::Header { schema_version, program, script_context } => {
+                header = Some((schema_version, program, script_context))
+            }
+            Record::Step(step) => steps.push(*step),
+            Record::Summary { result, error, budget, traces, total_steps } => {
+                summary = Some((result, error, budget, traces, total_steps))
+            }
+        }
+    }
+
+    let (schema_version, program, script_context) = header.ok_or_else(|| anyhow!("NDJSON trace has no header record"))?;
+    let (result, error, budget, traces, total_steps) =
+        summary.ok_or_else(|| anyhow!("NDJSON trace has no summary record, was it cut short?"))?;
+
+    Ok(ExecutionTrace {
+        schema_version,
+        program,
+        script_context,
+        result,
+        error,
+        budget,
+        traces,
+        total_steps,
+        steps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_step_selection() {
+        assert_eq!("all".parse::<StepSelection>().unwrap(), StepSelection::All);
+        assert_eq!("10..20".parse::<StepSelection>().unwrap(), StepSelection::Range(10..=20));
+        assert_eq!("..5".parse::<StepSelection>().unwrap(), StepSelection::Range(0..=5));
+        assert!("5..".parse::<StepSelection>().unwrap().contains(usize::MAX));
+        assert!("five".parse::<StepSelection>().is_err());
+    }
+}
@@ -1,60 +1,46 @@
 use serde::Serialize;
-use uplc::machine::debug::StepSnapshot;
-use std::collections::BTreeMap;
+use uplc::machine::debug::{ContinuationFrame, ScriptContextSnapshot, StepCost, StepSnapshot};
+use std::{collections::BTreeMap, rc::Rc};
 
 
 #[derive(Clone, Serialize)]
 pub struct Frame {
     pub step: usize,
     pub state_type: String,
-    
-    // REPLACE raw term with semantic description
-    pub human_description: String,  // "Checking if value >= 100 ADA"
-    pub technical_detail: String,   // Original UPLC term (collapsed by default)
-    
+    pub term: String,
     pub environment: Vec<String>,
     pub context_depth: usize,
+    pub continuation: Vec<ContinuationFrame>,
     pub cpu: i64,
     pub mem: i64,
+    pub cost: StepCost,
+    #[serde(skip)]
+    pub script_context: Option<Rc<ScriptContextSnapshot>>,
     pub source_location: Option<String>,
-    
-    // NEW: Validation context
-    pub validation_phase: String,   // "Redeemer validation"
-    pub current_check: String,      // "Signature verification"
-    pub check_passed: Option<bool>, // true/false/unknown
 }
 
 pub fn parse_snapshots_to_frames(
     snapshots: Vec<StepSnapshot>,
     source_map: &BTreeMap<u64, String>,
 ) -> Vec<Frame> {
-    snapshots.into_iter().map(|snap| {
-        Frame {
-            step: snap.step,
-            state_type: snap.state_type,
-            
-            // Convert UPLC to human language
-            human_description: interpret_term_semantically(&snap.term),
-            technical_detail: snap.term,
-            
-            validation_phase: infer_phase(&snap),
-            current_check: infer_check(&snap),
-            check_passed: detect_pass_fail(&snap),
-            
-            // ... rest of fields
-        }
-    }).collect()
+    snapshots.into_iter()
+        .map(|snap| frame_from_snapshot(snap, source_map))
+        .collect()
 }
 
-fn interpret_term_semantically(term: &str) -> String {
-    // Pattern matching on UPLC structures
-    if term.contains("equalsInteger") {
-        "Comparing two integers for equality"
-    } else if term.contains("verifyEd25519Signature") {
-        "Verifying cryptographic signature"
-    } else if term.contains("lessThanInteger") {
-        "Checking if value is below threshold"
+/// Convert a single snapshot, used when frames are rendered on demand.
+pub fn frame_from_snapshot(snap: StepSnapshot, source_map: &BTreeMap<u64, String>) -> Frame {
+    Frame {
+        source_location: source_map.get(&(snap.step as u64)).cloned(),
+        step: snap.step,
+        state_type: snap.state_type,
+        term: snap.term,
+        environment: snap.environment,
+        context_depth: snap.context_depth,
+        continuation: snap.continuation,
+        cpu: snap.cpu,
+        mem: snap.mem,
+        cost: snap.cost,
+        script_context: snap.script_context,
     }
-    // ... many more patterns
-    .to_string()
-}
\ No newline at end of file
+}
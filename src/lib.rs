@@ -3,9 +3,33 @@ pub mod executor;
 pub mod frames;
 pub mod uplc_file_utils;
 pub mod diagnostics;
+pub mod analyzer;
+pub mod schema;
+pub mod provenance;
+pub mod data_view;
+pub mod reporter;
+pub mod breakpoints;
+pub mod dap;
+pub mod export;
+pub mod recording;
+pub mod transaction;
+pub mod tx_report;
+pub mod ex_units;
+pub mod utxos;
+pub mod blueprint;
+pub mod parameters;
 
-pub use loader::{LoadedProgram, parse_parameter, load_programs_from_file, apply_parameters};
-pub use executor::execute_program;
-pub use frames::{parse_snapshots_to_frames, Frame};
+pub use loader::{LoadedProgram, parse_parameter, parse_argument, load_programs_from_file, apply_parameters, apply_arguments};
+pub use parameters::Argument;
+pub use executor::{execute_program, start_session, start_session_with, MachineConfig};
+pub use export::{ExecutionTrace, Format, StepSelection, SCHEMA_VERSION};
+pub use recording::{Recording, RECORDING_VERSION};
+pub use transaction::RedeemerPointer;
+pub use tx_report::{RedeemerReport, TxReport};
+pub use ex_units::{fit_ex_units, fit_ex_units_with_overrides, FittedRedeemer, FittedTx, ScriptPrices};
+pub use frames::{frame_from_snapshot, parse_snapshots_to_frames, Frame};
 pub use uplc_file_utils::{create_uplc_file, create_uplc_files};
-pub use diagnostics::{analyze_frame, print_diagnostic, Diagnostic, DiagnosticStatus, Severity};
\ No newline at end of file
+pub use breakpoints::{parse_breakpoint, Breakpoint, BreakpointHit, Condition, ParsedBreakpoint};
+pub use diagnostics::{print_diagnostic, Diagnostic, DiagnosticStatus, Engine, Rule, Severity};
+pub use analyzer::{analyze, RootCause};
+pub use reporter::{FailureReport, ReportFormat};
\ No newline at end of file
@@ -1,17 +1,28 @@
-use std::{collections::BTreeMap, ffi::OsStr, fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
 
 use anyhow::{Context, Result, anyhow};
-use minicbor::bytes::ByteVec;
 use serde::Deserialize;
 use tracing::{info, warn};
 pub use uplc::ast::Program;
+use num_bigint::BigInt;
+use pallas_primitives::conway::Language;
 use uplc::{
     PlutusData,
-    ast::{DeBruijn, FakeNamedDeBruijn, Name, NamedDeBruijn, Term, Constant},
+    ast::{Data, DeBruijn, FakeNamedDeBruijn, Name, NamedDeBruijn, Term},
     parser,
 };
 use std::rc::Rc;
 
+use crate::{
+    blueprint::{self, Blueprint},
+    parameters::{self, Argument},
+};
+
 #[derive(Clone)]
 pub struct LoadedProgram {
     pub filename: String,
@@ -19,23 +30,100 @@ pub struct LoadedProgram {
     pub source_map: BTreeMap<u64, String>,
 }
 
-enum FileType {
-    Uplc,
-    Flat,
-    Json,
+/// What a script file holds, told apart by its content rather than its
+/// extension.
+enum Script {
+    /// UPLC source.
+    Uplc(String),
+    /// A flat-encoded program.
+    Flat(Vec<u8>),
+    /// A flat program wrapped once in a CBOR byte string, and the language
+    /// a text envelope declares.
+    Cbor(Vec<u8>, Option<Language>),
+    /// `aiken uplc` output: compiled code and its source map.
+    Aiken(AikenExport),
+    Blueprint(Blueprint),
+}
+
+/// The contents of `file`, or of standard input for `-`.
+fn read_input(file: &Path) -> Result<Vec<u8>> {
+    if file == Path::new("-") {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes).context("could not read standard input")?;
+        return Ok(bytes);
+    }
+
+    fs::read(file).with_context(|| format!("could not read {}", file.display()))
 }
 
-fn identify_file_type(file: &Path) -> Result<FileType> {
-    let extension = file.extension().and_then(OsStr::to_str);
-    match extension {
-        Some("uplc") => Ok(FileType::Uplc),
-        Some("flat") => Ok(FileType::Flat),
-        Some("json") => Ok(FileType::Json),
-        _ => Err(anyhow!("That extension is not supported. Supported: .uplc .flat .json")),
+fn display_name(file: &Path) -> String {
+    if file == Path::new("-") {
+        "<stdin>".to_string()
+    } else {
+        file.display().to_string()
     }
 }
 
-fn fix_names(program: Program<NamedDeBruijn>) -> Result<Program<NamedDeBruijn>> {
+/// UPLC source, JSON (a blueprint, an Aiken export or a `cardano-cli` text
+/// envelope), or flat and CBOR-wrapped programs as bytes or hex.
+fn sniff(bytes: Vec<u8>) -> Result<Script> {
+    if let Ok(text) = std::str::from_utf8(&bytes) {
+        let text = text.trim();
+
+        if text.starts_with('(') || text.starts_with("--") {
+            return Ok(Script::Uplc(text.to_string()));
+        }
+        if text.starts_with('{') {
+            return sniff_json(serde_json::from_str(text).context("could not parse json")?);
+        }
+        if !text.is_empty() && text.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(sniff_binary(hex::decode(text).context("could not hex-decode the script")?, None));
+        }
+    }
+
+    Ok(sniff_binary(bytes, None))
+}
+
+fn sniff_json(json: serde_json::Value) -> Result<Script> {
+    if blueprint::is_blueprint(&json) {
+        return Ok(Script::Blueprint(Blueprint::from_value(json)?));
+    }
+
+    if json.get("cborHex").is_some() {
+        let envelope: TextEnvelope = serde_json::from_value(json).context("could not parse text envelope")?;
+        let language = match envelope.kind.as_str() {
+            "PlutusScriptV1" => Language::PlutusV1,
+            "PlutusScriptV2" => Language::PlutusV2,
+            "PlutusScriptV3" => Language::PlutusV3,
+            other => return Err(anyhow!("text envelope of type {} is not a Plutus script", other)),
+        };
+        let bytes = hex::decode(&envelope.cbor_hex).context("could not hex-decode cborHex")?;
+        return Ok(sniff_binary(bytes, Some(language)));
+    }
+
+    let export: AikenExport = serde_json::from_value(json).context("could not parse aiken json")?;
+    Ok(Script::Aiken(export))
+}
+
+/// A flat program starts with its version, never with a CBOR byte string
+/// header, so a byte string spanning the input is a wrapped program.
+/// Explorers and `cardano-cli` wrap it twice.
+fn sniff_binary(bytes: Vec<u8>, language: Option<Language>) -> Script {
+    match unwrap_cbor(&bytes) {
+        Some(inner) if unwrap_cbor(&inner).is_some() => Script::Cbor(inner, language),
+        Some(_) => Script::Cbor(bytes, language),
+        None => Script::Flat(bytes),
+    }
+}
+
+/// The content of the CBOR byte string that is all of `bytes`.
+fn unwrap_cbor(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut decoder = minicbor::Decoder::new(bytes);
+    let inner = decoder.bytes().ok()?;
+    (decoder.position() == bytes.len()).then(|| inner.to_vec())
+}
+
+pub(crate) fn fix_names(program: Program<NamedDeBruijn>) -> Result<Program<NamedDeBruijn>> {
     let debruijn: Program<DeBruijn> = program.into();
     let name: Program<Name> = debruijn.try_into()?;
     let named_de_bruijn: Program<NamedDeBruijn> = name.try_into()?;
@@ -47,65 +135,282 @@ fn load_flat(bytes: &[u8]) -> Result<Program<NamedDeBruijn>> {
     Ok(fake_named_de_bruijn.into())
 }
 
+/// The program in `cbor`, flat bytes wrapped in a CBOR byte string.
+fn load_cbor(cbor: &[u8]) -> Result<Program<NamedDeBruijn>> {
+    let mut flat = vec![];
+    let fake_named_de_bruijn: Program<FakeNamedDeBruijn> = Program::from_cbor(cbor, &mut flat)?;
+    Ok(fake_named_de_bruijn.into())
+}
+
+/// The program in hex `compiledCode`, as Aiken writes it: flat bytes
+/// wrapped in a CBOR byte string.
+pub(crate) fn program_from_compiled_code(compiled_code: &str) -> Result<Program<NamedDeBruijn>> {
+    let bytes = hex::decode(compiled_code)
+        .context("could not hex-decode compiled_code")?;
+
+    fix_names(load_cbor(&bytes)?)
+}
+
+/// Load every program in `file` (`-` for standard input): one, or each
+/// validator of a blueprint.
 pub async fn load_programs_from_file(file: &Path) -> Result<Vec<LoadedProgram>> {
-    let filename = file.display().to_string();
-    info!("Loading program from {}", file.display());
-    match identify_file_type(file)? {
-        FileType::Uplc => {
-            let code = fs::read_to_string(file)?;
+    info!("Loading program from {}", display_name(file));
+    let script = sniff(read_input(file)?)?;
+
+    match script {
+        Script::Blueprint(blueprint) => blueprint
+            .validators
+            .iter()
+            .map(|validator| {
+                Ok(LoadedProgram {
+                    filename: format!("{}#{}", display_name(file), validator.title),
+                    program: validator.program()?,
+                    source_map: BTreeMap::new(),
+                })
+            })
+            .collect(),
+        script => Ok(vec![load_script(file, script)?]),
+    }
+}
+
+fn load_script(file: &Path, script: Script) -> Result<LoadedProgram> {
+    let filename = display_name(file);
+
+    match script {
+        Script::Uplc(code) => {
             let program = parser::program(&code)
                 .context("parser::program failed")?
                 .try_into()?;
             let source_map = BTreeMap::new();
-            Ok(vec![LoadedProgram { filename, program, source_map }])
+            Ok(LoadedProgram { filename, program, source_map })
         }
-        FileType::Flat => {
-            let bytes = std::fs::read(file)?;
-            let program = fix_names(load_flat(&bytes)?)?;
+        Script::Flat(bytes) => {
+            let program = load_flat(&bytes)
+                .with_context(|| format!("{} is not UPLC, JSON, CBOR or flat", filename))?;
             let source_map = BTreeMap::new();
-            Ok(vec![LoadedProgram { filename, program, source_map }])
+            Ok(LoadedProgram { filename, program: fix_names(program)?, source_map })
         }
-        FileType::Json => {
-            let raw = fs::read(file).context("could not read json file")?;
-            let export: AikenExport = serde_json::from_slice(&raw)
-                .context("could not parse aiken json")?;
-
-            let bytes = hex::decode(&export.compiled_code)
-                .context("could not hex-decode compiled_code")?;
-
-            let cbor: ByteVec = minicbor::decode(&bytes)
-                .map_err(|e| anyhow!("minicbor decode failed: {:?}", e))?;
-
-            let inner: Vec<u8> = cbor.into();
-            let program = fix_names(load_flat(&inner)?)?;
+        Script::Cbor(cbor, _) => {
+            let program = fix_names(load_cbor(&cbor)?)?;
+            let source_map = BTreeMap::new();
+            Ok(LoadedProgram { filename, program, source_map })
+        }
+        Script::Aiken(export) => {
+            let program = program_from_compiled_code(&export.compiled_code)?;
             let source_map = export.source_map.unwrap_or_default();
             warn!("Large environment detected: {} bindings", source_map.len());
-            Ok(vec![LoadedProgram { filename, program, source_map }])
+            Ok(LoadedProgram { filename, program, source_map })
         }
+        Script::Blueprint(_) => unreachable!("blueprints hold several programs"),
     }
 }
 
+/// A program ready to have its arguments applied.
+pub struct SelectedProgram {
+    pub program: LoadedProgram,
+    pub arguments: Vec<Argument>,
+    /// The language the file declares, if it does.
+    pub language: Option<Language>,
+}
+
+/// Load the program in `file` and read `args` for it. For a blueprint,
+/// `validator` picks the validator and its schemas type the arguments;
+/// otherwise the first program is taken and the arguments are data or terms.
+pub async fn load_program(file: &Path, validator: Option<&str>, args: &[String]) -> Result<SelectedProgram> {
+    let script = sniff(read_input(file)?)?;
+
+    if let Script::Blueprint(blueprint) = script {
+        let selected = blueprint.validator(validator)?;
+
+        return Ok(SelectedProgram {
+            program: LoadedProgram {
+                filename: format!("{}#{}", display_name(file), selected.title),
+                program: selected.program()?,
+                source_map: BTreeMap::new(),
+            },
+            arguments: blueprint.apply_arguments(selected, args)?,
+            language: blueprint.language()?,
+        });
+    }
+
+    if let Some(validator) = validator {
+        return Err(anyhow!("{} is not a blueprint, there is no validator '{}' to pick", display_name(file), validator));
+    }
+
+    let language = match &script {
+        Script::Cbor(_, language) => language.clone(),
+        _ => None,
+    };
+    let program = load_script(file, script)?;
+    let arguments = args
+        .iter()
+        .enumerate()
+        .map(|(i, p)| parse_argument(i, p.clone()))
+        .collect::<Result<_>>()?;
+
+    Ok(SelectedProgram { program, arguments, language })
+}
+
+/// Read parameter `index` in any notation [`parameters`] accepts.
 pub fn parse_parameter(index: usize, parameter: String) -> Result<PlutusData> {
-    let bytes = hex::decode(&parameter)
-        .context(format!("could not hex-decode parameter {}", index))?;
-    let data = uplc::plutus_data(&bytes)
-        .map_err(|e| anyhow!("could not decode plutus data for parameter {}: {}", index, e))?;
-    Ok(data)
+    parameters::parse(&parameter).with_context(|| format!("invalid parameter {}", index))
+}
+
+/// Read argument `index`, a UPLC term or a parameter.
+pub fn parse_argument(index: usize, argument: String) -> Result<Argument> {
+    parameters::parse_argument(&argument).with_context(|| format!("invalid parameter {}", index))
+}
+
+/// Reads PlutusData in the detailed JSON schema `cardano-cli` uses:
+/// `{"constructor": 0, "fields": [...]}`, `{"map": [{"k": ..., "v": ...}]}`,
+/// `{"list": [...]}`, `{"int": 42}` and `{"bytes": "<hex>"}`. Integers too
+/// large for JSON numbers may be given as strings.
+pub fn data_from_json(value: &serde_json::Value) -> Result<PlutusData> {
+    data_from_json_at(value, "data")
+}
+
+fn data_from_json_at(value: &serde_json::Value, at: &str) -> Result<PlutusData> {
+    use serde_json::Value;
+
+    let fields = value
+        .as_object()
+        .ok_or_else(|| anyhow!("{}: expected an object with constructor, map, list, int or bytes, got {}", at, value))?;
+
+    let list = |key: &str| -> Result<&Vec<Value>> {
+        fields[key]
+            .as_array()
+            .ok_or_else(|| anyhow!("{}.{}: expected an array, got {}", at, key, fields[key]))
+    };
+
+    let keys: Vec<&str> = fields.keys().map(String::as_str).collect();
+    match keys.as_slice() {
+        ["constructor", "fields"] | ["fields", "constructor"] => {
+            let index = fields["constructor"]
+                .as_u64()
+                .ok_or_else(|| anyhow!("{}.constructor: expected a non-negative integer, got {}", at, fields["constructor"]))?;
+            let items = list("fields")?
+                .iter()
+                .enumerate()
+                .map(|(i, field)| data_from_json_at(field, &format!("{}.fields[{}]", at, i)))
+                .collect::<Result<_>>()?;
+
+            Ok(Data::constr(index, items))
+        }
+        ["list"] => Ok(Data::list(
+            list("list")?
+                .iter()
+                .enumerate()
+                .map(|(i, item)| data_from_json_at(item, &format!("{}.list[{}]", at, i)))
+                .collect::<Result<_>>()?,
+        )),
+        ["map"] => Ok(Data::map(
+            list("map")?
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let at = format!("{}.map[{}]", at, i);
+                    match (entry.get("k"), entry.get("v")) {
+                        (Some(k), Some(v)) => Ok((
+                            data_from_json_at(k, &format!("{}.k", at))?,
+                            data_from_json_at(v, &format!("{}.v", at))?,
+                        )),
+                        _ => Err(anyhow!("{}: expected an object with k and v, got {}", at, entry)),
+                    }
+                })
+                .collect::<Result<_>>()?,
+        )),
+        ["int"] => {
+            let int = match &fields["int"] {
+                Value::Number(n) => n.to_string(),
+                Value::String(s) => s.trim().to_string(),
+                other => return Err(anyhow!("{}.int: expected an integer, got {}", at, other)),
+            };
+            let int: BigInt = int
+                .parse()
+                .map_err(|_| anyhow!("{}.int: expected an integer, got {}", at, fields["int"]))?;
+
+            Ok(Data::integer(int))
+        }
+        ["bytes"] => {
+            let bytes = fields["bytes"]
+                .as_str()
+                .ok_or_else(|| anyhow!("{}.bytes: expected a hex string, got {}", at, fields["bytes"]))?;
+            let bytes = hex::decode(bytes).map_err(|e| anyhow!("{}.bytes: invalid hex '{}': {}", at, bytes, e))?;
+
+            Ok(Data::bytestring(bytes))
+        }
+        _ => Err(anyhow!(
+            "{}: expected one of constructor and fields, map, list, int or bytes, got keys {}",
+            at,
+            keys.join(", ")
+        )),
+    }
+}
+
+/// The script bytes the ledger stores and hashes (the flat program wrapped
+/// in a CBOR byte string) for any script file the loader reads.
+pub fn read_script(file: &Path) -> Result<Vec<u8>> {
+    read_script_with_language(file).map(|(cbor, _)| cbor)
+}
+
+/// [`read_script`], and the language the file declares if it does (a text
+/// envelope or a blueprint).
+pub fn read_script_with_language(file: &Path) -> Result<(Vec<u8>, Option<Language>)> {
+    let script = sniff(read_input(file)?)?;
+
+    let (cbor, language) = match script {
+        Script::Uplc(code) => {
+            let cbor = parser::program(&code)
+                .context("parser::program failed")?
+                .to_debruijn()?
+                .to_cbor()?;
+            (cbor, None)
+        }
+        Script::Flat(bytes) => {
+            load_flat(&bytes).with_context(|| format!("{} is not UPLC, JSON, CBOR or flat", file.display()))?;
+            let mut cbor = vec![];
+            minicbor::Encoder::new(&mut cbor)
+                .bytes(&bytes)
+                .map_err(|e| anyhow!("could not encode {}: {}", file.display(), e))?;
+            (cbor, None)
+        }
+        Script::Cbor(cbor, language) => {
+            load_cbor(&cbor)?;
+            (cbor, language)
+        }
+        Script::Aiken(export) => {
+            let cbor = hex::decode(&export.compiled_code).context("could not hex-decode compiled_code")?;
+            load_cbor(&cbor)?;
+            (cbor, None)
+        }
+        Script::Blueprint(blueprint) => {
+            let validator = blueprint.validator(None)?;
+            let cbor = hex::decode(&validator.compiled_code).context("could not hex-decode compiledCode")?;
+            load_cbor(&cbor)?;
+            (cbor, blueprint.language()?)
+        }
+    };
+
+    Ok((cbor, language))
 }
 
 // FIXED: Correct implementation of apply_parameters
-pub fn apply_parameters(
+pub fn apply_parameters(program: LoadedProgram, parameters: Vec<PlutusData>) -> Result<LoadedProgram> {
+    apply_arguments(program, parameters.into_iter().map(Argument::Data).collect())
+}
+
+/// Apply data and term arguments, in order.
+pub fn apply_arguments(
     LoadedProgram { filename, program, source_map }: LoadedProgram,
-    parameters: Vec<PlutusData>,
+    arguments: Vec<Argument>,
 ) -> Result<LoadedProgram> {
     let mut term = program.term;
     
-    // Apply each parameter by wrapping the term in an Apply node
-    for param in parameters {
-        let arg_term = Term::Constant(Rc::new(Constant::Data(param)));
+    // Apply each argument by wrapping the term in an Apply node
+    for argument in arguments {
         term = Term::Apply {
             function: Rc::new(term),
-            argument: Rc::new(arg_term),
+            argument: Rc::new(argument.to_term()?),
         };
     }
     
@@ -117,6 +422,14 @@ pub fn apply_parameters(
     Ok(LoadedProgram { filename, program, source_map })
 }
 
+#[derive(Deserialize)]
+struct TextEnvelope {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(rename = "cborHex")]
+    cbor_hex: String,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct AikenExport {
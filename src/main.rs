@@ -1,23 +1,27 @@
-use std::{env, io::{self, Write}, path::PathBuf, time::Instant};
-use anyhow::{Result, anyhow};
-
-mod loader;
-mod executor;
-mod frames;
-mod diagnostics;
+use std::{env, fs::{self, File}, io::{self, BufWriter, Write}, path::{Path, PathBuf}, process, rc::Rc, time::Instant};
+use anyhow::{Context, Result, anyhow, bail};
+use pallas_primitives::conway::{Language, MintedTx};
+use uplc::tx::ResolvedInput;
+use uplc::machine::{cost_model::ExBudget, debug::{DebugSession, ScriptContextSnapshot, StateView}};
 
+use cek_debugger::{analyzer, breakpoints, dap, data_view, diagnostics, ex_units::{self, ScriptPrices}, executor, export, frames, loader, provenance, recording::Recording, reporter::{FailureReport, ReportFormat}, transaction::{self, RedeemerPointer, ScriptOverride}, tx_report::TxReport};
+use export::{Detail, ExecutionTrace, Format, StepSelection};
 use diagnostics::print_diagnostic;
 
-fn print_frame(f: &frames::Frame, previous: Option<&frames::Frame>) {
+fn print_frame(f: &frames::Frame, call: Option<&provenance::Call>, diagnostics: &[diagnostics::Diagnostic]) {
     println!("\n{}", "═".repeat(80));
-    println!("Step {:04} │ {} │ CPU: {:>10} │ MEM: {:>10}", 
+    println!("Step {:04} │ {:<10} │ CPU: {:>10} │ MEM: {:>10}", 
         f.step, 
-        format!("{:<10}", f.state_type),
+        f.state_type,
         f.cpu,
         f.mem
     );
     println!("{}", "─".repeat(80));
 
+    if let Some(charge) = &f.cost.charge {
+        println!("💰 Cost: CPU {} │ MEM {} │ {}\n", f.cost.cpu, f.cost.mem, charge);
+    }
+
     println!("📋 Term:\n{}\n", f.term);
     
     if !f.environment.is_empty() {
@@ -30,98 +34,839 @@ fn print_frame(f: &frames::Frame, previous: Option<&frames::Frame>) {
         }
     }
     
-    if f.context_depth > 0 {
-        println!("🔀 Continuation Depth: {}", f.context_depth);
+    if !f.continuation.is_empty() {
+        println!("🔀 Continuation ({} frames, innermost first):", f.context_depth);
+        for frame in f.continuation.iter().take(5) {
+            println!("   {}", frame);
+        }
+        if f.continuation.len() > 5 {
+            println!("   ... and {} more", f.continuation.len() - 5);
+        }
+    }
+
+    if let Some(context) = &f.script_context {
+        print_transaction(context);
     }
 
-    // Add smart diagnostics
-    if let Some(diag) = diagnostics::analyze_frame(f, previous) {
-        print_diagnostic(&diag);
+    if let Some(call) = call {
+        println!("🧬 {}", call);
+    }
+
+    for diag in diagnostics {
+        print_diagnostic(diag);
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: cargo run -- <script.uplc|script.json> [param_hex ...]");
+/// Print the data held by the binding `selector` starts with, or by the
+/// returned value, as a tree; the rest of `selector` picks a field of it.
+fn view_data(session: &DebugSession, selector: &str) {
+    let view = session.view();
+    let split = selector.find(['.', '[']).unwrap_or(selector.len());
+    let (name, path) = selector.split_at(split);
+
+    let value = if name.is_empty() {
+        match view.state {
+            StateView::Return { value } => value,
+            _ => {
+                println!("⚠ No value is returned on this step");
+                return;
+            }
+        }
+    } else {
+        match data_view::environment(session, &view).into_iter().find(|(binding, _, _)| binding == name) {
+            Some((_, _, value)) => value,
+            None => {
+                println!("⚠ No binding {} in the environment", name);
+                return;
+            }
+        }
+    };
+
+    let network = data_view::network(session.script_context());
+    let holder = if name.is_empty() { "the returned value" } else { name };
+    let Some(mut tree) = data_view::tree(&view, value, network) else {
+        println!("⚠ No data in {}", holder);
+        return;
+    };
+
+    tree.label = name.to_string();
+    match tree.find(path) {
+        Some(node) => print!("{}", node.render(3)),
+        None => println!("⚠ No field {} in {}", path, holder),
+    }
+}
+
+fn print_transaction(context: &ScriptContextSnapshot) {
+    println!("🧾 Transaction {} ({}):", context.transaction_id, context.purpose);
+    println!("   Redeemer: {}", context.redeemer);
+    println!("   Validity: {} │ Fee: {} lovelace", context.validity_range, context.fee);
+    if !context.signatories.is_empty() {
+        println!("   Signed by: {}", context.signatories.join(", "));
+    }
+
+    let inputs = context.inputs.iter().map(|i| ("in ", i.output_reference.as_str(), &i.address, &i.value, &i.datum));
+    let references = context.reference_inputs.iter().map(|i| ("ref", i.output_reference.as_str(), &i.address, &i.value, &i.datum));
+    let outputs = context.outputs.iter().map(|o| ("out", "", &o.address, &o.value, &o.datum));
+
+    let lines: Vec<_> = inputs.chain(references).chain(outputs).collect();
+    for (kind, reference, address, value, datum) in lines.iter().take(8) {
+        let reference = if reference.is_empty() { String::new() } else { format!("{} ", reference) };
+        println!("   {} {}{} │ {} │ {}", kind, reference, address, value, datum);
+    }
+    if lines.len() > 8 {
+        println!("   ... and {} more", lines.len() - 8);
+    }
+}
+
+/// Print `label` and read one line of input, or `None` once stdin is closed.
+fn read_prompt(label: &str) -> io::Result<Option<String>> {
+    print!("{}", label);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+fn report_hit(hit: Option<breakpoints::BreakpointHit>, session: &uplc::machine::debug::DebugSession) {
+    match hit {
+        Some(hit) => {
+            let breakpoint = session.breakpoints()
+                .find(|(id, _)| *id == hit.id)
+                .map(|(_, b)| b.to_string())
+                .unwrap_or_default();
+            println!("● Breakpoint #{} hit at step {}: {}", hit.id, hit.step, breakpoint);
+        }
+        None if session.current_step() == 0 => println!("⚠ No earlier breakpoint hit"),
+        None => println!("⚠ No further breakpoint hit"),
+    }
+}
+
+const USAGE: &str = "Usage: cargo run -- <script|-> [param ...] [options]
+       cargo run -- plutus.json --validator <title> [argument ...] [options]
+       cargo run -- record <script|-> [param ...] [--output <file>]
+       cargo run -- tx <tx.cbor> <utxos.cbor> [--redeemer <purpose>:<index>] [--slot-config <config>] [options]
+       cargo run -- eval-tx <tx.cbor> <utxos.cbor> [--slot-config <config>] [--format json] [options]
+       cargo run -- fit-tx <tx.cbor> <utxos.cbor> [--margin <percent>] [--protocol-params <file>] [--output <file>] [options]
+       cargo run -- replay <recording|trace.json> [--verify] [--live]
+       cargo run -- dap
+
+Options:
+  --no-interactive       Run to the end and print a summary instead of stepping
+  --format json|ndjson   Run to the end and write the execution trace
+  --report <format>      Run to the end and, if it fails, write a failure report: text, markdown or json
+  --provenance           Show which part of the arguments (e.g. ctx.tx_info.fee) builtin arguments come from
+  --output <file>        Write the trace (or recording, or report) to a file instead of stdout
+  --steps <selection>    Steps to include in the trace: all (default), none, N, A..B, A.. or ..B
+  --full-terms           Keep every term and value of the trace in full instead of cutting them at 200 characters
+  --language <version>   Plutus language: v1, v2 (default, or the blueprint's) or v3
+  --validator <title>    Validator of a plutus.json blueprint (optional if there is only one)
+  --cost-model <file>    Cost model as protocol-parameters JSON (array, costModels or full parameters)
+  --cpu-budget <n>       Initial CPU budget
+  --mem-budget <n>       Initial memory budget
+  --redeemer <ptr>       Redeemer of the transaction to debug, e.g. spend:0 (optional if there is only one)
+  --slot-config <cfg>    mainnet (default), preprod, preview or <zero_time>,<zero_slot>,<slot_length>
+  --override <h>=<file>  tx, eval-tx, fit-tx: run a local build in place of the script with hash h
+  --margin <percent>     fit-tx: raise the evaluated ExUnits by this much (default 0)
+  --protocol-params <f>  fit-tx: protocol parameters JSON for the cost models and script prices";
+
+struct Options {
+    path: PathBuf,
+    params: Vec<String>,
+    interactive: bool,
+    format: Option<Format>,
+    report: Option<ReportFormat>,
+    output: Option<PathBuf>,
+    steps: StepSelection,
+    detail: Detail,
+    language: Option<Language>,
+    cost_model: Option<PathBuf>,
+    budget: ExBudget,
+    validator: Option<String>,
+    provenance: bool,
+}
+
+impl Options {
+    /// The machine configuration for a script of `language`.
+    fn config(&self, language: Language) -> Result<executor::MachineConfig> {
+        let mut config = executor::MachineConfig::new(language.clone()).with_budget(self.budget);
+
+        if let Some(path) = &self.cost_model {
+            let json = fs::read_to_string(path)
+                .with_context(|| format!("could not read cost model {}", path.display()))?;
+            config = config.with_cost_model(executor::parse_cost_model(&json, &language)?)?;
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options> {
+    let mut positional = vec![];
+    let mut interactive = true;
+    let mut format = None;
+    let mut report = None;
+    let mut output = None;
+    let mut steps = StepSelection::All;
+    let mut detail = Detail::Truncated(export::DEFAULT_TRUNCATION);
+    let mut language = None;
+    let mut cost_model = None;
+    let mut budget = ExBudget::default();
+    let mut validator = None;
+    let mut provenance = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next().ok_or_else(|| anyhow!("{} needs a value", flag))
+        };
+
+        match arg.as_str() {
+            "--no-interactive" => interactive = false,
+            "--format" => format = Some(value(arg)?.parse()?),
+            "--report" => report = Some(value(arg)?.parse()?),
+            "--output" => output = Some(PathBuf::from(value(arg)?)),
+            "--steps" => steps = value(arg)?.parse()?,
+            "--full-terms" => detail = Detail::Full,
+            "--language" => language = Some(executor::parse_language(value(arg)?)?),
+            "--cost-model" => cost_model = Some(PathBuf::from(value(arg)?)),
+            "--cpu-budget" => budget.cpu = parse_budget(arg, value(arg)?)?,
+            "--mem-budget" => budget.mem = parse_budget(arg, value(arg)?)?,
+            "--validator" => validator = Some(value(arg)?.clone()),
+            "--provenance" => provenance = true,
+            flag if flag.starts_with("--") => bail!("unknown option '{}'", flag),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.is_empty() {
+        bail!("missing program file");
+    }
+
+    Ok(Options {
+        path: PathBuf::from(positional.remove(0)),
+        params: positional,
+        interactive: interactive && format.is_none() && report.is_none(),
+        format,
+        report,
+        output,
+        steps,
+        detail,
+        language,
+        cost_model,
+        budget,
+        validator,
+        provenance,
+    })
+}
+
+fn parse_budget(flag: &str, value: &str) -> Result<i64> {
+    match value.replace('_', "").parse::<i64>() {
+        Ok(n) if n >= 0 => Ok(n),
+        _ => bail!("{} must be a non-negative integer, got '{}'", flag, value),
+    }
+}
+
+fn print_summary(trace: &ExecutionTrace, root_cause: Option<&analyzer::RootCause>) {
+    match (&trace.result, &trace.error) {
+        (Some(result), _) => println!("✓ Result: {}", result),
+        (_, Some(error)) => println!("✗ Error: {}", error),
+        _ => {}
+    }
+    if let Some(cause) = root_cause {
+        println!("🔎 Root cause: {}", cause);
+    }
+    println!("Steps: {}", trace.total_steps);
+    println!("Budget spent: CPU {} │ MEM {}", trace.budget.spent.cpu, trace.budget.spent.mem);
+    println!("Budget left:  CPU {} │ MEM {}", trace.budget.remaining.cpu, trace.budget.remaining.mem);
+    for trace in &trace.traces {
+        println!("📝 {}", trace);
+    }
+}
+
+/// Step through a recording, or a trace exported with `--format`. Only
+/// `--verify` and `--live` re-execute, and only a recording can be.
+fn replay(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut verify = false;
+    let mut live = false;
+    for arg in args {
+        match arg.as_str() {
+            "--verify" => verify = true,
+            "--live" => live = true,
+            flag if flag.starts_with("--") => bail!("unknown option '{}'", flag),
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("usage: replay <recording|trace> [--verify] [--live]"),
+        }
+    }
+    let Some(path) = path else {
+        bail!("usage: replay <recording|trace> [--verify] [--live]");
+    };
+
+    let bytes = fs::read(path).with_context(|| format!("could not read {}", path))?;
+
+    // Recordings are gzip-compressed, exported traces plain JSON or NDJSON.
+    let (trace, source_map, mut session) = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let recording = Recording::read(bytes.as_slice())?;
+        println!("Recording of {} ({} steps, checksum {})", recording.filename, recording.trace.total_steps, recording.checksum);
+
+        if verify {
+            recording.verify()?;
+            println!("✓ Re-execution reproduces the recorded trace");
+        }
+
+        // Diagnostics, root causes and data views read the machine itself, so
+        // they need the run reopened alongside the recorded frames.
+        let session = if live { Some(recording.session()?) } else { None };
+        (recording.trace, recording.source_map, session)
+    } else {
+        if verify || live {
+            bail!("{} is an exported trace, which holds no program to re-execute; --verify and --live need a recording", path);
+        }
+
+        let trace = ExecutionTrace::read(bytes.as_slice())?;
+        println!("Trace of {} ({} of {} steps)", trace.program, trace.steps.len(), trace.total_steps);
+        (trace, Default::default(), None)
+    };
+
+    // Traces hold the transaction once; every frame shows it.
+    let script_context = trace.script_context.map(Rc::new);
+    let mut frames = frames::parse_snapshots_to_frames(trace.steps, &source_map);
+    for frame in &mut frames {
+        frame.script_context = script_context.clone();
+    }
+    if frames.is_empty() {
+        bail!("{} has no steps", path);
+    }
+
+    let engine = diagnostics::Engine::default();
+
+    let mut current: usize = 0;
+    loop {
+        let diagnostics = match &mut session {
+            Some(session) => {
+                session.goto(frames[current].step);
+                engine.analyze(&session.view())
+            }
+            None => vec![],
+        };
+        print_frame(&frames[current], None, &diagnostics);
+
+        if frames[current].step + 1 == trace.total_steps {
+            match (&trace.result, &trace.error) {
+                (Some(result), _) => println!("\n✓ Result: {}", result),
+                (_, Some(error)) => {
+                    println!("\n✗ Error: {}", error);
+                    if let Some(cause) = session.as_mut().and_then(analyzer::analyze) {
+                        println!("🔎 Root cause: {}", cause);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(input) = read_prompt("\n[N]ext | [P]rev | [J]ump | [V]iew | [Q]uit > ")? else {
+            break;
+        };
+
+        match input.to_uppercase().as_str() {
+            "N" if current + 1 < frames.len() => current += 1,
+            "N" => println!("⚠ At last step"),
+            "P" if current > 0 => current -= 1,
+            "P" => println!("⚠ At first step"),
+            "J" => {
+                let Some(jump) = read_prompt("Jump to step: ")? else { break };
+                // Exported traces may hold only some of the steps.
+                match jump.parse::<usize>().ok().and_then(|n| frames.iter().position(|frame| frame.step == n)) {
+                    Some(index) => current = index,
+                    None => println!("⚠ Invalid step ({}..={})", frames[0].step, frames[frames.len() - 1].step),
+                }
+            }
+            "V" => match &session {
+                Some(session) => {
+                    let Some(selector) = read_prompt("View data (binding[.field...], or empty for the returned value): ")? else { break };
+                    view_data(session, &selector);
+                }
+                None => println!("⚠ Viewing data re-executes the recording; replay it with --live"),
+            },
+            "Q" => break,
+            _ => println!("⚠ Unknown command"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Debug one redeemer of a transaction with the script context the ledger
+/// would build for it.
+fn tx(args: &[String]) -> Result<()> {
+    let mut pointer = None;
+    let mut slot_config = None;
+    let mut overrides = vec![];
+    let mut rest = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next().ok_or_else(|| anyhow!("{} needs a value", flag))
+        };
+
+        match arg.as_str() {
+            "--redeemer" => pointer = Some(value(arg)?.parse::<RedeemerPointer>()?),
+            "--slot-config" => slot_config = Some(transaction::parse_slot_config(value(arg)?)?),
+            "--override" => overrides.push(value(arg)?.parse::<ScriptOverride>()?),
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    let options = parse_options(&rest)?;
+    if options.validator.is_some() {
+        bail!("--validator picks a blueprint validator, transactions pick their script with --redeemer");
+    }
+    let [utxos_path] = options.params.as_slice() else {
+        bail!("usage: tx <tx.cbor> <utxos.cbor> [--redeemer <purpose>:<index>]");
+    };
+
+    let tx_bytes = transaction::read_cbor(&options.path)?;
+    let tx = transaction::decode_tx(&tx_bytes)?;
+    let utxos = transaction::read_utxos(Path::new(utxos_path))?;
+
+    let overrides = load_overrides(&tx, &utxos, &overrides)?;
+
+    let redeemer = transaction::find_redeemer(&tx, pointer)?;
+    let pointer = RedeemerPointer::from(&redeemer);
+    let prepared = transaction::prepare_with_overrides(
+        &tx,
+        &utxos,
+        &redeemer,
+        &slot_config.unwrap_or_default(),
+        &transaction::override_map(&overrides),
+    )?;
+
+    if let Some(language) = &options.language {
+        if *language != prepared.language {
+            bail!("the {} script is {:?}, not {:?}", pointer, prepared.language, language);
+        }
+    }
+
+    if options.interactive {
+        println!(
+            "Debugging {} ({:?}, declared CPU {} │ MEM {})",
+            pointer, prepared.language, redeemer.ex_units.steps, redeemer.ex_units.mem
+        );
+    }
+
+    let config = options.config(prepared.language.clone())?;
+    let program = transaction::loaded_program(&prepared, format!("{}#{}", options.path.display(), pointer))?;
+
+    let roots = provenance::transaction_roots(&prepared);
+
+    debug(program, &options, &config, Some(transaction::script_context(&prepared)), roots)
+}
+
+/// Load `--override` scripts, warning about every one that is not the
+/// script it replaces.
+fn load_overrides(
+    tx: &MintedTx,
+    utxos: &[ResolvedInput],
+    overrides: &[ScriptOverride],
+) -> Result<Vec<transaction::LoadedOverride>> {
+    let overrides = transaction::load_overrides(tx, utxos, overrides)?;
+
+    for o in overrides.iter().filter(|o| !o.matches()) {
+        eprintln!(
+            "⚠ Script {} is overridden by {} (hash {}): the transaction does not commit to this script, results may differ on chain",
+            o.script_hash,
+            o.file.display(),
+            o.replacement_hash
+        );
+    }
+
+    Ok(overrides)
+}
+
+/// Run phase one and every redeemer of a transaction and report the outcome.
+fn eval_tx(args: &[String]) -> Result<()> {
+    let mut slot_config = None;
+    let mut overrides = vec![];
+    let mut rest = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| anyhow!("{} needs a value", flag));
+
+        match arg.as_str() {
+            "--slot-config" => slot_config = Some(transaction::parse_slot_config(value(arg)?)?),
+            "--override" => overrides.push(value(arg)?.parse::<ScriptOverride>()?),
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    let options = parse_options(&rest)?;
+    if options.validator.is_some() {
+        bail!("--validator picks a blueprint validator, transactions pick their script with --redeemer");
+    }
+    let [utxos_path] = options.params.as_slice() else {
+        bail!("usage: eval-tx <tx.cbor> <utxos.cbor> [--format json]");
+    };
+    if options.format == Some(Format::Ndjson) {
+        bail!("eval-tx writes --format json only");
+    }
+
+    let tx_bytes = transaction::read_cbor(&options.path)?;
+    let tx = transaction::decode_tx(&tx_bytes)?;
+    let utxos = transaction::read_utxos(Path::new(utxos_path))?;
+
+    let overrides = load_overrides(&tx, &utxos, &overrides)?;
+
+    let report = TxReport::evaluate_with_overrides(
+        &tx,
+        &utxos,
+        &slot_config.unwrap_or_default(),
+        &transaction::override_map(&overrides),
+        |language| options.config(language),
+    );
+
+    match (options.format, &options.output) {
+        (Some(_), Some(path)) => report.write_json(BufWriter::new(File::create(path)?))?,
+        (Some(_), None) => report.write_json(io::stdout().lock())?,
+        (None, Some(path)) => report.write_table(BufWriter::new(File::create(path)?))?,
+        (None, None) => report.write_table(io::stdout().lock())?,
+    }
+
+    if !report.passed() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Evaluate every redeemer of an unbalanced transaction and write it back
+/// with the ExUnits its scripts need.
+fn fit_tx(args: &[String]) -> Result<()> {
+    let mut slot_config = None;
+    let mut margin = 0;
+    let mut protocol_params = None;
+    let mut overrides = vec![];
+    let mut rest = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| anyhow!("{} needs a value", flag));
+
+        match arg.as_str() {
+            "--slot-config" => slot_config = Some(transaction::parse_slot_config(value(arg)?)?),
+            "--margin" => {
+                let percent = value(arg)?;
+                margin = percent
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| anyhow!("--margin must be a whole percentage, got '{}'", percent))?;
+            }
+            "--protocol-params" => protocol_params = Some(fs::read_to_string(value(arg)?)?),
+            "--override" => overrides.push(value(arg)?.parse::<ScriptOverride>()?),
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    let options = parse_options(&rest)?;
+    if options.validator.is_some() {
+        bail!("--validator picks a blueprint validator, transactions pick their script with --redeemer");
+    }
+    let [utxos_path] = options.params.as_slice() else {
+        bail!("usage: fit-tx <tx.cbor> <utxos.cbor> [--margin <percent>] [--protocol-params <file>] [--output <file>]");
+    };
+    if options.cost_model.is_some() || options.language.is_some() {
+        bail!("fit-tx takes the cost models of every language from --protocol-params");
+    }
+    if options.format == Some(Format::Ndjson) {
+        bail!("fit-tx writes --format json only");
+    }
+
+    let (cost_models, prices) = match &protocol_params {
+        Some(json) => (
+            executor::parse_cost_models(json)?,
+            Some(ScriptPrices::from_protocol_params(json)?),
+        ),
+        None => (None, None),
+    };
+
+    let tx_bytes = transaction::read_cbor(&options.path)?;
+    let utxos = transaction::read_utxos(Path::new(utxos_path))?;
+
+    let overrides = load_overrides(&transaction::decode_tx(&tx_bytes)?, &utxos, &overrides)?;
+
+    let fitted = ex_units::fit_ex_units_with_overrides(
+        &tx_bytes,
+        &utxos,
+        &slot_config.unwrap_or_default(),
+        cost_models.as_ref(),
+        &options.budget,
+        margin,
+        &transaction::override_map(&overrides),
+    )?;
+
+    let output = options.output.clone().unwrap_or_else(|| {
+        let mut path = options.path.clone().into_os_string();
+        path.push(".fitted");
+        PathBuf::from(path)
+    });
+    fs::write(&output, hex::encode(&fitted.cbor))?;
+
+    match options.format {
+        Some(_) => fitted.write_json(io::stdout().lock(), prices.as_ref())?,
+        None => {
+            fitted.write_table(io::stdout().lock(), prices.as_ref())?;
+            println!("Wrote {}", output.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a session on `program` with the transaction it runs in and, if
+/// `--provenance` is given, track values taken from the arguments named
+/// `roots`.
+fn open_session(
+    program: uplc::ast::Program<uplc::ast::NamedDeBruijn>,
+    options: &Options,
+    config: &executor::MachineConfig,
+    script_context: Option<ScriptContextSnapshot>,
+    roots: Vec<String>,
+) -> Result<DebugSession> {
+    let mut session = executor::start_session_with(program, config)?;
+    if let Some(script_context) = script_context {
+        session.set_script_context(script_context);
+    }
+    if options.provenance {
+        session.track_provenance(roots);
+    }
+
+    Ok(session)
+}
+
+/// Run `program` in batch mode or open it in the interactive stepper.
+fn debug(
+    program: loader::LoadedProgram,
+    options: &Options,
+    config: &executor::MachineConfig,
+    script_context: Option<ScriptContextSnapshot>,
+    roots: Vec<String>,
+) -> Result<()> {
+    if options.output.is_some() && options.format.is_none() && options.report.is_none() {
+        bail!("--output needs --format json or --format ndjson, or --report");
+    }
+    if options.format.is_some() && options.report.is_some() {
+        bail!("--format and --report cannot be combined");
+    }
+
+    if let Some(format) = options.report {
+        let mut session = open_session(program.program, options, config, script_context, roots)?;
+
+        let Some(report) = FailureReport::generate(&program.filename, &mut session, &program.source_map) else {
+            let trace = ExecutionTrace::collect(&program.filename, &mut session, &StepSelection::None);
+            print_summary(&trace, None);
+            return Ok(());
+        };
+
+        match &options.output {
+            Some(path) => report.write(format, BufWriter::new(File::create(path)?))?,
+            None => report.write(format, io::stdout().lock())?,
+        }
+        process::exit(1);
+    }
+
+    if !options.interactive {
+        let mut session = open_session(program.program, options, config, script_context, roots)?;
+        let selection = match options.format {
+            Some(_) => options.steps.clone(),
+            None => StepSelection::None,
+        };
+        let trace = ExecutionTrace::collect_with(&program.filename, &mut session, &selection, options.detail);
+
+        match (options.format, &options.output) {
+            (Some(format), Some(path)) => trace.write(format, BufWriter::new(File::create(path)?))?,
+            (Some(format), None) => trace.write(format, io::stdout().lock())?,
+            (None, _) if trace.error.is_some() => print_summary(&trace, analyzer::analyze(&mut session).as_ref()),
+            (None, _) => print_summary(&trace, None),
+        }
+
+        if trace.error.is_some() {
+            process::exit(1);
+        }
+
         return Ok(());
     }
-    
-    let path = PathBuf::from(&args[1]);
-    let params = args[2..].to_vec();
-
-    // Load program
-    let programs = loader::load_programs_from_file(&path).await?;
-    let mut program = programs.into_iter().next()
-        .ok_or_else(|| anyhow!("No valid program found in file"))?;
-
-    // Apply parameters
-    let parsed_params = params.iter().enumerate()
-        .map(|(i, p)| loader::parse_parameter(i, p.clone()))
-        .collect::<Result<Vec<_>>>()?;
-    
-    program = loader::apply_parameters(program, parsed_params)?;
 
-    // Execute with debugging
+    // Start a lazy debugging session
     let start = Instant::now();
-    let snapshots = executor::execute_program(program.program)?;
+    let mut session = open_session(program.program, options, config, script_context, roots)?;
     let duration = start.elapsed();
-    println!("Execution took: {:?}", duration);
-    
-    let frames = frames::parse_snapshots_to_frames(snapshots, &program.source_map);
+    println!("Session started in: {:?}", duration);
 
-    println!("\n CEK Machine Debugger - {} steps captured\n", frames.len());
+    println!("\n CEK Machine Debugger - steps are evaluated on demand\n");
 
     // Interactive stepper
-    let mut idx = 0;
+    let engine = diagnostics::Engine::default();
+    let mut root_cause = None;
     loop {
-        if let Some(frame) = frames.get(idx) {
-            let prev = if idx > 0 { frames.get(idx - 1) } else { None };
-            print_frame(frame, prev);
-        } else {
-            println!("Invalid frame index");
-            break;
+        let frame = frames::frame_from_snapshot(session.snapshot(), &program.source_map);
+        let view = session.view();
+        print_frame(&frame, provenance::call(&view).as_ref(), &engine.analyze(&view));
+
+        if let Some(Err(_)) = session.outcome() {
+            let cause = root_cause.get_or_insert_with(|| analyzer::analyze(&mut session));
+            if let Some(cause) = cause {
+                println!("\n🔎 Root cause: {}", cause);
+            }
         }
 
-        print!("\n[N]ext | [P]rev | [J]ump | [Q]uit > ");
-        io::stdout().flush()?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        
-        match input.trim().to_uppercase().as_str() {
+        let Some(input) = read_prompt("\n[N]ext | [P]rev | [J]ump | [C]ontinue | [R]everse | [B]reak | [D]elete | [L]ist | [V]iew | [Q]uit > ")? else {
+            break;
+        };
+
+        match input.to_uppercase().as_str() {
             "N" => {
-                if idx + 1 < frames.len() {
-                    idx += 1;
-                } else {
+                if !session.step() {
                     println!("⚠ At last step");
                 }
             }
             "P" => {
-                if idx > 0 {
-                    idx -= 1;
-                } else {
+                if !session.step_back() {
                     println!("⚠ At first step");
                 }
             }
             "J" => {
-                print!("Jump to step: ");
-                io::stdout().flush()?;
-                let mut jump = String::new();
-                io::stdin().read_line(&mut jump)?;
-                if let Ok(n) = jump.trim().parse::<usize>() {
-                    if n < frames.len() {
-                        idx = n;
-                    } else {
-                        println!("⚠ Invalid step (max: {})", frames.len() - 1);
+                let Some(jump) = read_prompt("Jump to step: ")? else { break };
+                if let Ok(n) = jump.parse::<usize>() {
+                    if session.goto(n) != n {
+                        println!("⚠ Invalid step (max: {})", session.current_step());
                     }
                 }
             }
+            "C" => {
+                let hit = session.continue_forward();
+                report_hit(hit, &session);
+            }
+            "R" => {
+                let hit = session.continue_backward();
+                report_hit(hit, &session);
+            }
+            "B" => {
+                let Some(spec) = read_prompt("Breakpoint (builtin name | error | trace | cpu:N | mem:N | step:N | at:FILE:LINE) [if CONDITION]: ")? else { break };
+                match breakpoints::parse_breakpoint(&spec, &program.source_map) {
+                    Ok(parsed) => {
+                        for breakpoint in parsed.breakpoints {
+                            let label = breakpoint.to_string();
+                            let id = session.add_conditional_breakpoint(breakpoint, parsed.condition.clone());
+                            match session.condition(id) {
+                                Some(condition) => println!("● Breakpoint #{}: {} if {}", id, label, condition),
+                                None => println!("● Breakpoint #{}: {}", id, label),
+                            }
+                        }
+                    }
+                    Err(e) => println!("⚠ {}", e),
+                }
+            }
+            "D" => {
+                let Some(id) = read_prompt("Delete breakpoint #: ")? else { break };
+                match id.parse::<usize>() {
+                    Ok(id) if session.remove_breakpoint(id) => println!("Removed breakpoint #{}", id),
+                    _ => println!("⚠ No such breakpoint"),
+                }
+            }
+            "L" => {
+                let mut any = false;
+                for (id, breakpoint) in session.breakpoints() {
+                    let condition = session.condition(id)
+                        .map(|c| format!(" if {}", c))
+                        .unwrap_or_default();
+                    println!("  #{} {}{} (hit {} times so far)", id, breakpoint, condition, session.hit_count(id));
+                    any = true;
+                }
+                if !any {
+                    println!("No breakpoints set");
+                }
+            }
+            "V" => {
+                let Some(selector) = read_prompt("View data (binding[.field...], or empty for the returned value): ")? else { break };
+                view_data(&session, &selector);
+            }
             "Q" => break,
             _ => println!("⚠ Unknown command"),
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        return Ok(());
+    }
+
+    if args[1] == "dap" {
+        let stdin = io::stdin();
+        return dap::run(stdin.lock(), io::stdout()).await;
+    }
+
+    if args[1] == "replay" {
+        return replay(&args[2..]);
+    }
+
+    if args[1] == "tx" {
+        return tx(&args[2..]);
+    }
+
+    if args[1] == "eval-tx" {
+        return eval_tx(&args[2..]);
+    }
+
+    if args[1] == "fit-tx" {
+        return fit_tx(&args[2..]);
+    }
+
+    let record = args[1] == "record";
+    let options = match parse_options(&args[if record { 2 } else { 1 }..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    // The script takes stdin, so there is nothing left to read commands from.
+    if !record && options.interactive && options.path == Path::new("-") {
+        eprintln!("a script read from standard input cannot be stepped interactively; add --no-interactive, --format or --report\n\n{}", USAGE);
+        process::exit(2);
+    }
+
+    // Load program and its arguments
+    let loader::SelectedProgram { program, arguments, language } =
+        loader::load_program(&options.path, options.validator.as_deref(), &options.params).await?;
+    let config = options.config(options.language.clone().or(language).unwrap_or(Language::PlutusV2))?;
+
+    if record {
+        let output = options.output.clone().unwrap_or_else(|| {
+            let mut path = options.path.clone().into_os_string();
+            path.push(".trace.gz");
+            PathBuf::from(path)
+        });
+        let params = arguments.iter().map(ToString::to_string).collect();
+        let recording = Recording::record(program, params, &config)?;
+        recording.write(BufWriter::new(File::create(&output)?))?;
+
+        println!("Recorded {} steps to {}", recording.trace.total_steps, output.display());
+        println!("Checksum: {}", recording.checksum);
+        return Ok(());
+    }
+
+    let roots = provenance::argument_roots(arguments.len());
+    let program = loader::apply_arguments(program, arguments)?;
+    debug(program, &options, &config, None, roots)
+}
+
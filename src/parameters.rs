@@ -0,0 +1,556 @@
+//! Parameters as people write them. A parameter is one of:
+//!
+//! - hex CBOR: `d8799f0102ff`
+//! - the detailed JSON schema of `cardano-cli`: `{"constructor": 0, "fields": [{"int": 1}]}`
+//! - CBOR diagnostic notation: `121([_ 1, h'cafe'])`
+//! - a UPLC data constant: `(con data (Constr 0 [I 1, B #cafe]))`
+//! - `@file`, a file holding any of the above, or raw CBOR
+//!
+//! Text made only of hex digits is always hex CBOR, so a bare integer is
+//! written `{"int": 10}` or `(con data (I 10))`. Errors point at the line and
+//! column they were found at.
+//!
+//! Arguments applied to a program may also be any UPLC term, such as
+//! `(con integer 5)` or `(lam x x)`. A term is recognised by its opening
+//! keyword; `term:` and `data:` name the kind explicitly, e.g. for an
+//! application `term:[(builtin addInteger) (con integer 1)]`.
+
+use std::{borrow::Cow, fmt::Display, fs};
+
+use anyhow::{Context, Result, anyhow, bail};
+use num_bigint::BigInt;
+use pallas_codec::utils::{KeyValuePairs, MaybeIndefArray};
+use pallas_primitives::alonzo::{self, Constr};
+use uplc::{
+    PlutusData,
+    ast::{Constant, Data, Name, NamedDeBruijn, Term},
+    machine::runtime::convert_tag_to_constr,
+    parser,
+};
+
+use crate::loader;
+
+/// An argument to apply to a program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    /// A data constant.
+    Data(PlutusData),
+    /// Any closed UPLC term.
+    Term(Term<Name>),
+}
+
+impl Argument {
+    pub fn to_term(&self) -> Result<Term<NamedDeBruijn>> {
+        match self {
+            Argument::Data(data) => Ok(Term::Constant(Constant::Data(data.clone()).into())),
+            Argument::Term(term) => term
+                .clone()
+                .try_into()
+                .map_err(|e| anyhow!("could not convert {}: {}", term.to_pretty(), e)),
+        }
+    }
+}
+
+/// Hex CBOR for data and `term:` followed by the term otherwise, which
+/// [`parse_argument`] reads back.
+impl Display for Argument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Argument::Data(data) => write!(f, "{}", Data::to_hex(data.clone())),
+            Argument::Term(term) => write!(f, "term:{}", term.to_pretty()),
+        }
+    }
+}
+
+/// Read an argument: a UPLC term, or data in any notation [`parse`] reads.
+pub fn parse_argument(argument: &str) -> Result<Argument> {
+    if let Some(data) = argument.strip_prefix("data:") {
+        return parse(data).map(Argument::Data);
+    }
+
+    if let Some(term) = argument.strip_prefix("term:") {
+        let text = dereference(term)?;
+        return closed(uplc_term(text.trim())?);
+    }
+
+    let text = dereference(argument)?;
+    let text = text.trim();
+    if is_uplc_term(text) && !is_data_constant(text) {
+        return closed(uplc_term(text)?);
+    }
+
+    parse(text).map(Argument::Data)
+}
+
+/// `term` as an argument, if it has no free variables.
+fn closed(term: Term<Name>) -> Result<Argument> {
+    let argument = Argument::Term(term);
+    argument.to_term()?;
+    Ok(argument)
+}
+
+/// Read a parameter in any of the supported notations.
+pub fn parse(parameter: &str) -> Result<PlutusData> {
+    let text = dereference(parameter)?;
+    let text = text.trim();
+
+    if text.is_empty() {
+        bail!("empty parameter");
+    }
+
+    if text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return from_hex(text);
+    }
+
+    if is_detailed_json(text) {
+        let json: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| positioned(text, e.line(), e.column(), "invalid JSON"))?;
+        return loader::data_from_json(&json);
+    }
+
+    if is_uplc_term(text) {
+        return from_uplc(text);
+    }
+
+    Notation::new(text).read()
+}
+
+/// The text of a parameter, with an `@file` reference replaced by the
+/// file's contents. Files that are not text are taken to be raw CBOR and
+/// returned as hex.
+pub fn dereference(parameter: &str) -> Result<Cow<'_, str>> {
+    let Some(path) = parameter.strip_prefix('@') else {
+        return Ok(Cow::Borrowed(parameter));
+    };
+
+    let bytes = fs::read(path).with_context(|| format!("could not read {}", path))?;
+    Ok(Cow::Owned(String::from_utf8(bytes).unwrap_or_else(|e| hex::encode(e.into_bytes()))))
+}
+
+fn from_hex(text: &str) -> Result<PlutusData> {
+    let bytes = hex::decode(text).with_context(|| format!("could not hex-decode '{}'", text))?;
+
+    uplc::plutus_data(&bytes).map_err(|e| {
+        let hint = if text.chars().all(|c| c.is_ascii_digit()) {
+            format!(" (hex digits are read as CBOR; write the integer as {{\"int\": {}}})", text)
+        } else {
+            String::new()
+        };
+        anyhow!("could not decode plutus data from hex: {}{}", e, hint)
+    })
+}
+
+/// `{"...`; an object in diagnostic notation has data keys, never text.
+fn is_detailed_json(text: &str) -> bool {
+    text.strip_prefix('{').is_some_and(|rest| rest.trim_start().starts_with('"'))
+}
+
+/// `(con data ...`.
+fn is_data_constant(text: &str) -> bool {
+    text.strip_prefix('(')
+        .and_then(|rest| rest.trim_start().strip_prefix("con"))
+        .is_some_and(|rest| rest.trim_start().starts_with("data"))
+}
+
+/// A term that opens with a keyword, such as `(lam` or `(con`.
+fn is_uplc_term(text: &str) -> bool {
+    const KEYWORDS: [&str; 8] = ["con", "lam", "delay", "force", "builtin", "error", "constr", "case"];
+
+    let Some(rest) = text.strip_prefix('(').map(str::trim_start) else {
+        return false;
+    };
+    KEYWORDS.iter().any(|keyword| {
+        rest.strip_prefix(keyword)
+            .is_some_and(|after| !after.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+    })
+}
+
+fn uplc_term(text: &str) -> Result<Term<Name>> {
+    parser::term(text)
+        .map_err(|e| positioned(text, e.location.line, e.location.column, format!("expected {}", e.expected)))
+}
+
+fn from_uplc(text: &str) -> Result<PlutusData> {
+    match uplc_term(text)? {
+        Term::Constant(constant) => match constant.as_ref() {
+            Constant::Data(data) => Ok(data.clone()),
+            other => bail!("expected a data constant, got (con {})", other.to_pretty()),
+        },
+        _ => bail!("expected a (con data ...) constant, not a term"),
+    }
+}
+
+/// `message`, pointing at 1-based `line` and `column` of `text`.
+fn positioned(text: &str, line: usize, column: usize, message: impl Display) -> anyhow::Error {
+    let source = text.lines().nth(line.saturating_sub(1)).unwrap_or_default();
+    let marker = " ".repeat(column.saturating_sub(1));
+
+    anyhow!("line {}, column {}: {}\n  {}\n  {}^", line, column, message, source, marker)
+}
+
+/// A reader for the CBOR diagnostic notation of RFC 8949 (section 8) and
+/// the extensions of RFC 8610 (appendix G) that can describe PlutusData:
+/// integers, byte strings (`h'..'`, `'text'` and chunked `(_ ..)`), definite
+/// and indefinite (`[_ ..]`) arrays and maps, constructor tags and bignums.
+/// `/ comments /` and encoding indicators (`_1`) are skipped.
+struct Notation<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Notation<'a> {
+    fn new(text: &'a str) -> Self {
+        Notation { text, position: 0 }
+    }
+
+    fn read(mut self) -> Result<PlutusData> {
+        let data = self.data()?;
+
+        self.skip();
+        match self.peek() {
+            None => Ok(data),
+            Some(_) => Err(self.error("unexpected input after the value")),
+        }
+    }
+
+    fn data(&mut self) -> Result<PlutusData> {
+        self.skip();
+        let start = self.position;
+
+        match self.peek() {
+            Some('[') => {
+                self.position += 1;
+                let (indefinite, items) = self.sequence(']', Self::data)?;
+                Ok(PlutusData::Array(array(indefinite, items)))
+            }
+            Some('{') => {
+                self.position += 1;
+                let (indefinite, pairs) = self.sequence('}', Self::pair)?;
+                Ok(PlutusData::Map(if indefinite {
+                    KeyValuePairs::Indef(pairs)
+                } else {
+                    KeyValuePairs::Def(pairs)
+                }))
+            }
+            Some('h' | '\'' | '(') => Ok(PlutusData::BoundedBytes(self.bytes()?.into())),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let integer = self.integer()?;
+
+                self.skip();
+                if self.peek() != Some('(') {
+                    return Ok(Data::integer(integer));
+                }
+                let tag = u64::try_from(&integer).map_err(|_| self.error_at(start, "invalid tag"))?;
+                self.position += 1;
+                let data = self.tagged(tag, start)?;
+                self.expect(')')?;
+                Ok(data)
+            }
+            Some('"') => Err(self.error("text strings are not PlutusData; byte strings are written h'..'")),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let word: String = self.rest().chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+                Err(self.error(format!("{} is not PlutusData", word)))
+            }
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    /// The content of tag `tag`, after its opening parenthesis.
+    fn tagged(&mut self, tag: u64, start: usize) -> Result<PlutusData> {
+        if convert_tag_to_constr(tag).is_some() {
+            return Ok(PlutusData::Constr(Constr { tag, any_constructor: None, fields: self.fields()? }));
+        }
+
+        match tag {
+            102 => {
+                self.skip();
+                self.expect('[')?;
+                self.skip();
+                let index = self.integer()?;
+                let index = u64::try_from(&index).map_err(|_| self.error("invalid constructor index"))?;
+                self.skip();
+                self.expect(',')?;
+                let fields = self.fields()?;
+                self.skip();
+                self.expect(']')?;
+                Ok(PlutusData::Constr(Constr { tag, any_constructor: Some(index), fields }))
+            }
+            2 => Ok(PlutusData::BigInt(alonzo::BigInt::BigUInt(self.bytes()?.into()))),
+            3 => Ok(PlutusData::BigInt(alonzo::BigInt::BigNInt(self.bytes()?.into()))),
+            _ => Err(self.error_at(
+                start,
+                format!("tag {} is not PlutusData (constructors are 121-127, 1280-1400 and 102, bignums 2 and 3)", tag),
+            )),
+        }
+    }
+
+    fn fields(&mut self) -> Result<MaybeIndefArray<PlutusData>> {
+        self.skip();
+        self.expect('[')?;
+        let (indefinite, fields) = self.sequence(']', Self::data)?;
+        Ok(array(indefinite, fields))
+    }
+
+    fn pair(&mut self) -> Result<(PlutusData, PlutusData)> {
+        let key = self.data()?;
+        self.skip();
+        self.expect(':')?;
+        Ok((key, self.data()?))
+    }
+
+    /// Items separated by commas up to `close`, after the opening bracket;
+    /// and whether the sequence is indefinite (`_`).
+    fn sequence<T>(&mut self, close: char, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<(bool, Vec<T>)> {
+        self.skip();
+        let indefinite = self.indefinite();
+        let mut items = vec![];
+
+        self.skip();
+        if self.peek() == Some(close) {
+            self.position += 1;
+            return Ok((indefinite, items));
+        }
+
+        loop {
+            items.push(item(self)?);
+            self.skip();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(c) if c == close => {
+                    self.position += 1;
+                    return Ok((indefinite, items));
+                }
+                _ => return Err(self.error(format!("expected ',' or '{}'", close))),
+            }
+        }
+    }
+
+    /// `h'cafe'`, `'text'` or the chunks of `(_ h'ca', h'fe')`.
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        self.skip();
+
+        if self.rest().starts_with("h'") {
+            self.position += 2;
+            let start = self.position;
+            let end = self.rest().find('\'').ok_or_else(|| self.error("unterminated byte string"))?;
+            let digits = &self.text[start..start + end];
+            self.position += end + 1;
+
+            let mut hex_digits = String::new();
+            for (offset, c) in digits.char_indices() {
+                match c {
+                    c if c.is_ascii_hexdigit() => hex_digits.push(c),
+                    c if c.is_whitespace() => {}
+                    c => return Err(self.error_at(start + offset, format!("'{}' is not a hex digit", c))),
+                }
+            }
+            return hex::decode(&hex_digits).map_err(|_| self.error_at(start, "odd number of hex digits"));
+        }
+
+        if self.peek() == Some('\'') {
+            self.position += 1;
+            let mut bytes = vec![];
+            let mut chars = self.rest().char_indices();
+            while let Some((offset, c)) = chars.next() {
+                match c {
+                    '\'' => {
+                        self.position += offset + 1;
+                        return Ok(bytes);
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, c)) => bytes.extend(c.to_string().as_bytes()),
+                        None => break,
+                    },
+                    c => bytes.extend(c.to_string().as_bytes()),
+                }
+            }
+            return Err(self.error("unterminated byte string"));
+        }
+
+        if self.peek() == Some('(') {
+            self.position += 1;
+            self.skip();
+            if !self.indefinite() {
+                return Err(self.error("expected '_' of a chunked byte string"));
+            }
+            let (_, chunks) = self.sequence(')', Self::bytes)?;
+            return Ok(chunks.concat());
+        }
+
+        Err(self.error("expected a byte string"))
+    }
+
+    fn integer(&mut self) -> Result<BigInt> {
+        let start = self.position;
+        let negative = self.peek() == Some('-');
+        if negative {
+            self.position += 1;
+        }
+
+        let digits = self.rest().chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 {
+            return Err(self.error("expected digits"));
+        }
+        self.position += digits;
+        let integer: BigInt = self.text[start..self.position].parse().expect("digits");
+
+        if self.peek() == Some('.') || self.peek() == Some('e') {
+            return Err(self.error_at(start, "floating-point numbers are not PlutusData"));
+        }
+        self.encoding_indicator();
+
+        Ok(integer)
+    }
+
+    /// Consume the `_` that marks an indefinite-length item.
+    fn indefinite(&mut self) -> bool {
+        let indefinite = self.peek() == Some('_') && !self.rest()[1..].starts_with(|c: char| c.is_ascii_digit());
+        if indefinite {
+            self.position += 1;
+        }
+        self.encoding_indicator();
+        indefinite
+    }
+
+    /// Skip an encoding indicator such as `_1`; the encoding of the value is
+    /// chosen when it is serialised.
+    fn encoding_indicator(&mut self) {
+        if self.peek() == Some('_') && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            self.position += 2;
+        }
+    }
+
+    /// Skip whitespace and `/ comments /`.
+    fn skip(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            match trimmed.strip_prefix('/').and_then(|comment| comment.find('/')) {
+                Some(end) => self.position += end + 2,
+                None => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.peek() {
+            Some(next) if next == c => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            _ => Err(self.error(format!("expected '{}'", c))),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, message: impl Display) -> anyhow::Error {
+        self.error_at(self.position, message)
+    }
+
+    fn error_at(&self, position: usize, message: impl Display) -> anyhow::Error {
+        let before = &self.text[..position];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+
+        positioned(self.text, line, column, message)
+    }
+}
+
+fn array(indefinite: bool, items: Vec<PlutusData>) -> MaybeIndefArray<PlutusData> {
+    if indefinite {
+        MaybeIndefArray::Indef(items)
+    } else {
+        MaybeIndefArray::Def(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(data: &PlutusData) -> String {
+        Data::to_hex(data.clone())
+    }
+
+    #[test]
+    fn test_notations_agree() {
+        let cbor = "d8799f0142cafea10203ff";
+
+        assert_eq!(hex(&parse(cbor).unwrap()), cbor);
+        assert_eq!(hex(&parse("121([_ 1, h'ca fe', {2: 3}])").unwrap()), cbor);
+        assert_eq!(hex(&parse("121_1([_ 1, (_ h'ca', h'fe'), {_ 2: 3}]) / Constr 0 /").unwrap()), "d8799f0142cafebf0203ffff");
+        assert_eq!(
+            hex(&parse(r#"{"constructor": 0, "fields": [{"int": 1}, {"bytes": "cafe"}, {"map": [{"k": {"int": 2}, "v": {"int": 3}}]}]}"#).unwrap()),
+            cbor
+        );
+        assert_eq!(hex(&parse("(con data (Constr 0 [I 1, B #cafe, Map [(I 2, I 3)]]))").unwrap()), cbor);
+
+        assert_eq!(hex(&parse("102([7, []])").unwrap()), "d866820780");
+        assert_eq!(parse("[-5, 'ab', 2(h'010000000000000000')]").unwrap(), parse("[-5, h'6162', 18446744073709551616]").unwrap());
+    }
+
+    #[test]
+    fn test_arguments_are_terms_or_data() {
+        let term = |text: &str| match parse_argument(text).unwrap() {
+            Argument::Term(term) => term.to_pretty(),
+            Argument::Data(data) => panic!("expected a term, got {:?}", data),
+        };
+
+        assert_eq!(term("(con integer 5)"), "(con integer 5)");
+        assert_eq!(term(" (lam x x)"), "(lam x x)");
+        assert_eq!(term("term:[(builtin addInteger) (con integer 1)]"), "[ (builtin addInteger) (con integer 1) ]");
+        assert_eq!(term("term:(con data (I 1))"), "(con data (I 1))");
+
+        assert_eq!(parse_argument("(con data (I 1))").unwrap(), Argument::Data(Data::integer(1.into())));
+        assert_eq!(parse_argument("data:[1]").unwrap(), Argument::Data(parse("[1]").unwrap()));
+        assert_eq!(parse_argument("(_ h'01')").unwrap(), Argument::Data(Data::bytestring(vec![1])));
+
+        let argument = parse_argument("(lam x [x (con bytestring #cafe)])").unwrap();
+        assert_eq!(parse_argument(&argument.to_string()).unwrap(), argument);
+        assert_eq!(parse_argument(&Argument::Data(Data::integer(1.into())).to_string()).unwrap().to_string(), "01");
+
+        assert!(format!("{:#}", parse_argument("(lam x y)").unwrap_err()).contains("y"));
+        assert!(format!("{:#}", parse_argument("term:[(lam x x)").unwrap_err()).starts_with("line 1, column 11"));
+    }
+
+    #[test]
+    fn test_file_references() {
+        let dir = std::env::temp_dir().join(format!("cek-debugger-parameters-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("datum.diag"), "121([\n  1 / amount /\n])\n").unwrap();
+        fs::write(dir.join("datum.cbor"), [0xd8, 0x79, 0x9f, 0x01, 0xff]).unwrap();
+
+        let text = parse(&format!("@{}", dir.join("datum.diag").display())).unwrap();
+        let binary = parse(&format!("@{}", dir.join("datum.cbor").display())).unwrap();
+        assert_eq!(hex(&text), "d8798101");
+        assert_eq!(hex(&binary), "d8799f01ff");
+        assert!(parse(&format!("@{}", dir.join("missing").display())).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_errors_point_at_the_position() {
+        let error = |text: &str| format!("{:#}", parse(text).unwrap_err());
+
+        assert!(error("[1, 2 3]").starts_with("line 1, column 7: expected ',' or ']'"), "{}", error("[1, 2 3]"));
+        assert!(error("121([\n  h'0g'])").starts_with("line 2, column 6: 'g' is not a hex digit"));
+        assert!(error("[\"text\"]").contains("text strings are not PlutusData"));
+        assert!(error("24(h'00')").contains("tag 24 is not PlutusData"));
+        assert!(error("{\"constructor\": 0,}").starts_with("line 1, column 19"));
+        assert!(error(r#"{"list": [{"int": "x"}]}"#).contains("data.list[0].int"));
+        assert!(error("(con data (Constr 0 [I x]))").starts_with("line 1, column 24: expected"));
+        assert!(error("(con integer 1)").contains("expected a data constant"));
+        assert!(error("42").contains("{\"int\": 42}"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Where the values a validator computes with come from.
+//!
+//! With provenance tracked, a debug session follows the data a program is
+//! applied to through the builtins that take it apart (`unConstrData`,
+//! `sndPair`, `headList`, `tailList`, `unIData`, ...). This module names
+//! those arguments and writes the paths with the field names of
+//! [`crate::schema`], so that a number being compared reads as
+//! `ctx.tx_info.fee` instead of a bare integer.
+
+use std::fmt;
+
+use uplc::{
+    ast::Constant,
+    builtins::DefaultFunction,
+    machine::{
+        debug::{render_value, MachineView, Origin},
+        value::Value,
+    },
+    tx::{eval::PreparedRedeemer, script_context::ScriptContext},
+    PlutusData,
+};
+
+use crate::schema;
+
+/// Names of the arguments the script of `prepared` is applied to: its
+/// datum, if it takes one, its redeemer and its script context.
+pub fn transaction_roots(prepared: &PreparedRedeemer) -> Vec<String> {
+    let names: &[&str] = match (&prepared.script_context, &prepared.datum) {
+        (ScriptContext::V1V2 { .. }, Some(_)) => &["datum", "redeemer", "ctx"],
+        (ScriptContext::V1V2 { .. }, None) => &["redeemer", "ctx"],
+        (ScriptContext::V3 { .. }, _) => &["ctx"],
+    };
+
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Names of `count` arguments given on the command line: `arg0`, `arg1`, ...
+pub fn argument_roots(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("arg{}", i)).collect()
+}
+
+/// Where `value` comes from, if the session on `view` tracks provenance and
+/// `value` was taken from one of the program's arguments.
+pub fn describe(view: &MachineView, value: &Value) -> Option<String> {
+    let origin = view.origin(value)?;
+    let root = match view.provenance_root(&origin.path().root) {
+        Some(Constant::Data(data)) => Some(data),
+        _ => None,
+    };
+
+    Some(describe_origin(origin, root))
+}
+
+/// `origin` with the fields of known shapes named; `root` is the data its
+/// path starts from.
+pub fn describe_origin(origin: &Origin, root: Option<&PlutusData>) -> String {
+    format!("{}{}", schema::label(origin.path(), root), origin.suffix())
+}
+
+/// `argument` followed by where it comes from, if known.
+pub fn annotate(argument: &str, origin: Option<&str>) -> String {
+    match origin {
+        Some(origin) => format!("{} ← {}", argument, origin),
+        None => argument.to_string(),
+    }
+}
+
+/// Each of `arguments` followed by where it comes from, if known. `origins`
+/// is empty when none is.
+pub fn annotate_all(arguments: &[String], origins: &[Option<String>]) -> Vec<String> {
+    arguments
+        .iter()
+        .enumerate()
+        .map(|(i, argument)| annotate(argument, origins.get(i).and_then(Option::as_deref)))
+        .collect()
+}
+
+/// The builtin the next step evaluates, with the origins of its arguments.
+pub struct Call {
+    pub function: DefaultFunction,
+    pub arguments: Vec<String>,
+    pub origins: Vec<Option<String>>,
+}
+
+/// The builtin `view` is about to evaluate, if any of its arguments has a
+/// known origin.
+pub fn call(view: &MachineView) -> Option<Call> {
+    let (function, arguments) = view.saturating.as_ref()?;
+    let origins: Vec<_> = arguments.iter().map(|value| describe(view, value)).collect();
+
+    if origins.iter().all(Option::is_none) {
+        return None;
+    }
+
+    Some(Call {
+        function: *function,
+        arguments: arguments.iter().map(|value| render_value(value)).collect(),
+        origins,
+    })
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.function)?;
+        for (argument, origin) in self.arguments.iter().zip(&self.origins) {
+            write!(f, "\n   {}", annotate(argument, origin.as_deref()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calls_show_where_arguments_come_from() {
+        let program = uplc::parser::program(
+            "(program 1.0.0 [(lam d [(builtin lessThanInteger) [(builtin unIData) d] (con integer 10)]) (con data (I 42))])",
+        )
+        .unwrap();
+        let mut session = crate::executor::start_session(program.try_into().unwrap());
+        session.track_provenance(argument_roots(1));
+
+        let call = loop {
+            if let Some(call) = call(&session.view()) {
+                if call.function == DefaultFunction::LessThanInteger {
+                    break call;
+                }
+            }
+            assert!(session.step());
+        };
+
+        assert_eq!(call.origins, [Some("arg0".to_string()), None]);
+        assert_eq!(call.to_string(), "lessThanInteger\n   (con integer 42) ← arg0\n   (con integer 10)");
+    }
+}
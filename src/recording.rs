@@ -0,0 +1,176 @@
+//! Recorded debug sessions: everything needed to reopen a run in the
+//! stepper, in one gzip-compressed file.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Read, Write},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use pallas_crypto::hash::Hasher;
+use pallas_primitives::conway::Language;
+use serde::{Deserialize, Serialize};
+use uplc::{
+    ast::{NamedDeBruijn, Program},
+    machine::{cost_model::ExBudget, debug::DebugSession},
+};
+
+use crate::{
+    executor::{self, MachineConfig},
+    export::{ExecutionTrace, StepSelection},
+    loader::{self, LoadedProgram},
+};
+
+/// Version of the recording layout, independent of the trace schema.
+pub const RECORDING_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub filename: String,
+    /// Flat-encoded program, hex, before the parameters are applied.
+    /// Binder names are kept so re-execution renders identical terms.
+    pub program: String,
+    /// Parameters in the order they were applied: hex-encoded CBOR for
+    /// data, `term:` followed by the term for any other argument.
+    pub params: Vec<String>,
+    pub language: Language,
+    pub cost_model: Option<Vec<i64>>,
+    pub budget: ExBudget,
+    pub source_map: BTreeMap<u64, String>,
+    /// blake2b-256 of the trace serialized as compact JSON.
+    pub checksum: String,
+    pub trace: ExecutionTrace,
+}
+
+impl Recording {
+    /// Run `program` with `params` applied and record every step.
+    pub fn record(program: LoadedProgram, params: Vec<String>, config: &MachineConfig) -> Result<Recording> {
+        let flat = program.program.to_flat()
+            .map_err(|e| anyhow!("could not flat-encode program: {}", e))?;
+
+        let filename = program.filename.clone();
+        let source_map = program.source_map.clone();
+        let trace = run(program, &params, config)?;
+
+        Ok(Recording {
+            version: RECORDING_VERSION,
+            filename,
+            program: hex::encode(flat),
+            params,
+            language: config.language.clone(),
+            cost_model: config.cost_model.clone(),
+            budget: config.budget,
+            source_map,
+            checksum: checksum(&trace)?,
+            trace,
+        })
+    }
+
+    pub fn config(&self) -> MachineConfig {
+        MachineConfig {
+            language: self.language.clone(),
+            cost_model: self.cost_model.clone(),
+            budget: self.budget,
+        }
+    }
+
+    /// The recorded program, before its parameters are applied.
+    pub fn loaded_program(&self) -> Result<LoadedProgram> {
+        let flat = hex::decode(&self.program).context("recorded program is not valid hex")?;
+        let program = Program::<NamedDeBruijn>::from_flat(&flat)
+            .map_err(|e| anyhow!("recorded program is not valid flat: {}", e))?;
+
+        Ok(LoadedProgram {
+            filename: self.filename.clone(),
+            program,
+            source_map: self.source_map.clone(),
+        })
+    }
+
+    /// Re-execute the recorded program and check that it produces the
+    /// recorded trace.
+    pub fn verify(&self) -> Result<()> {
+        let trace = self.execute()?;
+        let actual = checksum(&trace)?;
+
+        if actual == self.checksum {
+            return Ok(());
+        }
+
+        let step = self
+            .trace
+            .steps
+            .iter()
+            .zip(&trace.steps)
+            .find(|(recorded, replayed)| recorded != replayed)
+            .map(|(recorded, _)| recorded.step)
+            .unwrap_or_else(|| self.trace.steps.len().min(trace.steps.len()));
+
+        bail!(
+            "re-execution diverged from the recording at step {} (checksum {} instead of {})",
+            step,
+            actual,
+            self.checksum
+        )
+    }
+
+    /// Reopen the recorded run in a session at step 0.
+    pub fn session(&self) -> Result<DebugSession> {
+        let program = apply(self.loaded_program()?, &self.params)?;
+        executor::start_session_with(program.program, &self.config())
+    }
+
+    fn execute(&self) -> Result<ExecutionTrace> {
+        run(self.loaded_program()?, &self.params, &self.config())
+    }
+
+    pub fn write<W: Write>(&self, output: W) -> Result<()> {
+        let mut encoder = GzEncoder::new(output, Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Read a recording, rejecting files whose trace does not match their
+    /// checksum.
+    pub fn read<R: Read>(input: R) -> Result<Recording> {
+        let recording: Recording = serde_json::from_reader(BufReader::new(GzDecoder::new(input)))
+            .context("not a cek-debugger recording")?;
+
+        if recording.version > RECORDING_VERSION {
+            bail!(
+                "recording uses version {}, this debugger reads up to version {}",
+                recording.version,
+                RECORDING_VERSION
+            );
+        }
+
+        if checksum(&recording.trace)? != recording.checksum {
+            bail!("recording is corrupted: its trace does not match its checksum");
+        }
+
+        Ok(recording)
+    }
+}
+
+pub fn checksum(trace: &ExecutionTrace) -> Result<String> {
+    let bytes = serde_json::to_vec(trace)?;
+    Ok(hex::encode(Hasher::<256>::hash(&bytes)))
+}
+
+fn apply(program: LoadedProgram, params: &[String]) -> Result<LoadedProgram> {
+    let parsed = params.iter().enumerate()
+        .map(|(i, p)| loader::parse_argument(i, p.clone()))
+        .collect::<Result<Vec<_>>>()?;
+
+    loader::apply_arguments(program, parsed)
+}
+
+fn run(program: LoadedProgram, params: &[String], config: &MachineConfig) -> Result<ExecutionTrace> {
+    let program = apply(program, params)?;
+    let mut session = executor::start_session_with(program.program, config)?;
+
+    Ok(ExecutionTrace::collect(&program.filename, &mut session, &StepSelection::All))
+}
@@ -1,141 +1,385 @@
+//! Failure reports: why a validator rejected, what the transaction gave it
+//! and how to fix it, for the terminal, for pasting into PRs and issues as
+//! Markdown, and as JSON.
+
+use std::{collections::BTreeMap, fs, io::Write, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use uplc::{
+    builtins::DefaultFunction,
+    machine::{
+        cost_model::ExBudget,
+        debug::{DebugSession, ScriptContextSnapshot},
+    },
+};
+
+use crate::{
+    analyzer::{self, Check, Decision, Failure, Flow, RootCause},
+    provenance,
+};
+
+/// How a [`FailureReport`] is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Boxed plain text for the terminal.
+    Text,
+    Markdown,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(anyhow!("unknown report format '{}', expected text, markdown or json", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct FailureReport {
-    pub validator_intent: String,
+    pub script: String,
+    /// The machine error, as the ledger would report it.
+    pub error: String,
+    /// What the validator checks, when the deciding check is known.
+    pub validator_intent: Option<String>,
     pub transaction_provided: Vec<String>,
     pub failure_reason: String,
-    pub technical_root_cause: String,
+    pub technical_root_cause: Option<RootCause>,
     pub how_to_fix: Vec<String>,
     pub failing_code_snippet: Option<CodeSnippet>,
+    pub traces: Vec<String>,
+    pub steps: usize,
+    pub spent: ExBudget,
+    pub script_context: Option<ScriptContextSnapshot>,
 }
 
-#[derive(Debug)]
+/// Where the failing check is in the validator's source.
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct CodeSnippet {
-    pub source_file: String,
-    pub line_number: usize,
-    pub code: String,
-    pub highlight: String,
+    /// The source-map entry, e.g. `validators/vesting.ak:23`.
+    pub location: String,
+    /// The line itself, when the file can be read from here.
+    pub code: Option<String>,
 }
 
 impl FailureReport {
-    pub fn generate(frames: &[Frame], failure: ValidationFailure) -> Self {
-        FailureReport {
-            validator_intent: failure.intent,
-            transaction_provided: vec![
-                format!("Redeemer: {}", failure.provided.redeemer),
-                format!("Datum: {}", failure.provided.datum),
-                format!("Signatories: {:?}", failure.provided.signatories),
+    /// Run `session` to the end and report why it fails, or `None` if it
+    /// succeeds. `source_map` maps steps to source locations.
+    pub fn generate(script: &str, session: &mut DebugSession, source_map: &BTreeMap<u64, String>) -> Option<FailureReport> {
+        session.run_to_end();
+        let error = session.outcome()?.err()?;
+
+        let root_cause = analyzer::analyze(session);
+        let context = session.script_context().cloned();
+        let step = root_cause.as_ref().map(focus).unwrap_or(session.current_step());
+
+        Some(FailureReport {
+            script: script.to_string(),
+            error: error.to_string(),
+            validator_intent: root_cause.as_ref().and_then(|cause| cause.check.as_ref()).map(intent),
+            transaction_provided: context.as_ref().map(provided).unwrap_or_default(),
+            failure_reason: root_cause.as_ref().map(reason).unwrap_or_else(|| first_line(&error.to_string())),
+            how_to_fix: root_cause.as_ref().map(fixes).unwrap_or_default(),
+            failing_code_snippet: snippet(source_map, step),
+            traces: session.traces().iter().map(|trace| trace.to_string()).collect(),
+            steps: session.current_step(),
+            spent: session.spent(),
+            technical_root_cause: root_cause,
+            script_context: context,
+        })
+    }
+
+    pub fn write<W: Write>(&self, format: ReportFormat, mut output: W) -> Result<()> {
+        match format {
+            ReportFormat::Text => self.write_text(output),
+            ReportFormat::Markdown => self.write_markdown(output),
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut output, self)?;
+                writeln!(output)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_text<W: Write>(&self, mut output: W) -> Result<()> {
+        let rule = "═".repeat(62);
+        writeln!(output, "╔{}╗", rule)?;
+        writeln!(output, "║{:^62}║", "VALIDATOR FAILURE ANALYSIS")?;
+        writeln!(output, "╠{}╣", rule)?;
+
+        writeln!(output, "\nScript: {}", self.script)?;
+        writeln!(output, "Error:  {}", first_line(&self.error))?;
+
+        if let Some(intent) = &self.validator_intent {
+            writeln!(output, "\nValidator Intent:\n  {}", intent)?;
+        }
+
+        writeln!(output, "\nWhat Transaction Provided:")?;
+        if self.transaction_provided.is_empty() {
+            writeln!(output, "  (no transaction; debug it with `tx` to include the script context)")?;
+        }
+        for line in &self.transaction_provided {
+            writeln!(output, "  • {}", line)?;
+        }
+
+        writeln!(output, "\nWhy Validation Failed:\n  {}", self.failure_reason)?;
+
+        if let Some(cause) = &self.technical_root_cause {
+            writeln!(output, "\nTechnical Root Cause:\n  {}", cause)?;
+        }
+
+        if !self.how_to_fix.is_empty() {
+            writeln!(output, "\nHow to Fix:")?;
+            for (i, step) in self.how_to_fix.iter().enumerate() {
+                writeln!(output, "  {}. {}", i + 1, step)?;
+            }
+        }
+
+        if let Some(snippet) = &self.failing_code_snippet {
+            writeln!(output, "\nFailing Code ({}):", snippet.location)?;
+            if let Some(code) = &snippet.code {
+                writeln!(output, "  {}", code)?;
+            }
+        }
+
+        if !self.traces.is_empty() {
+            writeln!(output, "\nTraces:")?;
+            for trace in &self.traces {
+                writeln!(output, "  📝 {}", trace)?;
+            }
+        }
+
+        writeln!(output, "\nSteps: {} │ CPU: {} │ MEM: {}", self.steps, self.spent.cpu, self.spent.mem)?;
+        writeln!(output, "╚{}╝", rule)?;
+        Ok(())
+    }
+
+    fn write_markdown<W: Write>(&self, mut output: W) -> Result<()> {
+        writeln!(output, "## ❌ Validator failure: `{}`\n", self.script)?;
+        writeln!(output, "**Error:** {}\n", first_line(&self.error))?;
+
+        if let Some(intent) = &self.validator_intent {
+            writeln!(output, "### Validator intent\n\n{}\n", intent)?;
+        }
+
+        if !self.transaction_provided.is_empty() {
+            writeln!(output, "### What the transaction provided\n")?;
+            for line in &self.transaction_provided {
+                writeln!(output, "- {}", line)?;
+            }
+            writeln!(output)?;
+        }
+
+        writeln!(output, "### Why validation failed\n\n{}\n", self.failure_reason)?;
+
+        if let Some(cause) = &self.technical_root_cause {
+            writeln!(output, "### Technical root cause\n\n`{}`\n", cause)?;
+        }
+
+        if !self.how_to_fix.is_empty() {
+            writeln!(output, "### How to fix\n")?;
+            for (i, step) in self.how_to_fix.iter().enumerate() {
+                writeln!(output, "{}. {}", i + 1, step)?;
+            }
+            writeln!(output)?;
+        }
+
+        if let Some(snippet) = &self.failing_code_snippet {
+            writeln!(output, "### Failing code (`{}`)\n", snippet.location)?;
+            if let Some(code) = &snippet.code {
+                writeln!(output, "```\n{}\n```\n", code)?;
+            }
+        }
+
+        if !self.traces.is_empty() {
+            writeln!(output, "### Traces\n")?;
+            for trace in &self.traces {
+                writeln!(output, "- `{}`", trace)?;
+            }
+            writeln!(output)?;
+        }
+
+        writeln!(output, "<sub>{} steps · CPU {} · MEM {}</sub>", self.steps, self.spent.cpu, self.spent.mem)?;
+        Ok(())
+    }
+}
+
+/// The step worth looking at: the deciding check, or the failure.
+fn focus(cause: &RootCause) -> usize {
+    match (&cause.check, &cause.failure) {
+        (Some(check), _) => check.step,
+        (None, Failure::ErrorTerm { step } | Failure::Builtin { step, .. } | Failure::Machine { step, .. }) => *step,
+    }
+}
+
+/// What `check` has to return for the validator to accept, in words.
+fn requirement(check: &Check) -> String {
+    use DefaultFunction::*;
+
+    let argument = |i: usize| check.operand(i);
+
+    match check.function {
+        EqualsInteger | EqualsByteString | EqualsString | EqualsData => {
+            format!("{} == {}", argument(0), argument(1))
+        }
+        LessThanInteger | LessThanByteString => format!("{} < {}", argument(0), argument(1)),
+        LessThanEqualsInteger | LessThanEqualsByteString => format!("{} <= {}", argument(0), argument(1)),
+        NullList => format!("{} is empty", argument(0)),
+        VerifyEd25519Signature | VerifyEcdsaSecp256k1Signature | VerifySchnorrSecp256k1Signature => {
+            format!("a valid signature by {}", argument(0))
+        }
+        function => format!("{}({})", function, check.annotated_arguments().join(", ")),
+    }
+}
+
+fn intent(check: &Check) -> String {
+    // The check's result led to the error, so the validator wants the other one.
+    if check.result {
+        format!("The validator requires that {} does not hold.", requirement(check))
+    } else {
+        format!("The validator requires {}.", requirement(check))
+    }
+}
+
+fn reason(cause: &RootCause) -> String {
+    let mut reason = match (&cause.failure, &cause.decision, &cause.check) {
+        (Failure::ErrorTerm { .. }, _, Some(check)) => {
+            let mut reason = format!(
+                "{}({}) returned {}, so the validator took its error branch.",
+                check.function,
+                check.annotated_arguments().join(", "),
+                if check.result { "True" } else { "False" }
+            );
+            if check.flow == Flow::Nearest {
+                reason.push_str(" It is the last such check before the branch; the branch condition did not come from it directly.");
+            }
+            reason
+        }
+        (Failure::ErrorTerm { .. }, Some(Decision::IfThenElse { step, condition }), None) => {
+            format!("At step {}, ifThenElse on {} chose the error branch.", step, condition)
+        }
+        (Failure::ErrorTerm { .. }, Some(Decision::Case { step, tag }), None) => {
+            format!("At step {}, a case on constructor {} chose the error branch.", step, tag)
+        }
+        (Failure::ErrorTerm { .. }, None, None) => "The validator evaluated (error) without taking a branch.".to_string(),
+        (Failure::Builtin { function, arguments, origins, error, .. }, _, _) => {
+            let arguments = provenance::annotate_all(arguments, origins);
+            format!("{} failed on {}: {}", function, arguments.join(", "), first_line(error))
+        }
+        (Failure::Machine { error, .. }, _, _) => first_line(error),
+    };
+
+    if let Some(trace) = &cause.trace {
+        reason.push_str(&format!(" The last trace was \"{}\".", trace));
+    }
+
+    reason
+}
+
+fn fixes(cause: &RootCause) -> Vec<String> {
+    use DefaultFunction::*;
+
+    let mut fixes = match (&cause.failure, &cause.check) {
+        (_, Some(check)) => match check.function {
+            VerifyEd25519Signature | VerifyEcdsaSecp256k1Signature | VerifySchnorrSecp256k1Signature => vec![
+                "Sign with the key the validator expects".to_string(),
+                "Check that the signed message is the one the validator verifies".to_string(),
             ],
-            failure_reason: format_failure_reason(&failure.root_cause),
-            technical_root_cause: extract_failing_term(frames),
-            how_to_fix: failure.fix_suggestion.lines()
-                .map(|s| s.to_string())
-                .collect(),
-            failing_code_snippet: find_source_location(frames),
+            EqualsByteString => vec![
+                format!("Make {} match {}", check.operand(0), check.operand(1)),
+                "Check the key hash, policy id or script hash on each side; a required signer may be missing".to_string(),
+            ],
+            _ if check.result => vec![format!("Change the transaction so that {} no longer holds", requirement(check))],
+            _ => vec![format!("Change the transaction so that {} holds", requirement(check))],
+        },
+        (Failure::Builtin { function, .. }, None) => match function {
+            UnConstrData | UnMapData | UnListData | UnIData | UnBData => vec![
+                "Make the datum or redeemer match the type the validator expects".to_string(),
+            ],
+            DivideInteger | QuotientInteger | RemainderInteger | ModInteger => {
+                vec!["Make sure the divisor cannot be zero".to_string()]
+            }
+            HeadList | TailList => vec!["Provide the list elements the validator expects".to_string()],
+            _ => vec![format!("Check the arguments passed to {}", function)],
+        },
+        (Failure::Machine { error, .. }, None) if error.contains("over budget") => {
+            vec!["Raise the budget with --cpu-budget / --mem-budget, or the redeemer's ExUnits".to_string()]
         }
+        _ => vec![],
+    };
+
+    fixes.push(format!("Jump to step {} in the debugger to inspect the state there", focus(cause)));
+    fixes
+}
+
+fn provided(context: &ScriptContextSnapshot) -> Vec<String> {
+    let mut provided = vec![
+        format!("Purpose: {}", context.purpose),
+        format!("Redeemer: {}", context.redeemer),
+        match context.signatories.is_empty() {
+            true => "Signatories: none".to_string(),
+            false => format!("Signatories: {}", context.signatories.join(", ")),
+        },
+        format!("Validity: {}", context.validity_range),
+        format!("Fee: {} lovelace", context.fee),
+    ];
+
+    for input in &context.inputs {
+        provided.push(format!("Input {} at {}: {}, {}", input.output_reference, input.address, input.value, input.datum));
+    }
+    for input in &context.reference_inputs {
+        provided.push(format!("Reference input {} at {}: {}, {}", input.output_reference, input.address, input.value, input.datum));
+    }
+    for (i, output) in context.outputs.iter().enumerate() {
+        provided.push(format!("Output {} to {}: {}, {}", i, output.address, output.value, output.datum));
+    }
+
+    provided
+}
+
+/// The source location of `step`, or of the closest step before it.
+fn snippet(source_map: &BTreeMap<u64, String>, step: usize) -> Option<CodeSnippet> {
+    let (_, location) = source_map.range(..=step as u64).next_back()?;
+
+    let code = location.rsplit_once(':').and_then(|(file, line)| {
+        let line: usize = line.parse().ok()?;
+        let source = fs::read_to_string(file).ok()?;
+        source.lines().nth(line.checked_sub(1)?).map(|code| code.trim().to_string())
+    });
+
+    Some(CodeSnippet {
+        location: location.clone(),
+        code,
+    })
+}
+
+fn first_line(text: &str) -> String {
+    text.lines().next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_formats() {
+        assert_eq!("md".parse::<ReportFormat>().unwrap(), ReportFormat::Markdown);
+        assert!("html".parse::<ReportFormat>().is_err());
     }
-    
-    pub fn display(&self) -> String {
-        format!(
-            r#"
-╔════════════════════════════════════════════════════════════╗
-║              VALIDATOR FAILURE ANALYSIS                    ║
-╠════════════════════════════════════════════════════════════╣
-
-Validator Intent:
-  {}
-
-What Transaction Provided:
-{}
-
-Why Validation Failed:
-  {}
-
-Technical Root Cause:
-  {}
-
-How to Fix:
-{}
-
-{}
-╚════════════════════════════════════════════════════════════╝
-            "#,
-            self.validator_intent,
-            self.transaction_provided.iter()
-                .map(|p| format!("  • {}", p))
-                .collect::<Vec<_>>()
-                .join("\n"),
-            self.failure_reason,
-            self.technical_root_cause,
-            self.how_to_fix.iter()
-                .enumerate()
-                .map(|(i, step)| format!("  {}. {}", i + 1, step))
-                .collect::<Vec<_>>()
-                .join("\n"),
-            self.failing_code_snippet.as_ref()
-                .map(|s| format!(
-                    "Failing Code ({}:L{}):\n  {}\n  ↑ {}",
-                    s.source_file, s.line_number, s.code, s.highlight
-                ))
-                .unwrap_or_default()
-        )
+
+    #[test]
+    fn test_snippets_use_the_closest_source_location() {
+        let source_map = BTreeMap::from([(3, "validator.ak:12".to_string()), (9, "validator.ak:20".to_string())]);
+
+        assert_eq!(snippet(&source_map, 7).unwrap().location, "validator.ak:12");
+        assert_eq!(snippet(&source_map, 9).unwrap().location, "validator.ak:20");
+        assert!(snippet(&source_map, 2).is_none());
+        assert!(snippet(&source_map, 7).unwrap().code.is_none());
     }
 }
-```
-
----
-
-## Implementation Priority
-
-**Week 1: Foundation**
-1. Add `ScriptContextSnapshot` to `StepSnapshot`
-2. Implement basic pattern matching in `interpret_term_semantically()`
-3. Create simple failure detection
-
-**Week 2: Analysis**
-1. Build `ValidatorAnalyzer` with common patterns
-2. Implement `reconstruct_intent()` for top 10 validator types
-3. Create mismatch detection logic
-
-**Week 3: Reporting**
-1. Build `FailureReport` generator
-2. Create pretty-print formatter
-3. Add source code snippet extraction
-
-**Week 4: Polish**
-1. Add more validator patterns
-2. Improve fix suggestions
-3. Create documentation
-
----
-
-## Example Output (Target)
-```
-╔════════════════════════════════════════════════════════════╗
-║              VALIDATOR FAILURE ANALYSIS                    ║
-╠════════════════════════════════════════════════════════════╣
-
-Validator Intent:
-  This validator ensures the transaction is signed by the owner
-  and the output value is at least 100 ADA.
-
-What Transaction Provided:
-  • Redeemer: Unit
-  • Signatories: [addr1_test_xyz...]
-  • Output Value: 50 ADA
-
-Why Validation Failed:
-  The output value (50 ADA) is below the required minimum (100 ADA).
-
-Technical Root Cause:
-  At step 47: lessThanInteger check returned False
-  Expected: outputValue >= 100000000
-  Actual:   outputValue == 50000000
-
-How to Fix:
-  1. Increase the output UTXO value to at least 100 ADA
-  2. Or modify the validator to accept lower amounts
-  3. Check your transaction builder configuration
-
-Failing Code (vesting.ak:L23):
-  if value >= minAmount then True else False
-  ↑ This check failed because 50 ADA < 100 ADA
-╚════════════════════════════════════════════════════════════╝
\ No newline at end of file
@@ -0,0 +1,445 @@
+//! The shapes of the data the ledger hands to validators.
+//!
+//! Scripts receive their script context as plain `Data`: constructors with
+//! numbered fields, lists and maps. This module knows which field is which,
+//! as `uplc::tx::to_plutus_data` lays them out for each Plutus version, so a
+//! path into the context reads `ctx.tx_info.outputs[1].value.lovelace`
+//! rather than `ctx.fields[0].fields[2][1].fields[1][0].value[0].value`.
+
+use uplc::{
+    machine::{
+        debug::{Path, Segment},
+        runtime::convert_tag_to_constr,
+        value::from_pallas_bigint,
+    },
+    PlutusData,
+};
+
+/// What a piece of data stands for, when it is part of a known structure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Unknown,
+    Integer,
+    Bytes,
+    /// Milliseconds since the Unix epoch.
+    PosixTime,
+    Bool,
+    ScriptContext,
+    TxInfo,
+    /// Why a V1 or V2 script runs, or the key of a redeemer.
+    ScriptPurpose,
+    /// Why a V3 script runs.
+    ScriptInfo,
+    TxInInfo,
+    OutputReference,
+    TransactionId,
+    TxOut,
+    Address,
+    Credential,
+    StakingCredential,
+    /// A map of policy ids to [`Shape::Assets`].
+    Value,
+    /// A map of asset names to quantities.
+    Assets,
+    /// The assets under ada's policy id: just the amount of lovelace.
+    Lovelace,
+    OutputDatum,
+    Interval,
+    Bound,
+    BoundType,
+    Maybe(&'static Shape),
+    List(&'static Shape),
+    Map(&'static Shape, &'static Shape),
+}
+
+/// Named fields of a constructor. Fields with an empty name, such as the
+/// content of a `Some`, add nothing to a path.
+pub type Fields = &'static [(&'static str, Shape)];
+
+const NONE: Fields = &[];
+
+const SCRIPT_CONTEXT_V1_V2: Fields = &[("tx_info", Shape::TxInfo), ("purpose", Shape::ScriptPurpose)];
+const SCRIPT_CONTEXT_V3: Fields = &[
+    ("tx_info", Shape::TxInfo),
+    ("redeemer", Shape::Unknown),
+    ("script_info", Shape::ScriptInfo),
+];
+
+const TX_INFO_V1: Fields = &[
+    ("inputs", Shape::List(&Shape::TxInInfo)),
+    ("outputs", Shape::List(&Shape::TxOut)),
+    ("fee", Shape::Value),
+    ("mint", Shape::Value),
+    ("certificates", Shape::Unknown),
+    ("withdrawals", Shape::Unknown),
+    ("valid_range", Shape::Interval),
+    ("signatories", Shape::List(&Shape::Bytes)),
+    ("data", Shape::Unknown),
+    ("id", Shape::TransactionId),
+];
+const TX_INFO_V2: Fields = &[
+    ("inputs", Shape::List(&Shape::TxInInfo)),
+    ("reference_inputs", Shape::List(&Shape::TxInInfo)),
+    ("outputs", Shape::List(&Shape::TxOut)),
+    ("fee", Shape::Value),
+    ("mint", Shape::Value),
+    ("certificates", Shape::Unknown),
+    ("withdrawals", Shape::Map(&Shape::StakingCredential, &Shape::Integer)),
+    ("valid_range", Shape::Interval),
+    ("signatories", Shape::List(&Shape::Bytes)),
+    ("redeemers", Shape::Map(&Shape::ScriptPurpose, &Shape::Unknown)),
+    ("data", Shape::Map(&Shape::Bytes, &Shape::Unknown)),
+    ("id", Shape::TransactionId),
+];
+const TX_INFO_V3: Fields = &[
+    ("inputs", Shape::List(&Shape::TxInInfo)),
+    ("reference_inputs", Shape::List(&Shape::TxInInfo)),
+    ("outputs", Shape::List(&Shape::TxOut)),
+    ("fee", Shape::Integer),
+    ("mint", Shape::Value),
+    ("certificates", Shape::Unknown),
+    ("withdrawals", Shape::Map(&Shape::Credential, &Shape::Integer)),
+    ("valid_range", Shape::Interval),
+    ("signatories", Shape::List(&Shape::Bytes)),
+    ("redeemers", Shape::Map(&Shape::ScriptPurpose, &Shape::Unknown)),
+    ("data", Shape::Map(&Shape::Bytes, &Shape::Unknown)),
+    ("id", Shape::TransactionId),
+    ("votes", Shape::Unknown),
+    ("proposal_procedures", Shape::Unknown),
+    ("current_treasury_amount", Shape::Maybe(&Shape::Integer)),
+    ("treasury_donation", Shape::Maybe(&Shape::Integer)),
+];
+
+const TX_IN_INFO: Fields = &[("out_ref", Shape::OutputReference), ("resolved", Shape::TxOut)];
+const OUTPUT_REFERENCE: Fields = &[("transaction_id", Shape::TransactionId), ("output_index", Shape::Integer)];
+const TRANSACTION_ID: Fields = &[("hash", Shape::Bytes)];
+const TX_OUT_V1: Fields = &[
+    ("address", Shape::Address),
+    ("value", Shape::Value),
+    ("datum_hash", Shape::Maybe(&Shape::Bytes)),
+];
+const TX_OUT: Fields = &[
+    ("address", Shape::Address),
+    ("value", Shape::Value),
+    ("datum", Shape::OutputDatum),
+    ("reference_script", Shape::Maybe(&Shape::Bytes)),
+];
+const ADDRESS: Fields = &[
+    ("payment_credential", Shape::Credential),
+    ("stake_credential", Shape::Maybe(&Shape::StakingCredential)),
+];
+const HASH: Fields = &[("hash", Shape::Bytes)];
+const INLINE_CREDENTIAL: Fields = &[("credential", Shape::Credential)];
+const POINTER: Fields = &[
+    ("slot", Shape::Integer),
+    ("transaction_index", Shape::Integer),
+    ("certificate_index", Shape::Integer),
+];
+const INLINE_DATUM: Fields = &[("datum", Shape::Unknown)];
+const INTERVAL: Fields = &[("lower_bound", Shape::Bound), ("upper_bound", Shape::Bound)];
+const BOUND: Fields = &[("bound_type", Shape::BoundType), ("is_inclusive", Shape::Bool)];
+const FINITE: Fields = &[("time", Shape::PosixTime)];
+const POLICY_ID: Fields = &[("policy_id", Shape::Bytes)];
+const SPENDING: Fields = &[("out_ref", Shape::OutputReference)];
+const SPENDING_V3: Fields = &[("out_ref", Shape::OutputReference), ("datum", Shape::Maybe(&Shape::Unknown))];
+const REWARDING: Fields = &[("credential", Shape::StakingCredential)];
+const REWARDING_V3: Fields = &[("credential", Shape::Credential)];
+const CERTIFICATE: Fields = &[("certificate", Shape::Unknown)];
+const INDEXED_CERTIFICATE: Fields = &[("index", Shape::Integer), ("certificate", Shape::Unknown)];
+const VOTER: Fields = &[("voter", Shape::Unknown)];
+const PROPOSAL: Fields = &[("index", Shape::Integer), ("procedure", Shape::Unknown)];
+
+impl Shape {
+    /// The name and fields of constructor `index` of this shape, with
+    /// `arity` fields. Versions of the script context differ in the number
+    /// of fields, which tells them apart.
+    pub fn constructor(self, index: u64, arity: usize) -> Option<(&'static str, Fields)> {
+        let constructor = match (self, index, arity) {
+            (Shape::ScriptContext, 0, 2) => ("ScriptContext", SCRIPT_CONTEXT_V1_V2),
+            (Shape::ScriptContext, 0, 3) => ("ScriptContext", SCRIPT_CONTEXT_V3),
+            (Shape::TxInfo, 0, 10) => ("TxInfo", TX_INFO_V1),
+            (Shape::TxInfo, 0, 12) => ("TxInfo", TX_INFO_V2),
+            (Shape::TxInfo, 0, 16) => ("TxInfo", TX_INFO_V3),
+            (Shape::ScriptPurpose | Shape::ScriptInfo, 0, 1) => ("Minting", POLICY_ID),
+            (Shape::ScriptPurpose, 1, 1) => ("Spending", SPENDING),
+            (Shape::ScriptInfo, 1, 2) => ("Spending", SPENDING_V3),
+            (Shape::ScriptPurpose, 2, 1) => ("Rewarding", REWARDING),
+            (Shape::ScriptInfo, 2, 1) => ("Rewarding", REWARDING_V3),
+            (Shape::ScriptPurpose, 3, 1) => ("Certifying", CERTIFICATE),
+            (Shape::ScriptPurpose | Shape::ScriptInfo, 3, 2) => ("Certifying", INDEXED_CERTIFICATE),
+            (Shape::ScriptPurpose | Shape::ScriptInfo, 4, 1) => ("Voting", VOTER),
+            (Shape::ScriptPurpose | Shape::ScriptInfo, 5, 2) => ("Proposing", PROPOSAL),
+            (Shape::TxInInfo, 0, 2) => ("TxInInfo", TX_IN_INFO),
+            (Shape::OutputReference, 0, 2) => ("OutputReference", OUTPUT_REFERENCE),
+            (Shape::TransactionId, 0, 1) => ("TransactionId", TRANSACTION_ID),
+            (Shape::TxOut, 0, 3) => ("TxOut", TX_OUT_V1),
+            (Shape::TxOut, 0, 4) => ("TxOut", TX_OUT),
+            (Shape::Address, 0, 2) => ("Address", ADDRESS),
+            (Shape::Credential, 0, 1) => ("VerificationKey", HASH),
+            (Shape::Credential, 1, 1) => ("Script", HASH),
+            (Shape::StakingCredential, 0, 1) => ("Inline", INLINE_CREDENTIAL),
+            (Shape::StakingCredential, 1, 3) => ("Pointer", POINTER),
+            (Shape::OutputDatum, 0, 0) => ("NoOutputDatum", NONE),
+            (Shape::OutputDatum, 1, 1) => ("DatumHash", HASH),
+            (Shape::OutputDatum, 2, 1) => ("InlineDatum", INLINE_DATUM),
+            (Shape::Interval, 0, 2) => ("Interval", INTERVAL),
+            (Shape::Bound, 0, 2) => ("Bound", BOUND),
+            (Shape::BoundType, 0, 0) => ("NegativeInfinity", NONE),
+            (Shape::BoundType, 1, 1) => ("Finite", FINITE),
+            (Shape::BoundType, 2, 0) => ("PositiveInfinity", NONE),
+            (Shape::Bool, 0, 0) => ("False", NONE),
+            (Shape::Bool, 1, 0) => ("True", NONE),
+            (Shape::Maybe(_), 0, 1) => ("Some", self.some()),
+            (Shape::Maybe(_), 1, 0) => ("None", NONE),
+            _ => return None,
+        };
+
+        Some(constructor)
+    }
+
+    /// The shape of the elements of a list.
+    pub fn element(self) -> Shape {
+        match self {
+            Shape::List(element) => *element,
+            _ => Shape::Unknown,
+        }
+    }
+
+    /// The shapes of the keys and values of a map.
+    pub fn entry(self) -> (Shape, Shape) {
+        match self {
+            Shape::Map(key, value) => (*key, *value),
+            Shape::Value => (Shape::Bytes, Shape::Assets),
+            Shape::Assets | Shape::Lovelace => (Shape::Bytes, Shape::Integer),
+            _ => (Shape::Unknown, Shape::Unknown),
+        }
+    }
+
+    /// The shape of the value under `key` in a map of this shape.
+    pub fn entry_value(self, key: &PlutusData) -> Shape {
+        match (self, key) {
+            (Shape::Value, PlutusData::BoundedBytes(policy)) if policy.is_empty() => Shape::Lovelace,
+            _ => self.entry().1,
+        }
+    }
+
+    fn some(self) -> Fields {
+        match self {
+            Shape::Maybe(Shape::Integer) => &[("", Shape::Integer)],
+            Shape::Maybe(Shape::Bytes) => &[("", Shape::Bytes)],
+            Shape::Maybe(Shape::StakingCredential) => &[("", Shape::StakingCredential)],
+            _ => &[("", Shape::Unknown)],
+        }
+    }
+}
+
+/// The shape of the program argument called `root`, as the debugger names
+/// them: only the script context is known.
+pub fn root_shape(root: &str) -> Shape {
+    match root {
+        "ctx" => Shape::ScriptContext,
+        _ => Shape::Unknown,
+    }
+}
+
+/// The shape of data found without a path, judged from its layout: only a
+/// script context, or the transaction info in one, is told apart.
+pub fn recognise(data: &PlutusData) -> Shape {
+    let tx_info = |data: &PlutusData| match data {
+        PlutusData::Constr(constr) => {
+            constructor_index(constr) == Some(0)
+                && matches!(constr.fields.len(), 10 | 12 | 16)
+                && matches!(constr.fields.first(), Some(PlutusData::Array(_)))
+        }
+        _ => false,
+    };
+
+    match data {
+        PlutusData::Constr(constr)
+            if constructor_index(constr) == Some(0)
+                && matches!(constr.fields.len(), 2 | 3)
+                && constr.fields.first().is_some_and(tx_info) =>
+        {
+            Shape::ScriptContext
+        }
+        data if tx_info(data) => Shape::TxInfo,
+        _ => Shape::Unknown,
+    }
+}
+
+/// The index of a constructor, however it is encoded.
+pub fn constructor_index(constr: &uplc::Constr<PlutusData>) -> Option<u64> {
+    convert_tag_to_constr(constr.tag).or(constr.any_constructor)
+}
+
+/// `path` with the fields of known shapes named. `root` is the data the
+/// path starts from; where it is missing, or the data is of no known shape,
+/// fields keep their numbers.
+pub fn label(path: &Path, root: Option<&PlutusData>) -> String {
+    resolve(path, root).label
+}
+
+/// The data at `path` in `root`, with its shape when it is part of a known
+/// structure.
+pub fn locate<'a>(path: &Path, root: Option<&'a PlutusData>) -> Option<(&'a PlutusData, Shape)> {
+    let resolved = resolve(path, root);
+    resolved.data.map(|data| (data, resolved.shape))
+}
+
+/// What is known of the data at the end of a path.
+struct Resolved<'a> {
+    label: String,
+    shape: Shape,
+    data: Option<&'a PlutusData>,
+}
+
+fn resolve<'a>(path: &Path, root: Option<&'a PlutusData>) -> Resolved<'a> {
+    let mut label = path.root.clone();
+    let mut shape = root_shape(&path.root);
+    let mut data = root;
+    let mut segments = path.segments.iter().peekable();
+
+    while let Some(segment) = segments.next() {
+        match (segment, data) {
+            (Segment::Field(i), Some(PlutusData::Constr(constr))) => {
+                let named = constructor_index(constr)
+                    .and_then(|index| shape.constructor(index, constr.fields.len()))
+                    .and_then(|(_, fields)| fields.get(*i));
+
+                match named {
+                    Some((name, field)) => {
+                        if !name.is_empty() {
+                            label.push('.');
+                            label.push_str(name);
+                        }
+                        shape = *field;
+                    }
+                    None => {
+                        label.push_str(&segment.to_string());
+                        shape = Shape::Unknown;
+                    }
+                }
+                data = constr.fields.get(*i);
+            }
+            (Segment::Index(i), Some(PlutusData::Map(entries))) => {
+                let key_shape = shape.entry().0;
+                let entry = entries.get(*i);
+
+                match (segments.peek(), entry) {
+                    (Some(Segment::Value), Some((key, value))) => {
+                        segments.next();
+                        label.push_str(&key_label(shape, key).unwrap_or_else(|| "[…]".to_string()));
+                        shape = shape.entry_value(key);
+                        data = Some(value);
+                    }
+                    (Some(Segment::Key), Some((key, _))) => {
+                        segments.next();
+                        label.push_str(&format!("[{}].key", i));
+                        shape = key_shape;
+                        data = Some(key);
+                    }
+                    _ => {
+                        label.push_str(&segment.to_string());
+                        shape = Shape::Unknown;
+                        data = None;
+                    }
+                }
+            }
+            (Segment::Index(i), Some(PlutusData::Array(items))) => {
+                label.push_str(&segment.to_string());
+                shape = shape.element();
+                data = items.get(*i);
+            }
+            _ => {
+                label.push_str(&segment.to_string());
+                shape = Shape::Unknown;
+                data = None;
+            }
+        }
+    }
+
+    Resolved { label, shape, data }
+}
+
+/// How the entry with key `key` of a map of `shape` is written after the
+/// map, if the key is simple enough to serve as a label: `["NAME"]` or
+/// `[#hex]` for bytes, `[n]` for integers.
+pub fn key_label(shape: Shape, key: &PlutusData) -> Option<String> {
+    match (shape, key) {
+        // Ada is the asset with an empty name under the empty policy id, so
+        // its one amount reads `.lovelace`.
+        (Shape::Value, PlutusData::BoundedBytes(policy)) if policy.is_empty() => Some(".lovelace".to_string()),
+        (Shape::Lovelace, _) => Some(String::new()),
+        (Shape::Assets, PlutusData::BoundedBytes(name)) => match printable(name) {
+            Some(name) => Some(format!("[{:?}]", name)),
+            None => Some(format!("[#{}]", hex::encode(name.as_slice()))),
+        },
+        (_, PlutusData::BoundedBytes(bytes)) => Some(format!("[#{}]", hex::encode(bytes.as_slice()))),
+        (_, PlutusData::BigInt(n)) => Some(format!("[{}]", from_pallas_bigint(n))),
+        _ => None,
+    }
+}
+
+/// `bytes` as text, if they are printable ASCII, as asset names often are.
+pub fn printable(bytes: &[u8]) -> Option<&str> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .filter(|text| text.chars().all(|c| c.is_ascii_graphic() || c == ' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(root: &str, segments: &[Segment]) -> Path {
+        Path {
+            root: root.to_string(),
+            segments: segments.to_vec(),
+        }
+    }
+
+    fn data(code: &str) -> PlutusData {
+        crate::parameters::parse(&format!("(con data ({}))", code)).unwrap()
+    }
+
+    #[test]
+    fn test_script_context_fields_are_named() {
+        let output = "Constr 0 [Constr 0 [Constr 0 [B #aa], Constr 1 []], Map [(B #, Map [(B #, I 5)])], Constr 0 [], Constr 1 []]";
+        let ctx = data(&format!(
+            "Constr 0 [Constr 0 [List [], List [], List [{}], Map [], Map [], List [], Map [], I 0, List [], Map [], Map [], Constr 0 [B #00]], Constr 0 [B #00]]",
+            output
+        ));
+        use Segment::*;
+
+        let lovelace = path("ctx", &[Field(0), Field(2), Index(0), Field(1), Index(0), Value, Index(0), Value]);
+        assert_eq!(label(&lovelace, Some(&ctx)), "ctx.tx_info.outputs[0].value.lovelace");
+
+        let credential = path("ctx", &[Field(0), Field(2), Index(0), Field(0), Field(0), Field(0)]);
+        assert_eq!(label(&credential, Some(&ctx)), "ctx.tx_info.outputs[0].address.payment_credential.hash");
+
+        assert_eq!(label(&path("ctx", &[Field(1), Constructor]), Some(&ctx)), "ctx.purpose.constructor");
+    }
+
+    #[test]
+    fn test_map_keys_label_entries() {
+        let bytes = |b: &[u8]| data(&format!("B #{}", hex::encode(b)));
+
+        assert_eq!(key_label(Shape::Value, &bytes(b"")).as_deref(), Some(".lovelace"));
+        assert_eq!(key_label(Shape::Value.entry_value(&bytes(b"")), &bytes(b"")).as_deref(), Some(""));
+        assert_eq!(key_label(Shape::Assets, &bytes(b"MY TOKEN")).as_deref(), Some("[\"MY TOKEN\"]"));
+        assert_eq!(key_label(Shape::Assets, &bytes(&[0, 1])).as_deref(), Some("[#0001]"));
+        assert_eq!(key_label(Shape::Unknown, &bytes(b"AB")).as_deref(), Some("[#4142]"));
+        assert_eq!(
+            key_label(Shape::Unknown, &data("I 340282366920938463463374607431768211456")).as_deref(),
+            Some("[340282366920938463463374607431768211456]")
+        );
+        assert_eq!(key_label(Shape::Unknown, &data("List []")), None);
+    }
+
+    #[test]
+    fn test_unknown_roots_keep_field_numbers() {
+        let redeemer = data("Constr 0 [I 1]");
+        assert_eq!(label(&path("redeemer", &[Segment::Field(0)]), Some(&redeemer)), "redeemer.fields[0]");
+        assert_eq!(label(&path("ctx", &[Segment::Field(0), Segment::Constructor]), None), "ctx.fields[0].constructor");
+    }
+}
@@ -0,0 +1,365 @@
+//! Debugging the scripts of a real transaction: decoding the transaction and
+//! the UTxOs it spends, and building the program the ledger runs for one of
+//! its redeemers.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use pallas_codec::minicbor::{self, data::Type};
+use pallas_crypto::hash::Hasher;
+use pallas_primitives::{
+    KeyValuePairs,
+    conway::{self, MintedTx, Redeemer, RedeemerTag, ScriptHash, TransactionInput, TransactionOutput},
+};
+use uplc::machine::debug::ScriptContextSnapshot;
+use uplc::tx::{
+    self, DataLookupTable, ResolvedInput, SlotConfig,
+    eval::{PreparedRedeemer, prepare_redeemer},
+    script_context::PlutusScript,
+};
+
+use crate::loader::{self, LoadedProgram};
+
+/// A redeemer of a transaction, written `<purpose>:<index>`, e.g. `spend:0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedeemerPointer {
+    pub tag: RedeemerTag,
+    pub index: u32,
+}
+
+impl FromStr for RedeemerPointer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (tag, index) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("invalid redeemer '{}', expected <purpose>:<index> such as spend:0", s))?;
+
+        let tag = match tag.trim().to_lowercase().as_str() {
+            "spend" => RedeemerTag::Spend,
+            "mint" => RedeemerTag::Mint,
+            "publish" | "cert" => RedeemerTag::Cert,
+            "withdraw" | "reward" => RedeemerTag::Reward,
+            "vote" => RedeemerTag::Vote,
+            "propose" => RedeemerTag::Propose,
+            other => bail!(
+                "unknown redeemer purpose '{}', expected spend, mint, publish, withdraw, vote or propose",
+                other
+            ),
+        };
+
+        let index = index
+            .trim()
+            .parse()
+            .map_err(|e| anyhow!("invalid redeemer index '{}': {}", index, e))?;
+
+        Ok(RedeemerPointer { tag, index })
+    }
+}
+
+impl Display for RedeemerPointer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tag = match self.tag {
+            RedeemerTag::Spend => "spend",
+            RedeemerTag::Mint => "mint",
+            RedeemerTag::Cert => "publish",
+            RedeemerTag::Reward => "withdraw",
+            RedeemerTag::Vote => "vote",
+            RedeemerTag::Propose => "propose",
+        };
+
+        write!(f, "{}:{}", tag, self.index)
+    }
+}
+
+impl From<&Redeemer> for RedeemerPointer {
+    fn from(redeemer: &Redeemer) -> Self {
+        RedeemerPointer {
+            tag: redeemer.tag,
+            index: redeemer.index,
+        }
+    }
+}
+
+/// A script of the transaction to replace with a local build, written
+/// `<script hash>=<file.uplc|.flat|.json>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptOverride {
+    pub script_hash: ScriptHash,
+    pub file: PathBuf,
+}
+
+impl FromStr for ScriptOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (script_hash, file) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid override '{}', expected <script hash>=<file>", s))?;
+
+        Ok(ScriptOverride {
+            script_hash: script_hash
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("invalid script hash '{}', expected 56 hex digits", script_hash))?,
+            file: PathBuf::from(file.trim()),
+        })
+    }
+}
+
+/// An override loaded in the language of the script it replaces.
+#[derive(Clone, Debug)]
+pub struct LoadedOverride {
+    pub script_hash: ScriptHash,
+    pub file: PathBuf,
+    /// The hash of the replacement, which differs from `script_hash` unless
+    /// the file is the very same script.
+    pub replacement_hash: ScriptHash,
+    pub script: PlutusScript,
+}
+
+impl LoadedOverride {
+    pub fn matches(&self) -> bool {
+        self.script_hash == self.replacement_hash
+    }
+}
+
+/// Load every override, checking that the transaction has the script it
+/// replaces.
+pub fn load_overrides(
+    tx: &MintedTx,
+    utxos: &[ResolvedInput],
+    overrides: &[ScriptOverride],
+) -> Result<Vec<LoadedOverride>> {
+    let lookup_table = DataLookupTable::from_transaction(tx, utxos);
+
+    overrides
+        .iter()
+        .map(|ScriptOverride { script_hash, file }| {
+            let original = lookup_table.get_script(script_hash).ok_or_else(|| {
+                let available: Vec<String> = lookup_table.iter_script_hashes().map(|h| h.to_string()).collect();
+                anyhow!(
+                    "the transaction has no script {} to override (it has: {})",
+                    script_hash,
+                    available.join(", ")
+                )
+            })?;
+
+            let bytes: conway::Bytes = loader::read_script(file)
+                .with_context(|| format!("invalid override {}", file.display()))?
+                .into();
+
+            let (script, tag) = match original {
+                PlutusScript::V1(_) => (PlutusScript::V1(conway::PlutusScript(bytes)), 1),
+                PlutusScript::V2(_) => (PlutusScript::V2(conway::PlutusScript(bytes)), 2),
+                PlutusScript::V3(_) => (PlutusScript::V3(conway::PlutusScript(bytes)), 3),
+            };
+
+            Ok(LoadedOverride {
+                script_hash: *script_hash,
+                file: file.clone(),
+                replacement_hash: Hasher::<224>::hash_tagged(&script, tag),
+                script,
+            })
+        })
+        .collect()
+}
+
+/// The replacement scripts by the hash they stand in for.
+pub fn override_map(overrides: &[LoadedOverride]) -> HashMap<ScriptHash, PlutusScript> {
+    overrides
+        .iter()
+        .map(|o| (o.script_hash, o.script.clone()))
+        .collect()
+}
+
+/// Read a CBOR file, either raw or as hex text.
+pub fn read_cbor(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+
+    match std::str::from_utf8(&bytes).map(str::trim) {
+        Ok(text) if !text.is_empty() && text.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            hex::decode(text).with_context(|| format!("{} is not valid hex", path.display()))
+        }
+        _ => Ok(bytes),
+    }
+}
+
+pub fn decode_tx(bytes: &[u8]) -> Result<MintedTx<'_>> {
+    minicbor::decode(bytes).map_err(|e| anyhow!("not a Conway transaction: {}", e))
+}
+
+/// Decode the UTxOs a transaction spends or references, given as a CBOR map
+/// from input to output or as an array of `[input, output]` pairs.
+pub fn decode_utxos(bytes: &[u8]) -> Result<Vec<ResolvedInput>> {
+    let datatype = minicbor::Decoder::new(bytes)
+        .datatype()
+        .map_err(|e| anyhow!("invalid UTxO CBOR: {}", e))?;
+
+    let pairs: Vec<(TransactionInput, TransactionOutput)> = match datatype {
+        Type::Map | Type::MapIndef => minicbor::decode::<KeyValuePairs<_, _>>(bytes)
+            .map(|pairs| pairs.to_vec()),
+        _ => minicbor::decode(bytes),
+    }
+    .map_err(|e| anyhow!("expected a CBOR map or array of [input, output] pairs: {}", e))?;
+
+    Ok(pairs
+        .into_iter()
+        .map(|(input, output)| ResolvedInput { input, output })
+        .collect())
+}
+
+/// Read the UTxOs from a JSON or YAML fixture (see [`crate::utxos`]) or a
+/// CBOR file.
+pub fn read_utxos(path: &Path) -> Result<Vec<ResolvedInput>> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json" | "yaml" | "yml") => crate::utxos::read(path),
+        _ => decode_utxos(&read_cbor(path)?),
+    }
+}
+
+/// Every redeemer of `tx`, in witness set order.
+pub fn redeemers(tx: &MintedTx) -> Vec<Redeemer> {
+    tx.transaction_witness_set
+        .redeemer
+        .as_ref()
+        .map(|redeemers| {
+            tx::iter_redeemers(redeemers)
+                .map(|(key, data, ex_units)| Redeemer {
+                    tag: key.tag,
+                    index: key.index,
+                    data: data.clone(),
+                    ex_units,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The redeemer `pointer` designates, or the only redeemer of `tx` when no
+/// pointer is given.
+pub fn find_redeemer(tx: &MintedTx, pointer: Option<RedeemerPointer>) -> Result<Redeemer> {
+    let redeemers = redeemers(tx);
+    let available = || {
+        redeemers
+            .iter()
+            .map(|r| RedeemerPointer::from(r).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match pointer {
+        Some(pointer) => redeemers
+            .iter()
+            .find(|r| RedeemerPointer::from(*r) == pointer)
+            .cloned()
+            .ok_or_else(|| anyhow!("the transaction has no {} redeemer (it has: {})", pointer, available())),
+        None => match redeemers.as_slice() {
+            [] => bail!("the transaction has no redeemers, there is no script to debug"),
+            [redeemer] => Ok(redeemer.clone()),
+            _ => bail!("the transaction has several redeemers, pick one with --redeemer ({})", available()),
+        },
+    }
+}
+
+/// Resolve the script of `redeemer` and apply it to its datum, redeemer and
+/// script context, exactly as phase two evaluation does.
+pub fn prepare(
+    tx: &MintedTx,
+    utxos: &[ResolvedInput],
+    redeemer: &Redeemer,
+    slot_config: &SlotConfig,
+) -> Result<PreparedRedeemer> {
+    prepare_with_overrides(tx, utxos, redeemer, slot_config, &HashMap::new())
+}
+
+/// Like [`prepare`], but running the replacement of any overridden script.
+pub fn prepare_with_overrides(
+    tx: &MintedTx,
+    utxos: &[ResolvedInput],
+    redeemer: &Redeemer,
+    slot_config: &SlotConfig,
+    overrides: &HashMap<ScriptHash, PlutusScript>,
+) -> Result<PreparedRedeemer> {
+    let mut lookup_table = DataLookupTable::from_transaction(tx, utxos);
+    for (script_hash, script) in overrides {
+        lookup_table.override_script(*script_hash, script.clone());
+    }
+
+    prepare_redeemer(tx, utxos, slot_config, redeemer, &lookup_table).map_err(|e| anyhow!("{}", e))
+}
+
+/// The prepared program as if it had been loaded from a file, with readable
+/// binder names.
+pub fn loaded_program(prepared: &PreparedRedeemer, filename: String) -> Result<LoadedProgram> {
+    Ok(LoadedProgram {
+        filename,
+        program: loader::fix_names(prepared.program.clone())?,
+        source_map: Default::default(),
+    })
+}
+
+/// What the stepper shows of the transaction `prepared` was built from.
+pub fn script_context(prepared: &PreparedRedeemer) -> ScriptContextSnapshot {
+    ScriptContextSnapshot::from(&prepared.script_context)
+}
+
+/// `mainnet`, `preprod`, `preview` or `<zero_time>,<zero_slot>,<slot_length>`.
+pub fn parse_slot_config(s: &str) -> Result<SlotConfig> {
+    let (zero_time, zero_slot, slot_length) = match s.trim() {
+        "mainnet" => return Ok(SlotConfig::default()),
+        "preprod" => (1655769600000, 86400, 1000),
+        "preview" => (1666656000000, 0, 1000),
+        custom => {
+            let parts: Vec<&str> = custom.split(',').map(str::trim).collect();
+            let [zero_time, zero_slot, slot_length] = parts.as_slice() else {
+                bail!("invalid slot config '{}', expected mainnet, preprod, preview or <zero_time>,<zero_slot>,<slot_length>", s);
+            };
+
+            let invalid = |n: &str, e| anyhow!("invalid slot config value '{}': {}", n, e);
+            let number = |n: &str| n.parse::<u64>().map_err(|e| invalid(n, e));
+            let slot_length = slot_length.parse::<u32>().map_err(|e| invalid(slot_length, e))?;
+            (number(zero_time)?, number(zero_slot)?, slot_length)
+        }
+    };
+
+    Ok(SlotConfig {
+        zero_time,
+        zero_slot,
+        slot_length,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_redeemer_pointer() {
+        let pointer: RedeemerPointer = "spend:0".parse().unwrap();
+        assert_eq!(pointer, RedeemerPointer { tag: RedeemerTag::Spend, index: 0 });
+
+        assert_eq!("Reward:3".parse::<RedeemerPointer>().unwrap().to_string(), "withdraw:3");
+        assert!("spend".parse::<RedeemerPointer>().is_err());
+        assert!("burn:0".parse::<RedeemerPointer>().is_err());
+        assert!("mint:-1".parse::<RedeemerPointer>().is_err());
+
+        let script_override: ScriptOverride = "c4f241450001af08f3ddbaf9335db79883cbcd81071b8e3508de3055=build/mint.uplc"
+            .parse()
+            .unwrap();
+        assert_eq!(script_override.file, PathBuf::from("build/mint.uplc"));
+        assert!("c4f2=mint.uplc".parse::<ScriptOverride>().is_err());
+        assert!("mint.uplc".parse::<ScriptOverride>().is_err());
+
+        assert_eq!(parse_slot_config("preview").unwrap().zero_slot, 0);
+        assert_eq!(parse_slot_config("1,2,3").unwrap().slot_length, 3);
+        assert!(parse_slot_config("1,2").is_err());
+        assert!(parse_slot_config("0,0,4294967297").is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Whole-transaction evaluation for `eval-tx`: the phase one checks, then
+//! every redeemer's script with the budget it declares and the budget it
+//! actually needs.
+
+use std::{collections::HashMap, io::Write};
+
+use anyhow::Result;
+use pallas_crypto::hash::Hasher;
+use pallas_primitives::conway::{Language, MintedTx, ScriptHash};
+use serde::{Deserialize, Serialize};
+use uplc::{
+    machine::{self, cost_model::ExBudget, Trace},
+    tx::{self, eval, script_context::PlutusScript, DataLookupTable, ResolvedInput, SlotConfig},
+};
+
+use crate::{executor::MachineConfig, transaction::{self, RedeemerPointer}};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TxReport {
+    pub transaction_id: String,
+    /// Why phase one rejects the transaction: missing scripts, extra or
+    /// missing redeemers, missing datums. `None` when it passes.
+    pub phase_one_error: Option<String>,
+    pub redeemers: Vec<RedeemerReport>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RedeemerReport {
+    /// `<purpose>:<index>`, as `--redeemer` takes it.
+    pub redeemer: String,
+    /// The hash of the script that ran, which for an override is its own.
+    /// `None` when the script could not be resolved.
+    pub script_hash: Option<String>,
+    pub language: Option<Language>,
+    /// The ExUnits the transaction declares for this redeemer.
+    pub declared: ExBudget,
+    /// What the script spent, whether or not it succeeded.
+    pub actual: Option<ExBudget>,
+    pub passed: bool,
+    pub error: Option<String>,
+    pub traces: Vec<String>,
+}
+
+impl TxReport {
+    /// Run phase one, then phase two for every redeemer, with the cost model
+    /// and budget `config` gives for its script's language.
+    ///
+    /// Redeemers are evaluated even when phase one fails, so that every
+    /// problem shows up in one report.
+    pub fn evaluate(
+        tx: &MintedTx,
+        utxos: &[ResolvedInput],
+        slot_config: &SlotConfig,
+        config: impl Fn(Language) -> Result<MachineConfig>,
+    ) -> TxReport {
+        TxReport::evaluate_with_overrides(tx, utxos, slot_config, &HashMap::new(), config)
+    }
+
+    /// Like [`TxReport::evaluate`], but running the replacement of any
+    /// overridden script. Phase one still checks the original scripts.
+    pub fn evaluate_with_overrides(
+        tx: &MintedTx,
+        utxos: &[ResolvedInput],
+        slot_config: &SlotConfig,
+        overrides: &HashMap<ScriptHash, PlutusScript>,
+        config: impl Fn(Language) -> Result<MachineConfig>,
+    ) -> TxReport {
+        let mut lookup_table = DataLookupTable::from_transaction(tx, utxos);
+        let phase_one_error = tx::eval_phase_one(tx, utxos, &lookup_table)
+            .err()
+            .map(|e| e.to_string());
+
+        for (script_hash, script) in overrides {
+            lookup_table.override_script(*script_hash, script.clone());
+        }
+
+        let redeemers = transaction::redeemers(tx)
+            .iter()
+            .map(|redeemer| {
+                let declared = ExBudget {
+                    cpu: redeemer.ex_units.steps as i64,
+                    mem: redeemer.ex_units.mem as i64,
+                };
+                let mut report = RedeemerReport {
+                    redeemer: RedeemerPointer::from(redeemer).to_string(),
+                    script_hash: None,
+                    language: None,
+                    declared,
+                    actual: None,
+                    passed: false,
+                    error: None,
+                    traces: vec![],
+                };
+
+                let prepared = match eval::prepare_redeemer(tx, utxos, slot_config, redeemer, &lookup_table) {
+                    Ok(prepared) => prepared,
+                    Err(e) => {
+                        report.error = Some(e.to_string());
+                        return report;
+                    }
+                };
+                report.script_hash = Some(prepared.script_hash.to_string());
+                report.language = Some(prepared.language.clone());
+
+                let config = match config(prepared.language.clone()) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        report.error = Some(e.to_string());
+                        return report;
+                    }
+                };
+
+                // Phase two proper, as the ledger runs it, with the cost model
+                // of the script's language.
+                let evaluated = eval::eval_prepared_redeemer(
+                    prepared,
+                    redeemer,
+                    config.cost_models().as_ref(),
+                    &config.budget,
+                );
+
+                report.error = match evaluated {
+                    Ok((_, result)) => {
+                        let actual = result.cost();
+                        report.actual = Some(actual);
+                        report.traces = result.traces.iter().map(|trace| trace.to_string()).collect();
+
+                        if actual.cpu > declared.cpu || actual.mem > declared.mem {
+                            Some("the script needs more than the declared ExUnits".to_string())
+                        } else {
+                            None
+                        }
+                    }
+                    Err(e) => match machine_failure(e) {
+                        Ok((error, actual, traces)) => {
+                            report.actual = Some(actual);
+                            report.traces = traces.iter().map(|trace| trace.to_string()).collect();
+                            Some(error.to_string())
+                        }
+                        Err(e) => Some(e.to_string()),
+                    },
+                };
+                report.passed = report.error.is_none();
+
+                report
+            })
+            .collect();
+
+        TxReport {
+            transaction_id: hex::encode(Hasher::<256>::hash(tx.transaction_body.raw_cbor())),
+            phase_one_error,
+            redeemers,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.phase_one_error.is_none() && self.redeemers.iter().all(|r| r.passed)
+    }
+
+    pub fn write_json<W: Write>(&self, mut output: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut output, self)?;
+        writeln!(output)?;
+        Ok(())
+    }
+
+    /// One row per redeemer, followed by its error and traces.
+    pub fn write_table<W: Write>(&self, mut output: W) -> Result<()> {
+        writeln!(output, "Transaction {}", self.transaction_id)?;
+        match &self.phase_one_error {
+            None => writeln!(output, "Phase one: ✓ passed")?,
+            Some(error) => writeln!(output, "Phase one: ✗ {}", error)?,
+        }
+        writeln!(output)?;
+
+        if self.redeemers.is_empty() {
+            writeln!(output, "No redeemers, no scripts to run")?;
+            return Ok(());
+        }
+
+        writeln!(
+            output,
+            "{:<12} {:<56} {:<4} {:>25} {:>25}  RESULT",
+            "REDEEMER", "SCRIPT HASH", "LANG", "DECLARED CPU / MEM", "ACTUAL CPU / MEM"
+        )?;
+
+        let budget = |b: &ExBudget| format!("{} / {}", b.cpu, b.mem);
+
+        for report in &self.redeemers {
+            let language = match report.language {
+                Some(Language::PlutusV1) => "V1",
+                Some(Language::PlutusV2) => "V2",
+                Some(Language::PlutusV3) => "V3",
+                None => "-",
+            };
+
+            writeln!(
+                output,
+                "{:<12} {:<56} {:<4} {:>25} {:>25}  {}",
+                report.redeemer,
+                report.script_hash.as_deref().unwrap_or("-"),
+                language,
+                budget(&report.declared),
+                report.actual.as_ref().map(budget).unwrap_or_else(|| "-".to_string()),
+                if report.passed { "✓ pass" } else { "✗ fail" }
+            )?;
+
+            if let Some(error) = &report.error {
+                for (i, line) in error.lines().map(str::trim).filter(|l| !l.is_empty()).enumerate() {
+                    writeln!(output, "  {} {}", if i == 0 { "✗" } else { " " }, line)?;
+                }
+            }
+            for trace in &report.traces {
+                writeln!(output, "  📝 {}", trace)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What the script spent and traced before it failed, when the failure is
+/// the script's own rather than the redeemer's setup.
+fn machine_failure(error: tx::error::Error) -> Result<(machine::Error, ExBudget, Vec<Trace>), tx::error::Error> {
+    match error {
+        tx::error::Error::RedeemerError { err, .. } => machine_failure(*err),
+        tx::error::Error::Machine(error, spent, traces) => Ok((error, spent, traces)),
+        other => Err(other),
+    }
+}
@@ -0,0 +1,285 @@
+//! Hand-editable UTxO fixtures: the JSON `cardano-cli query utxo
+//! --output-json` prints, with reference scripts given as files. The same
+//! fixture may be written in YAML.
+//!
+//! ```json
+//! {
+//!   "975c17a4…6a50#2": {
+//!     "address": "addr_test1vzmvs72wnfazvkv5gzjdpltee5rkgng4j9llzd5578m8ydgkp6edr",
+//!     "value": { "lovelace": 10000000, "c4f24145…3055": { "": 10 } },
+//!     "inlineDatum": { "constructor": 0, "fields": [{ "int": 42 }] },
+//!     "referenceScript": { "file": "validators/mint.flat", "language": "v2" }
+//!   }
+//! }
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use pallas_addresses::Address;
+use pallas_codec::utils::{CborWrap, NonEmptyKeyValuePairs, PositiveCoin};
+use pallas_primitives::{
+    PlutusScript,
+    conway::{
+        DatumOption, Language, PostAlonzoTransactionOutput, ScriptRef,
+        TransactionInput, TransactionOutput, Value,
+    },
+};
+use serde::Deserialize;
+use uplc::tx::ResolvedInput;
+
+use crate::{executor, loader, parameters};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Utxo {
+    address: String,
+    value: serde_json::Value,
+    #[serde(default, rename = "datumhash")]
+    datum_hash: Option<String>,
+    #[serde(default)]
+    inline_datum: Option<serde_json::Value>,
+    #[serde(default)]
+    inline_datum_raw: Option<String>,
+    /// Printed by `cardano-cli` next to `inlineDatum`; the hash follows from
+    /// the datum, so it is not read.
+    #[serde(default, rename = "inlineDatumhash")]
+    _inline_datum_hash: Option<String>,
+    #[serde(default)]
+    reference_script: Option<ReferenceScript>,
+}
+
+/// Any script file the loader reads, relative to the fixture. Without a
+/// language, the one the file declares is used, or PlutusV2.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReferenceScript {
+    File(PathBuf),
+    WithLanguage { file: PathBuf, language: String },
+}
+
+/// Read a UTxO fixture, JSON or YAML by extension, resolving reference
+/// script files against its directory.
+pub fn read(path: &Path) -> Result<Vec<ResolvedInput>> {
+    let text = fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml" | "yml") => from_yaml(&text, base),
+        _ => from_json(&text, base),
+    }
+    .with_context(|| format!("invalid UTxO fixture {}", path.display()))
+}
+
+pub fn from_json(json: &str, base: &Path) -> Result<Vec<ResolvedInput>> {
+    let utxos = serde_json::from_str(json).context("expected an object from <tx hash>#<index> to output")?;
+
+    resolve_all(&utxos, base)
+}
+
+/// The JSON fixture written in YAML. Hex keys that YAML would read as numbers
+/// (a policy id of only digits, say) must be quoted.
+pub fn from_yaml(yaml: &str, base: &Path) -> Result<Vec<ResolvedInput>> {
+    let utxos = serde_yaml::from_str(yaml).context("expected a mapping from <tx hash>#<index> to output")?;
+
+    resolve_all(&utxos, base)
+}
+
+fn resolve_all(utxos: &serde_json::Map<String, serde_json::Value>, base: &Path) -> Result<Vec<ResolvedInput>> {
+    utxos
+        .iter()
+        .map(|(reference, utxo)| {
+            resolve(reference, utxo, base).with_context(|| format!("in UTxO {}", reference))
+        })
+        .collect()
+}
+
+fn resolve(reference: &str, utxo: &serde_json::Value, base: &Path) -> Result<ResolvedInput> {
+    let (transaction_id, index) = reference
+        .split_once('#')
+        .ok_or_else(|| anyhow!("expected <tx hash>#<index>"))?;
+    let input = TransactionInput {
+        transaction_id: transaction_id
+            .parse()
+            .map_err(|_| anyhow!("invalid transaction hash '{}', expected 64 hex digits", transaction_id))?,
+        index: index.parse().map_err(|_| anyhow!("invalid output index '{}'", index))?,
+    };
+
+    let utxo = Utxo::deserialize(utxo)?;
+
+    let address = Address::from_bech32(&utxo.address)
+        .or_else(|_| Address::from_hex(&utxo.address))
+        .map_err(|e| anyhow!("invalid address '{}': {}", utxo.address, e))?;
+
+    let inline_datum = match (&utxo.inline_datum_raw, &utxo.inline_datum) {
+        (Some(raw), _) => Some(parameters::parse(raw).context("invalid inlineDatumRaw")?),
+        (None, Some(json)) => Some(loader::data_from_json(json).context("invalid inlineDatum")?),
+        (None, None) => None,
+    };
+    let datum_option = match (inline_datum, &utxo.datum_hash) {
+        (Some(_), Some(_)) => bail!("an output has either an inline datum or a datumhash, not both"),
+        (Some(data), None) => Some(DatumOption::Data(CborWrap(data))),
+        (None, Some(hash)) => Some(DatumOption::Hash(
+            hash.parse()
+                .map_err(|_| anyhow!("invalid datumhash '{}', expected 64 hex digits", hash))?,
+        )),
+        (None, None) => None,
+    };
+
+    let script_ref = utxo
+        .reference_script
+        .map(|script| reference_script(script, base))
+        .transpose()?
+        .map(CborWrap);
+
+    Ok(ResolvedInput {
+        input,
+        output: TransactionOutput::PostAlonzo(PostAlonzoTransactionOutput {
+            address: address.to_vec().into(),
+            value: value(&utxo.value)?,
+            datum_option,
+            script_ref,
+        }),
+    })
+}
+
+/// `10000000`, or `{"lovelace": 10000000, "<policy id>": {"<asset name hex>": 10}}`.
+fn value(value: &serde_json::Value) -> Result<Value> {
+    let quantity = |q: &serde_json::Value, at: &str| {
+        q.as_u64()
+            .ok_or_else(|| anyhow!("value{}: expected a non-negative integer, got {}", at, q))
+    };
+
+    let fields = match value {
+        serde_json::Value::Object(fields) => fields,
+        lovelace => return Ok(Value::Coin(quantity(lovelace, "")?)),
+    };
+
+    let lovelace = fields
+        .get("lovelace")
+        .map(|q| quantity(q, ".lovelace"))
+        .transpose()?
+        .unwrap_or(0);
+
+    let mut policies = vec![];
+    for (policy_id, assets) in fields.iter().filter(|(key, _)| *key != "lovelace") {
+        let at = format!(".{}", policy_id);
+        let policy = policy_id
+            .parse()
+            .map_err(|_| anyhow!("value{}: expected lovelace or a policy id of 56 hex digits", at))?;
+        let assets = assets
+            .as_object()
+            .ok_or_else(|| anyhow!("value{}: expected an object from asset name (hex) to quantity", at))?;
+
+        let mut tokens = vec![];
+        for (name, q) in assets {
+            let at = format!("{}.{}", at, name);
+            let name = hex::decode(name).map_err(|_| anyhow!("value{}: asset names are hex", at))?;
+            let q = PositiveCoin::try_from(quantity(q, &at)?)
+                .map_err(|_| anyhow!("value{}: quantities must be positive", at))?;
+            tokens.push((name.into(), q));
+        }
+
+        policies.push((
+            policy,
+            NonEmptyKeyValuePairs::try_from(tokens).map_err(|_| anyhow!("value{}: no assets", at))?,
+        ));
+    }
+
+    Ok(match NonEmptyKeyValuePairs::try_from(policies) {
+        Ok(assets) => Value::Multiasset(lovelace, assets),
+        Err(_) => Value::Coin(lovelace),
+    })
+}
+
+fn reference_script(script: ReferenceScript, base: &Path) -> Result<ScriptRef> {
+    let (file, language) = match script {
+        ReferenceScript::File(file) => (file, None),
+        ReferenceScript::WithLanguage { file, language } => (file, Some(executor::parse_language(&language)?)),
+    };
+
+    let path = base.join(&file);
+    let (bytes, declared) = loader::read_script_with_language(&path)
+        .with_context(|| format!("invalid reference script {}", path.display()))?;
+
+    let language = match (language, declared) {
+        (Some(language), Some(declared)) if language != declared => {
+            bail!("reference script {} is {:?}, not {:?}", path.display(), declared, language)
+        }
+        (language, declared) => language.or(declared).unwrap_or(Language::PlutusV2),
+    };
+    let bytes = bytes.into();
+
+    Ok(match language {
+        Language::PlutusV1 => ScriptRef::PlutusV1Script(PlutusScript(bytes)),
+        Language::PlutusV2 => ScriptRef::PlutusV2Script(PlutusScript(bytes)),
+        Language::PlutusV3 => ScriptRef::PlutusV3Script(PlutusScript(bytes)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_errors_name_the_utxo_and_field() {
+        let tx = "975c17a4fed0051be622328efa548e206657d2b65a19224bf6ff8132571e6a50";
+        let address = "addr_test1vzmvs72wnfazvkv5gzjdpltee5rkgng4j9llzd5578m8ydgkp6edr";
+        let fixture = |utxo: &str| format!("{{\"{}#0\": {}}}", tx, utxo);
+        let error = |utxo: &str| format!("{:#}", from_json(&fixture(utxo), Path::new(".")).unwrap_err());
+
+        let utxos = from_json(
+            &fixture(&format!(r#"{{"address": "{}", "value": 5, "datumhash": "{}"}}"#, address, tx)),
+            Path::new("."),
+        )
+        .unwrap();
+        assert_eq!(utxos[0].input.index, 0);
+
+        assert!(error(&format!(r#"{{"address": "{}", "value": -1}}"#, address)).contains("in UTxO"));
+        assert!(error(&format!(r#"{{"address": "{}", "value": {{"ab": {{}}}}}}"#, address)).contains("value.ab"));
+        assert!(error(r#"{"address": "addr_nope", "value": 1}"#).contains("invalid address"));
+        assert!(error(&format!(r#"{{"address": "{}", "value": 1, "datum": 1}}"#, address)).contains("unknown field"));
+
+        let bad_datum = format!(
+            r#"{{"address": "{}", "value": 1, "inlineDatum": {{"list": [{{"int": "x"}}]}}}}"#,
+            address
+        );
+        assert!(error(&bad_datum).contains("data.list[0].int"), "{}", error(&bad_datum));
+
+        let bad_raw = format!(r#"{{"address": "{}", "value": 1, "inlineDatumRaw": "d8"}}"#, address);
+        assert!(error(&bad_raw).contains("invalid inlineDatumRaw"), "{}", error(&bad_raw));
+        assert!(!error(&bad_raw).contains("parameter 0"), "{}", error(&bad_raw));
+    }
+
+    #[test]
+    fn test_yaml_fixtures_read_like_json() {
+        let tx = "975c17a4fed0051be622328efa548e206657d2b65a19224bf6ff8132571e6a50";
+        let address = "addr_test1vzmvs72wnfazvkv5gzjdpltee5rkgng4j9llzd5578m8ydgkp6edr";
+        let policy = "c4f241450001af08f3ddbaf9335db79883cbcd81071b8e3508de3055";
+
+        let json = from_json(
+            &format!(
+                r#"{{"{}#2": {{"address": "{}", "value": {{"lovelace": 10000000, "{}": {{"": 10}}}},
+                    "inlineDatum": {{"constructor": 0, "fields": [{{"int": 42}}]}}}}}}"#,
+                tx, address, policy
+            ),
+            Path::new("."),
+        )
+        .unwrap();
+        let yaml = from_yaml(
+            &format!(
+                "{}#2:\n  address: {}\n  value:\n    lovelace: 10000000\n    {}:\n      \"\": 10\n  \
+                 inlineDatum:\n    constructor: 0\n    fields:\n      - int: 42\n",
+                tx, address, policy
+            ),
+            Path::new("."),
+        )
+        .unwrap();
+
+        assert_eq!(yaml, json);
+        assert!(format!("{:#}", from_yaml("- 1", Path::new(".")).unwrap_err()).contains("expected a mapping"));
+    }
+}
@@ -0,0 +1,55 @@
+// tests/blueprint_tests.rs
+use std::path::Path;
+
+use cek_debugger::executor::execute_program;
+use cek_debugger::loader::{apply_arguments, load_program, load_programs_from_file};
+
+const BLUEPRINT: &str = "tests/fixtures/plutus.json";
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+async fn run(args: &[String]) -> anyhow::Result<()> {
+    let selected = load_program(Path::new(BLUEPRINT), Some("vault.spend"), args).await?;
+    let applied = apply_arguments(selected.program, selected.arguments)?;
+    let snapshots = execute_program(applied.program)?;
+    match snapshots.last().map(|s| s.state_type.as_str()) {
+        Some("Done") => Ok(()),
+        state => anyhow::bail!("ended in {:?}", state),
+    }
+}
+
+#[tokio::test]
+async fn test_blueprint_lists_every_validator() {
+    let programs = load_programs_from_file(Path::new(BLUEPRINT)).await.unwrap();
+    let names: Vec<_> = programs.iter().map(|p| p.filename.as_str()).collect();
+    assert_eq!(names, [
+        "tests/fixtures/plutus.json#vault.spend",
+        "tests/fixtures/plutus.json#vault.unparameterized"
+    ]);
+}
+
+#[tokio::test]
+async fn test_blueprint_arguments_are_typed_by_schema() {
+    let datum = r#"{"owner": "abcd", "amount": 5}"#;
+    run(&args(&["\"abcd\"", datum, "true", "d87980"])).await.unwrap();
+
+    let other_owner = r#"{"owner": "ab", "amount": 5}"#;
+    assert!(run(&args(&["\"abcd\"", other_owner, "true", "d87980"])).await.is_err());
+
+    let error = run(&args(&["\"abcd\"", r#"{"owner": "abcd", "amount": "five"}"#])).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("$.amount"), "{:#}", error);
+}
+
+#[tokio::test]
+async fn test_blueprint_validator_must_be_picked() {
+    let error = load_program(Path::new(BLUEPRINT), None, &[]).await.err().unwrap();
+    assert!(error.to_string().contains("vault.spend, vault.unparameterized"), "{}", error);
+
+    let error = load_program(Path::new(BLUEPRINT), Some("vault.mint"), &[]).await.err().unwrap();
+    assert!(error.to_string().contains("vault.mint"), "{}", error);
+
+    let uplc = Path::new("crates/uplc/test_data/basic/integer/integer.uplc");
+    assert!(load_program(uplc, Some("vault.spend"), &[]).await.is_err());
+}
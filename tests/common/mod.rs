@@ -0,0 +1,77 @@
+//! The harness the integration tests share: the mint transaction fixture,
+//! scratch directories and the `cek-debugger` binary.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A preview transaction minting with a PlutusV2 policy, and the UTxOs it spends.
+pub const TX: &str = "tests/fixtures/mint.tx";
+pub const UTXOS: &str = "tests/fixtures/mint.utxos";
+
+/// The hash of the transaction's minting policy, for `--override`.
+pub const MINT: &str = "c4f241450001af08f3ddbaf9335db79883cbcd81071b8e3508de3055";
+
+/// The binary under test.
+pub fn cek_debugger() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_cek-debugger"))
+}
+
+/// Run `command` with `input` on stdin, capturing stdout and stderr. The
+/// process may exit before reading all of it.
+pub fn run_with_input(command: &mut Command, input: &[u8]) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let _ = child.stdin.take().unwrap().write_all(input);
+    child.wait_with_output().unwrap()
+}
+
+/// A scratch directory of its own, removed when dropped, so tests running in
+/// parallel never share files.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "cek-debugger-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    /// Write `name` in the directory, returning its path.
+    pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
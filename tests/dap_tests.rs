@@ -0,0 +1,286 @@
+mod common;
+
+use std::{
+    io::BufReader,
+    process::{Child, ChildStdin, ChildStdout, Stdio},
+};
+
+use cek_debugger::dap::{read_message, write_message};
+use serde_json::{Value, json};
+use uplc::{ast::{DeBruijn, Program}, parser};
+
+use common::{cek_debugger, TempDir};
+
+const TWICE: &str = "(program 1.0.0 [(lam f [f [f (con integer 1)]]) (lam x [(force (builtin trace)) (con string \"add\") [(builtin addInteger) x (con integer 1)]])])";
+
+/// A scripted DAP client driving `cek-debugger dap` over stdio.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    seq: i64,
+    events: Vec<Value>,
+}
+
+impl Client {
+    fn spawn() -> Client {
+        let mut child = cek_debugger()
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        Client {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            seq: 0,
+            events: vec![],
+        }
+    }
+
+    /// Send a request and return its response, collecting interleaved events.
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let request = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        write_message(&mut self.stdin, &request).unwrap();
+
+        loop {
+            let message = read_message(&mut self.stdout).unwrap().expect("adapter exited");
+            if message["type"] == "response" && message["request_seq"] == self.seq {
+                assert_eq!(message["success"], true, "{} failed: {}", command, message);
+                return message;
+            }
+            self.events.push(message);
+        }
+    }
+
+    /// Wait for the next event with the given name.
+    fn event(&mut self, name: &str) -> Value {
+        if let Some(i) = self.events.iter().position(|e| e["event"] == name) {
+            return self.events.remove(i);
+        }
+
+        loop {
+            let message = read_message(&mut self.stdout).unwrap().expect("adapter exited");
+            if message["event"] == name {
+                return message;
+            }
+            self.events.push(message);
+        }
+    }
+
+    fn current_frame(&mut self) -> Value {
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        trace["body"]["stackFrames"][0].clone()
+    }
+}
+
+/// Launch `code`, written to a directory that lives as long as the session.
+fn launch(client: &mut Client, name: &str, code: &str) -> TempDir {
+    let dir = TempDir::new("dap");
+    let path = dir.write(&format!("{}.uplc", name), code);
+
+    let initialize = client.request("initialize", json!({ "adapterID": "cek" }));
+    assert_eq!(initialize["body"]["supportsStepBack"], true);
+    client.event("initialized");
+
+    client.request("launch", json!({ "program": path, "stopOnEntry": true }));
+    dir
+}
+
+#[test]
+fn breakpoints_stepping_and_reverse_continue() {
+    let mut client = Client::spawn();
+    let _dir = launch(&mut client, "twice", TWICE);
+
+    let breakpoints = client.request(
+        "setFunctionBreakpoints",
+        json!({ "breakpoints": [{ "name": "addInteger" }] }),
+    );
+    let id = breakpoints["body"]["breakpoints"][0]["id"].clone();
+    assert_eq!(breakpoints["body"]["breakpoints"][0]["verified"], true);
+
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.event("stopped")["body"]["reason"], "entry");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    let first = client.event("stopped");
+    assert_eq!(first["body"]["reason"], "breakpoint");
+    assert_eq!(first["body"]["hitBreakpointIds"], json!([id]));
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    let frames = trace["body"]["stackFrames"].as_array().unwrap();
+    assert!(frames.len() > 1);
+    assert!(frames[1]["name"].as_str().unwrap().starts_with("apply"));
+
+    let scopes = client.request("scopes", json!({ "frameId": 0 }));
+    let machine = scopes["body"]["scopes"][1]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": machine }));
+    let step = variables["body"]["variables"][0].clone();
+    assert_eq!(step["name"], "step");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["body"]["reason"], "breakpoint");
+    assert_eq!(client.event("output")["body"]["output"], "add\n");
+    let second = client.current_frame();
+
+    client.request("stepBack", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["body"]["reason"], "step");
+    assert_ne!(client.current_frame(), second);
+
+    client.request("reverseContinue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["body"]["reason"], "breakpoint");
+    let variables = client.request("variables", json!({ "variablesReference": machine }));
+    assert_eq!(variables["body"]["variables"][0], step);
+
+    client.request("disconnect", json!({}));
+    client.event("terminated");
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn breakpoints_set_before_launch_apply_at_launch() {
+    // An Aiken export whose source map puts step 2 on line 20 of
+    // validators/twice.ak, and step 3 on the same line of another twice.ak.
+    let program: Program<DeBruijn> = parser::program(TWICE).unwrap().try_into().unwrap();
+    let export = json!({
+        "compiledCode": hex::encode(program.to_cbor().unwrap()),
+        "sourceMap": { "1": "validators/twice.ak:2", "2": "validators/twice.ak:20:5", "3": "lib/twice.ak:20" },
+    });
+    let dir = TempDir::new("dap-pending");
+    let path = dir.write("twice.json", export.to_string());
+
+    // The order clients use: breakpoints once `initialized` arrives, then
+    // launch and configurationDone.
+    let mut client = Client::spawn();
+    client.request("initialize", json!({ "adapterID": "cek" }));
+    client.event("initialized");
+
+    let source = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": "/home/dev/vault/validators/twice.ak" }, "breakpoints": [{ "line": 20 }] }),
+    );
+    let source = &source["body"]["breakpoints"][0];
+    assert_eq!(source["verified"], false);
+    assert_eq!(source["line"], 20);
+
+    let function = client.request("setFunctionBreakpoints", json!({ "breakpoints": [{ "name": "addInteger" }] }));
+    let function = &function["body"]["breakpoints"][0];
+    assert_eq!(function["verified"], false);
+
+    client.request("launch", json!({ "program": path, "stopOnEntry": true }));
+    for _ in 0..2 {
+        let changed = client.event("breakpoint");
+        assert_eq!(changed["body"]["reason"], "changed");
+        assert_eq!(changed["body"]["breakpoint"]["verified"], true, "{}", changed);
+    }
+
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.event("stopped")["body"]["reason"], "entry");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["body"]["hitBreakpointIds"], json!([source["id"]]));
+    let frame = client.current_frame();
+    assert_eq!(frame["line"], 20);
+    assert_eq!(frame["source"]["path"], "validators/twice.ak");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["body"]["hitBreakpointIds"], json!([function["id"]]));
+
+    client.request("disconnect", json!({}));
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn evaluation_result_and_environment() {
+    let mut client = Client::spawn();
+    let _dir = launch(&mut client, "env", "(program 1.0.0 [(lam x [(lam y x) (con integer 2)]) (con integer 1)])");
+
+    client.request("setExceptionBreakpoints", json!({ "filters": [] }));
+    client.request("configurationDone", json!({}));
+    client.event("stopped");
+
+    let mut bindings = vec![];
+    for _ in 0..12 {
+        client.request("next", json!({ "threadId": 1 }));
+        client.event("stopped");
+        let variables = client.request("variables", json!({ "variablesReference": 1 }));
+        bindings.push(variables["body"]["variables"].as_array().unwrap().len());
+    }
+    assert_eq!(bindings.iter().max(), Some(&2));
+
+    client.request("continue", json!({ "threadId": 1 }));
+    client.event("stopped");
+    let result = client.event("output");
+    assert!(result["body"]["output"].as_str().unwrap().contains("Result: (con integer 1)"));
+
+    client.request("disconnect", json!({}));
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn data_unfolds_into_named_fields() {
+    let key = "00".repeat(28);
+    let ctx = format!(
+        "Constr 0 [Constr 0 [List [], List [], List [Constr 0 [Constr 0 [Constr 0 [B #{}], Constr 1 []], Map [(B #, Map [(B #, I 5)])], Constr 0 [], Constr 1 []]], Map [], Map [], List [], Map [], Constr 0 [Constr 0 [Constr 1 [I 1714564800000], Constr 1 []], Constr 0 [Constr 2 [], Constr 1 []]], List [], Map [], Map [], Constr 0 [B #00]], Constr 0 [B #00]]",
+        key
+    );
+    let mut client = Client::spawn();
+    let _dir = launch(&mut client, "data", &format!("(program 1.0.0 [(lam ctx [(builtin unConstrData) ctx]) (con data ({}))])", ctx));
+
+    client.request("configurationDone", json!({}));
+    client.event("stopped");
+
+    let ctx = loop {
+        client.request("next", json!({ "threadId": 1 }));
+        client.event("stopped");
+        let variables = client.request("variables", json!({ "variablesReference": 1 }));
+        if let Some(ctx) = variables["body"]["variables"].get(0) {
+            break ctx.clone();
+        }
+    };
+    assert!(ctx["name"].as_str().unwrap().starts_with("ctx"));
+    assert_eq!(ctx["value"], "ScriptContext");
+
+    let unfold = |client: &mut Client, variable: &Value, name: &str| -> Value {
+        let children = client.request("variables", json!({ "variablesReference": variable["variablesReference"] }));
+        children["body"]["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|child| child["name"] == name)
+            .cloned()
+            .unwrap_or_else(|| panic!("no {} in {}", name, children))
+    };
+
+    let tx_info = unfold(&mut client, &ctx, "tx_info");
+
+    // Listing the same scope again during a stop hands out the same references.
+    let again = client.request("variables", json!({ "variablesReference": 1 }));
+    assert_eq!(again["body"]["variables"][0], ctx);
+    assert_eq!(unfold(&mut client, &ctx, "tx_info"), tx_info);
+
+    let outputs = unfold(&mut client, &tx_info, "outputs");
+    let output = unfold(&mut client, &outputs, "[0]");
+    assert!(unfold(&mut client, &output, "address")["value"].as_str().unwrap().starts_with("addr1"));
+    assert_eq!(unfold(&mut client, &output, "value")["value"], "5 lovelace");
+
+    let range = unfold(&mut client, &tx_info, "valid_range");
+    let lower = unfold(&mut client, &range, "lower_bound");
+    let bound = unfold(&mut client, &lower, "bound_type");
+    let time = unfold(&mut client, &bound, "time");
+    assert_eq!(time["value"], "2024-05-01T12:00:00Z (1714564800000)");
+    assert_eq!(time["variablesReference"], 0);
+
+    client.request("disconnect", json!({}));
+    assert!(client.child.wait().unwrap().success());
+}
@@ -16,4 +16,52 @@ fn test_simple_integer_program() {
 fn test_lambda_application() {
     let _code = "(program 1.0.0 [(lam x x) (con integer 5)])";
     // ... test execution
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_machine_config_selects_budget_and_cost_model() {
+    use cek_debugger::executor::{execute_program_with, start_session_with, MachineConfig};
+    use pallas_primitives::conway::Language;
+    use uplc::machine::cost_model::ExBudget;
+
+    let code = "(program 1.0.0 [(builtin addInteger) (con integer 1) (con integer 2)])";
+    let program: Program<NamedDeBruijn> = parser::program(code).unwrap().try_into().unwrap();
+
+    let starved = MachineConfig::new(Language::PlutusV3).with_budget(ExBudget { mem: 10, cpu: 10 });
+    assert!(execute_program_with(program.clone(), &starved).is_err());
+
+    let free = MachineConfig::new(Language::PlutusV1).with_cost_model(vec![0; 166]).unwrap();
+    let mut session = start_session_with(program, &free).unwrap();
+    session.run_to_end();
+    assert!(matches!(session.outcome(), Some(Ok(_))));
+    assert_eq!(session.spent(), ExBudget { mem: 0, cpu: 0 });
+}
+
+#[test]
+fn test_default_cost_model_follows_the_language() {
+    use cek_debugger::executor::{start_session_with, MachineConfig};
+    use pallas_primitives::conway::Language;
+    use uplc::machine::{cost_model::{CostModel, ExBudget}, Machine};
+
+    // Division is priced differently by the V2 and V3 models.
+    let code = "(program 1.0.0 [(builtin divideInteger) (con integer 123456789012345678901234567890123456789) (con integer 12345)])";
+    let program: Program<NamedDeBruijn> = parser::program(code).unwrap().try_into().unwrap();
+
+    let models = [
+        (Language::PlutusV1, CostModel::v1()),
+        (Language::PlutusV2, CostModel::v2()),
+        (Language::PlutusV3, CostModel::v3()),
+    ];
+    let mut spent = vec![];
+    for (language, costs) in models {
+        let mut machine = Machine::new(language.clone(), costs, ExBudget::default(), 1);
+        machine.run(program.term.clone()).unwrap();
+
+        let mut session = start_session_with(program.clone(), &MachineConfig::new(language)).unwrap();
+        session.run_to_end();
+        assert_eq!(session.spent(), ExBudget::default() - machine.ex_budget);
+        spent.push(session.spent());
+    }
+
+    assert_ne!(spent[1], spent[2]);
+}
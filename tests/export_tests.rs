@@ -0,0 +1,119 @@
+mod common;
+
+use cek_debugger::{
+    executor::start_session,
+    export::{Detail, ExecutionTrace, Format, StepSelection, SCHEMA_VERSION},
+};
+use uplc::{ast::{NamedDeBruijn, Program}, parser};
+
+use common::{cek_debugger, run_with_input, TempDir};
+
+const TRACED: &str = "(program 1.0.0 [(force (builtin trace)) (con string \"hello\") [(builtin addInteger) (con integer 1) (con integer 2)]])";
+
+fn trace_of(code: &str, selection: &StepSelection) -> ExecutionTrace {
+    let program: Program<NamedDeBruijn> = parser::program(code).unwrap().try_into().unwrap();
+    let mut session = start_session(program);
+    ExecutionTrace::collect("test.uplc", &mut session, selection)
+}
+
+#[test]
+fn test_trace_round_trips_in_both_formats() {
+    let trace = trace_of(TRACED, &StepSelection::All);
+    assert_eq!(trace.schema_version, SCHEMA_VERSION);
+    assert_eq!(trace.result.as_deref(), Some("(con integer 3)"));
+    assert_eq!(trace.traces, vec!["hello"]);
+    assert_eq!(trace.steps.len(), trace.total_steps);
+
+    for format in [Format::Json, Format::Ndjson] {
+        let mut bytes = vec![];
+        trace.write(format, &mut bytes).unwrap();
+        assert_eq!(ExecutionTrace::read(bytes.as_slice()).unwrap(), trace);
+    }
+}
+
+#[test]
+fn test_trace_filters_steps_and_reports_errors() {
+    let trace = trace_of("(program 1.0.0 [(lam x (error)) (con integer 1)])", &StepSelection::Range(1..=2));
+    assert_eq!(trace.steps.iter().map(|s| s.step).collect::<Vec<_>>(), vec![1, 2]);
+    assert!(trace.result.is_none());
+    assert!(trace.error.is_some());
+
+    let newer = serde_json::to_string(&ExecutionTrace { schema_version: SCHEMA_VERSION + 1, ..trace }).unwrap();
+    assert!(ExecutionTrace::read(newer.as_bytes()).is_err());
+}
+
+#[test]
+fn test_batch_mode_writes_ndjson() {
+    let dir = TempDir::new("export");
+    let path = dir.write("traced.uplc", TRACED);
+
+    let output = cek_debugger()
+        .arg(&path)
+        .args(["--format", "ndjson", "--steps", "none"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 2);
+
+    let trace = ExecutionTrace::read(stdout.as_bytes()).unwrap();
+    assert!(trace.steps.is_empty());
+    assert_eq!(trace.result.as_deref(), Some("(con integer 3)"));
+
+    let summary = cek_debugger()
+        .arg("crates/uplc/test_data/basic/integer/integer.uplc")
+        .arg("--no-interactive")
+        .output()
+        .unwrap();
+    assert!(summary.status.success());
+    assert!(String::from_utf8(summary.stdout).unwrap().contains("Result:"));
+}
+
+#[test]
+fn test_long_renderings_are_cut_unless_full() {
+    let long = format!("(program 1.0.0 [(lam s s) (con string \"{}\")])", "x".repeat(500));
+    let program: Program<NamedDeBruijn> = parser::program(&long).unwrap().try_into().unwrap();
+
+    let full = ExecutionTrace::collect("long.uplc", &mut start_session(program.clone()), &StepSelection::All);
+    let cut = ExecutionTrace::collect_with("long.uplc", &mut start_session(program), &StepSelection::All, Detail::Truncated(40));
+
+    assert!(full.steps[0].term.len() > 500);
+    assert_eq!(cut.steps.len(), full.steps.len());
+    for (cut, full) in cut.steps.iter().zip(&full.steps) {
+        assert_eq!((cut.step, cut.cpu, cut.mem), (full.step, full.cpu, full.mem));
+        assert!(cut.term.chars().count() < 70, "{}", cut.term);
+        assert!(full.term.starts_with(cut.term.split('…').next().unwrap()));
+    }
+    assert!(cut.steps[0].term.ends_with("more characters)"), "{}", cut.steps[0].term);
+    assert_eq!(cut.result, full.result);
+}
+
+#[test]
+fn test_replay_opens_an_exported_trace() {
+    let dir = TempDir::new("export-replay");
+    let script = dir.write("traced.uplc", TRACED);
+    let trace = dir.join("trace.json");
+
+    let exported = cek_debugger()
+        .arg(&script)
+        .args(["--format", "json", "--steps", "2..4", "--output"])
+        .arg(&trace)
+        .output()
+        .unwrap();
+    assert!(exported.status.success());
+
+    let output = run_with_input(cek_debugger().arg("replay").arg(&trace), b"J\n3\nJ\n0\nQ\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("3 of"), "{}", stdout);
+    assert!(stdout.contains("Step 0003"));
+    assert!(stdout.contains("Invalid step (2..=4)"));
+
+    let live = cek_debugger()
+        .args(["replay", "--live"])
+        .arg(&trace)
+        .output()
+        .unwrap();
+    assert!(!live.status.success());
+}
@@ -1,3 +1,5 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
@@ -34,6 +36,113 @@ mod full_workflow_tests {
         let applied = apply_parameters(program, params).unwrap();
         let snapshots = execute_program(applied.program).unwrap();
         
-        assert!(snapshots.len() > 0);
+        assert!(!snapshots.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_full_workflow_with_term_arguments() {
+        use cek_debugger::loader::{apply_arguments, parse_argument, LoadedProgram};
+
+        let code = "(program 1.0.0 (lam f (lam n [f [(builtin addInteger) n (con integer 1)]])))";
+        let program = LoadedProgram {
+            filename: "helper.uplc".to_string(),
+            program: uplc::parser::program(code).unwrap().try_into().unwrap(),
+            source_map: Default::default(),
+        };
+
+        let square = parse_argument(0, "(lam x [(builtin multiplyInteger) x x])".to_string()).unwrap();
+        let four = parse_argument(1, "(con integer 4)".to_string()).unwrap();
+        let applied = apply_arguments(program, vec![square, four]).unwrap();
+        let snapshots = execute_program(applied.program).unwrap();
+
+        let last = snapshots.last().unwrap();
+        assert_eq!(last.state_type, "Done");
+        assert!(last.term.contains("25"), "{}", last.term);
+    }
+}
+
+#[cfg(test)]
+mod content_tests {
+    use std::path::Path;
+    use cek_debugger::loader::{load_program, load_programs_from_file, read_script, read_script_with_language};
+    use pallas_primitives::conway::Language;
+
+    use crate::common::TempDir;
+
+    const COMPILED_CODE: &str = "581c01000022225333573466e3cdd70021bae357426aae78dd50018a4c2d";
+
+    #[tokio::test]
+    async fn test_text_envelope_declares_the_language() {
+        let envelope = Path::new("tests/fixtures/vault.plutus");
+
+        let selected = load_program(envelope, None, &[]).await.unwrap();
+        assert_eq!(selected.language, Some(Language::PlutusV3));
+
+        let (cbor, language) = read_script_with_language(envelope).unwrap();
+        assert_eq!(hex::encode(cbor), COMPILED_CODE);
+        assert_eq!(language, Some(Language::PlutusV3));
+    }
+
+    #[tokio::test]
+    async fn test_content_is_sniffed_whatever_the_extension() {
+        let dir = TempDir::new("sniff");
+
+        let single = hex::decode(COMPILED_CODE).unwrap();
+        let double = [vec![0x58, single.len() as u8], single.clone()].concat();
+        let files = [
+            ("single.txt", format!("{}\n", COMPILED_CODE).into_bytes()),
+            ("double.hex", hex::encode(&double).into_bytes()),
+            ("double.bin", double),
+            ("flat.script", single[2..].to_vec()),
+            ("source", b"-- a comment\n(program 1.0.0 (con integer 1))".to_vec()),
+        ];
+
+        let expected = load_programs_from_file(Path::new("tests/fixtures/vault.plutus")).await.unwrap();
+        for (name, bytes) in &files[..4] {
+            let path = dir.write(name, bytes);
+
+            let programs = load_programs_from_file(&path).await.unwrap();
+            assert_eq!(programs[0].program, expected[0].program, "{}", name);
+            assert_eq!(hex::encode(read_script(&path).unwrap()), COMPILED_CODE, "{}", name);
+        }
+
+        let (name, bytes) = &files[4];
+        dir.write(name, bytes);
+        assert!(load_programs_from_file(&dir.join(name)).await.is_ok());
+
+        dir.write("junk", [0xff, 0x00, 0x13]);
+        let error = load_programs_from_file(&dir.join("junk")).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("is not UPLC, JSON, CBOR or flat"), "{:#}", error);
+    }
+}
+
+#[cfg(test)]
+mod stdin_tests {
+    use std::process::Stdio;
+
+    use crate::common::{cek_debugger, run_with_input};
+
+    const PROGRAM: &str = "crates/uplc/test_data/basic/integer/integer.uplc";
+    const SCRIPT: &[u8] = b"(program 1.0.0 (con integer 1))";
+
+    #[test]
+    fn test_stepper_stops_when_stdin_closes() {
+        let output = cek_debugger().arg(PROGRAM).stdin(Stdio::null()).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        // A prompt cut short ends the session too.
+        let output = run_with_input(cek_debugger().arg(PROGRAM), b"N\nJ\n");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    #[test]
+    fn test_script_from_stdin_is_not_stepped_interactively() {
+        // Refused before the script is read, so the pipe may already be closed.
+        let output = run_with_input(cek_debugger().arg("-"), SCRIPT);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--no-interactive"));
+
+        let output = run_with_input(cek_debugger().args(["-", "--no-interactive"]), SCRIPT);
+        assert!(output.status.success());
+    }
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use cek_debugger::{
+    executor::MachineConfig,
+    loader::LoadedProgram,
+    recording::Recording,
+};
+use uplc::{ast::{NamedDeBruijn, Program}, machine::cost_model::ExBudget, parser};
+
+use common::{cek_debugger, run_with_input, TempDir};
+
+const UN_I_DATA: &str = "(program 1.0.0 (lam d [(builtin unIData) d]))";
+
+fn record() -> Recording {
+    let program: Program<NamedDeBruijn> = parser::program(UN_I_DATA).unwrap().try_into().unwrap();
+    let program = LoadedProgram {
+        filename: "un_i_data.uplc".to_string(),
+        program,
+        source_map: Default::default(),
+    };
+
+    Recording::record(program, vec!["182a".to_string()], &MachineConfig::default()).unwrap()
+}
+
+#[test]
+fn test_recording_round_trips_and_verifies() {
+    let recording = record();
+    assert_eq!(recording.trace.result.as_deref(), Some("(con integer 42)"));
+    assert_eq!(recording.trace.steps.len(), recording.trace.total_steps);
+
+    let mut bytes = vec![];
+    recording.write(&mut bytes).unwrap();
+    assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+
+    let read = Recording::read(bytes.as_slice()).unwrap();
+    assert_eq!(read, recording);
+    read.verify().unwrap();
+}
+
+#[test]
+fn test_recording_detects_tampering_and_divergence() {
+    let mut tampered = record();
+    tampered.trace.steps[1].cpu += 1;
+    let mut bytes = vec![];
+    tampered.write(&mut bytes).unwrap();
+    assert!(Recording::read(bytes.as_slice()).unwrap_err().to_string().contains("corrupted"));
+
+    let mut diverging = record();
+    diverging.budget = ExBudget { mem: 1_000_000, cpu: 1_000_000_000 };
+    let error = diverging.verify().unwrap_err().to_string();
+    assert!(error.contains("diverged from the recording at step 0"), "{}", error);
+}
+
+#[test]
+fn test_record_and_replay_commands() {
+    let dir = TempDir::new("recording");
+    let script = dir.write("un_i_data.uplc", UN_I_DATA);
+    let trace = dir.join("un_i_data.trace.gz");
+
+    let recorded = cek_debugger()
+        .arg("record")
+        .arg(&script)
+        .arg("182a")
+        .arg("--output")
+        .arg(&trace)
+        .output()
+        .unwrap();
+    assert!(recorded.status.success(), "{}", String::from_utf8_lossy(&recorded.stderr));
+
+    let output = run_with_input(cek_debugger().arg("replay").arg(&trace).arg("--verify"), b"J\n4\nN\nQ\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("Re-execution reproduces the recorded trace"));
+    assert!(stdout.contains("Step 0004"));
+}
+
+#[test]
+fn test_replay_only_re_executes_when_live() {
+    // A recording whose program no longer decodes still replays from its
+    // snapshots, but cannot be reopened.
+    let mut recording = record();
+    recording.program = "not a program".to_string();
+    let dir = TempDir::new("replay");
+    let path = dir.join("un_i_data.trace.gz");
+    recording.write(std::fs::File::create(&path).unwrap()).unwrap();
+
+    let replay = |flags: &[&str]| run_with_input(cek_debugger().arg("replay").arg(&path).args(flags), b"J\n4\nV\nQ\n");
+
+    let snapshots = replay(&[]);
+    let stdout = String::from_utf8(snapshots.stdout).unwrap();
+    assert!(snapshots.status.success());
+    assert!(stdout.contains("Step 0004"));
+    assert!(stdout.contains("replay it with --live"));
+
+    let live = replay(&["--live"]);
+    assert!(!live.status.success());
+    assert!(String::from_utf8_lossy(&live.stderr).contains("not valid hex"));
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::{collections::BTreeMap, path::Path};
+
+use cek_debugger::{executor::{self, MachineConfig}, loader, FailureReport, ReportFormat};
+use pallas_primitives::conway::Language;
+
+use common::{cek_debugger, TempDir, MINT, TX, UTXOS};
+
+const CONFORMANCE: &str = "crates/uplc/test_data/conformance/v2";
+
+async fn report(fixture: &str) -> Option<FailureReport> {
+    let path = Path::new(CONFORMANCE).join(fixture);
+    let program = loader::load_programs_from_file(&path).await.unwrap().remove(0);
+    // The conformance programs use `case` and `constr`, which only V3 prices.
+    let config = MachineConfig::new(Language::PlutusV3);
+    let mut session = executor::start_session_with(program.program, &config).unwrap();
+
+    FailureReport::generate(&program.filename, &mut session, &BTreeMap::new())
+}
+
+fn render(report: &FailureReport, format: ReportFormat) -> String {
+    let mut output = vec![];
+    report.write(format, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[tokio::test]
+async fn builtin_failures_name_the_call() {
+    let report = report("example/DivideByZero/DivideByZero.uplc").await.unwrap();
+
+    assert!(report.error.starts_with("divide By Zero"));
+    assert_eq!(report.validator_intent, None);
+    assert!(report.failure_reason.starts_with("remainderInteger failed on 4, 0"), "{}", report.failure_reason);
+    assert!(report.how_to_fix[0].contains("divisor"));
+
+    let json: serde_json::Value = serde_json::from_str(&render(&report, ReportFormat::Json)).unwrap();
+    assert_eq!(json["technical_root_cause"]["failure"]["kind"], "builtin");
+    assert_eq!(json["technical_root_cause"]["failure"]["function"], "remainderInteger");
+    assert_eq!(json["steps"], report.steps);
+
+    let markdown = render(&report, ReportFormat::Markdown);
+    assert!(markdown.starts_with("## ❌ Validator failure"));
+    assert!(markdown.contains("### Technical root cause\n\n`step "));
+    assert!(!markdown.contains("### Validator intent"));
+}
+
+#[tokio::test]
+async fn error_terms_and_machine_errors_are_reported() {
+    let error = report("builtin/semantics/ifThenElse/ifThenElse-4/ifThenElse-4.uplc").await.unwrap();
+    assert_eq!(error.error, "the validator crashed / exited prematurely");
+    assert!(render(&error, ReportFormat::Text).contains("VALIDATOR FAILURE ANALYSIS"));
+
+    let case = report("term/case/case-5/case-5.uplc").await.unwrap();
+    assert_eq!(case.failure_reason, "attempted to case a non-const");
+
+    assert!(report("example/factorial/factorial.uplc").await.is_none());
+}
+
+#[test]
+fn transaction_reports_carry_the_script_context() {
+    // Accepts only redeemer constructor 1; the transaction's redeemer is Constr 0 [].
+    let dir = TempDir::new("report");
+    let script = dir.write(
+        "mint.uplc",
+        "(program 1.0.0 (lam r (lam ctx (force [(force (builtin ifThenElse)) [(builtin equalsInteger) [(force (force (builtin fstPair))) [(builtin unConstrData) r]] (con integer 1)] (delay (con unit ())) (delay (error))]))))",
+    );
+
+    let output = cek_debugger()
+        .args(["tx", TX, UTXOS, "--slot-config", "preview"])
+        .args(["--override", &format!("{}={}", MINT, script.display()), "--report", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["validator_intent"], "The validator requires 0 == 1.");
+    assert_eq!(report["technical_root_cause"]["check"]["function"], "equalsInteger");
+    assert_eq!(report["technical_root_cause"]["check"]["flow"], "direct");
+    assert_eq!(report["script_context"]["fee"], 342881);
+
+    let provided: Vec<String> = serde_json::from_value(report["transaction_provided"].clone()).unwrap();
+    assert!(provided.contains(&"Redeemer: Constr 0 []".to_string()), "{:?}", provided);
+    assert!(provided.iter().any(|line| line.starts_with("Output 0 to addr_test1")));
+}
+
+#[test]
+fn provenance_names_the_script_context_fields() {
+    // Accepts only if the first output holds less than 100 lovelace.
+    let fields = |d: &str| format!("[(force (force (builtin sndPair))) [(builtin unConstrData) {}]]", d);
+    let head = |l: &str| format!("[(force (builtin headList)) {}]", l);
+    let tail = |l: &str| format!("[(force (builtin tailList)) {}]", l);
+    let entry = |m: &str| format!("[(force (force (builtin sndPair))) {}]", head(&format!("[(builtin unMapData) {}]", m)));
+
+    let outputs = head(&tail(&tail(&fields(&head(&fields("ctx"))))));
+    let value = head(&tail(&fields(&head(&format!("[(builtin unListData) {}]", outputs)))));
+    let lovelace = format!("[(builtin unIData) {}]", entry(&entry(&value)));
+
+    let dir = TempDir::new("provenance");
+    let script = dir.write(
+        "mint.uplc",
+        format!(
+            "(program 1.0.0 (lam r (lam ctx (force [(force (builtin ifThenElse)) [(builtin lessThanInteger) {} (con integer 100)] (delay (con unit ())) (delay (error))]))))",
+            lovelace
+        ),
+    );
+
+    let output = cek_debugger()
+        .args(["tx", TX, UTXOS, "--slot-config", "preview"])
+        .args(["--override", &format!("{}={}", MINT, script.display()), "--report", "json", "--provenance"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report["validator_intent"],
+        "The validator requires ctx.tx_info.outputs[0].value.lovelace (999920) < 100."
+    );
+    assert_eq!(report["technical_root_cause"]["check"]["origins"][0], "ctx.tx_info.outputs[0].value.lovelace");
+    assert!(report["technical_root_cause"]["check"]["origins"][1].is_null());
+}
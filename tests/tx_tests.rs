@@ -0,0 +1,385 @@
+mod common;
+
+use std::path::Path;
+
+use cek_debugger::{executor, fit_ex_units, loader, transaction, utxos, MachineConfig, ScriptPrices, TxReport};
+use pallas_codec::utils::CborWrap;
+use pallas_primitives::conway::{DatumOption, Language, ScriptRef, TransactionOutput};
+use uplc::{
+    machine::{
+        cost_model::ExBudget,
+        debug::{DatumSummary, ValidityRange},
+    },
+    tx::{eval_phase_two, SlotConfig},
+};
+
+use common::{cek_debugger, run_with_input, TempDir, MINT, TX, UTXOS};
+
+fn preview() -> SlotConfig {
+    SlotConfig {
+        zero_time: 1660003200000,
+        zero_slot: 0,
+        slot_length: 1000,
+    }
+}
+
+#[test]
+fn prepared_redeemer_costs_what_phase_two_charges() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let tx = transaction::decode_tx(&tx_bytes).unwrap();
+    let utxos = transaction::decode_utxos(&transaction::read_cbor(Path::new(UTXOS)).unwrap()).unwrap();
+
+    let redeemer = transaction::find_redeemer(&tx, Some("mint:0".parse().unwrap())).unwrap();
+    assert_eq!(transaction::find_redeemer(&tx, None).unwrap(), redeemer);
+    assert!(transaction::find_redeemer(&tx, Some("spend:0".parse().unwrap())).is_err());
+
+    let prepared = transaction::prepare(&tx, &utxos, &redeemer, &preview()).unwrap();
+    let program = transaction::loaded_program(&prepared, "mint".to_string()).unwrap();
+
+    let config = MachineConfig::new(prepared.language.clone());
+    let mut session = executor::start_session_with(program.program, &config).unwrap();
+    session.run_to_end();
+    assert!(session.outcome().unwrap().is_ok());
+
+    let evaluated = eval_phase_two(&tx, &utxos, None, None, &preview(), false, |_| ()).unwrap();
+    let (expected, _) = &evaluated[0];
+
+    assert_eq!(
+        session.spent(),
+        ExBudget {
+            cpu: expected.ex_units.steps as i64,
+            mem: expected.ex_units.mem as i64,
+        }
+    );
+}
+
+/// The V2 cost model the mint fixture's ExUnits were computed with.
+const COST_MODELS: &str = "tests/fixtures/mint.costmodels.json";
+
+#[test]
+fn v2_script_costs_what_the_ledger_charges() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let tx = transaction::decode_tx(&tx_bytes).unwrap();
+    let utxos = transaction::decode_utxos(&transaction::read_cbor(Path::new(UTXOS)).unwrap()).unwrap();
+    let redeemer = transaction::find_redeemer(&tx, None).unwrap();
+    let prepared = transaction::prepare(&tx, &utxos, &redeemer, &preview()).unwrap();
+    assert_eq!(prepared.language, Language::PlutusV2);
+
+    let json = std::fs::read_to_string(COST_MODELS).unwrap();
+    let config = MachineConfig::new(Language::PlutusV2)
+        .with_cost_model(executor::parse_cost_model(&json, &Language::PlutusV2).unwrap())
+        .unwrap();
+    let program = transaction::loaded_program(&prepared, "mint".to_string()).unwrap();
+    let mut session = executor::start_session_with(program.program, &config).unwrap();
+    session.run_to_end();
+
+    let cost_models = executor::parse_cost_models(&json).unwrap().unwrap();
+    let evaluated = eval_phase_two(&tx, &utxos, Some(&cost_models), None, &preview(), false, |_| ()).unwrap();
+    let ledger = ExBudget {
+        cpu: evaluated[0].0.ex_units.steps as i64,
+        mem: evaluated[0].0.ex_units.mem as i64,
+    };
+
+    assert_eq!(session.spent(), ledger);
+    assert_eq!(
+        ledger,
+        ExBudget {
+            cpu: redeemer.ex_units.steps as i64,
+            mem: redeemer.ex_units.mem as i64,
+        }
+    );
+}
+
+#[test]
+fn script_context_summarizes_the_transaction() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let tx = transaction::decode_tx(&tx_bytes).unwrap();
+    let utxos = transaction::decode_utxos(&transaction::read_cbor(Path::new(UTXOS)).unwrap()).unwrap();
+    let redeemer = transaction::find_redeemer(&tx, None).unwrap();
+
+    let prepared = transaction::prepare(&tx, &utxos, &redeemer, &preview()).unwrap();
+    let context = transaction::script_context(&prepared);
+
+    assert_eq!(context.purpose, format!("mint {}", MINT));
+    assert_eq!(context.redeemer, "Constr 0 []");
+    assert_eq!(context.fee, 342881);
+    assert_eq!(context.validity_range, ValidityRange { lower_bound: None, upper_bound: None });
+
+    assert_eq!(context.inputs.len(), 1);
+    assert!(context.inputs[0].output_reference.ends_with("#2"));
+    assert!(context.inputs[0].address.starts_with("addr_test1"));
+    assert_eq!(context.inputs[0].value.lovelace, 10000000);
+    assert_eq!(context.inputs[0].datum, DatumSummary::None);
+
+    let minted = &context.outputs[0].value.assets;
+    assert_eq!(minted.len(), 1);
+    assert_eq!(minted[0].quantity, 10);
+    assert_eq!(minted[0].asset_name, "");
+}
+
+#[test]
+fn tx_command_runs_the_selected_redeemer() {
+    let output = cek_debugger()
+        .args([
+            "tx", TX, UTXOS,
+            "--redeemer", "mint:0",
+            "--slot-config", "1660003200000,0,1000",
+            "--format", "json",
+            "--steps", "none",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let trace: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(trace["program"], format!("{}#mint:0", TX));
+    assert!(trace["result"].is_string());
+
+    let output = cek_debugger()
+        .args(["tx", TX, UTXOS, "--format", "ndjson", "--steps", "0"])
+        .output()
+        .unwrap();
+    // The transaction is written once, in the header, not with every step.
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines[0]["script_context"]["fee"], 342881);
+    assert!(lines[1]["script_context"].is_null());
+
+    // Replaying the trace still shows the transaction at every step.
+    let dir = TempDir::new("tx-trace");
+    let trace = dir.write("trace.ndjson", &output.stdout);
+    let replay = run_with_input(cek_debugger().arg("replay").arg(&trace), b"Q\n");
+    assert!(String::from_utf8_lossy(&replay.stdout).contains("Fee: 342881 lovelace"));
+
+    let missing = cek_debugger()
+        .args(["tx", TX, UTXOS, "--redeemer", "spend:0", "--no-interactive"])
+        .output()
+        .unwrap();
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("it has: mint:0"));
+}
+
+#[test]
+fn eval_tx_reports_phase_one_and_every_redeemer() {
+    let output = cek_debugger()
+        .args(["eval-tx", TX, UTXOS, "--slot-config", "preview", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: TxReport = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report.passed());
+    assert_eq!(report.redeemers.len(), 1);
+
+    let mint = &report.redeemers[0];
+    assert_eq!(mint.redeemer, "mint:0");
+    assert_eq!(mint.script_hash.as_deref(), Some(MINT));
+    assert!(mint.actual.unwrap().cpu <= mint.declared.cpu);
+
+    let dir = TempDir::new("tx");
+    let empty = dir.write("empty.utxos", "a0");
+
+    let output = cek_debugger()
+        .args(["eval-tx", TX, empty.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table.contains("Phase one: ✗"), "{}", table);
+    assert!(table.contains("✗ fail"), "{}", table);
+
+    let output = cek_debugger()
+        .args(["eval-tx", TX, UTXOS, "--cpu-budget", "1000", "--format", "json"])
+        .output()
+        .unwrap();
+    let report: TxReport = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report.phase_one_error.is_none());
+    assert!(!report.redeemers[0].passed);
+    assert!(report.redeemers[0].error.as_ref().unwrap().contains("over budget"));
+}
+
+#[test]
+fn eval_tx_reports_what_the_ledger_evaluator_returns() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let tx = transaction::decode_tx(&tx_bytes).unwrap();
+    let utxos = transaction::decode_utxos(&transaction::read_cbor(Path::new(UTXOS)).unwrap()).unwrap();
+    let units = |evaluated: Vec<(pallas_primitives::conway::Redeemer, _)>| -> Vec<ExBudget> {
+        evaluated
+            .iter()
+            .map(|(redeemer, _)| ExBudget {
+                cpu: redeemer.ex_units.steps as i64,
+                mem: redeemer.ex_units.mem as i64,
+            })
+            .collect()
+    };
+
+    let report = TxReport::evaluate(&tx, &utxos, &preview(), |language| Ok(MachineConfig::new(language)));
+    let ledger = units(eval_phase_two(&tx, &utxos, None, None, &preview(), false, |_| ()).unwrap());
+    assert_eq!(report.redeemers.iter().map(|r| r.actual.unwrap()).collect::<Vec<_>>(), ledger);
+
+    let output = cek_debugger()
+        .args(["eval-tx", TX, UTXOS, "--slot-config", "preview", "--cost-model", COST_MODELS, "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: TxReport = serde_json::from_slice(&output.stdout).unwrap();
+
+    let cost_models = executor::parse_cost_models(&std::fs::read_to_string(COST_MODELS).unwrap()).unwrap();
+    let ledger = units(eval_phase_two(&tx, &utxos, cost_models.as_ref(), None, &preview(), false, |_| ()).unwrap());
+    assert_eq!(report.redeemers[0].actual, Some(ledger[0]));
+    assert_eq!(report.redeemers[0].actual, Some(report.redeemers[0].declared));
+    assert!(report.passed());
+}
+
+#[test]
+fn fitted_ex_units_are_what_the_scripts_spend_plus_the_margin() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let utxos = transaction::decode_utxos(&transaction::read_cbor(Path::new(UTXOS)).unwrap()).unwrap();
+
+    let fitted = fit_ex_units(&tx_bytes, &utxos, &preview(), None, &ExBudget::default(), 10).unwrap();
+    let mint = &fitted.redeemers[0];
+    assert_eq!(mint.redeemer, "mint:0");
+    assert_eq!(mint.fitted.cpu, mint.evaluated.cpu + (mint.evaluated.cpu * 10 + 99) / 100);
+
+    let original = transaction::decode_tx(&tx_bytes).unwrap();
+    let tx = transaction::decode_tx(&fitted.cbor).unwrap();
+    assert_eq!(tx.transaction_body.raw_cbor(), original.transaction_body.raw_cbor());
+
+    let redeemer = transaction::find_redeemer(&tx, None).unwrap();
+    assert_eq!(redeemer.ex_units.steps as i64, mint.fitted.cpu);
+    assert_eq!(redeemer.ex_units.mem as i64, mint.fitted.mem);
+    assert!(TxReport::evaluate(&tx, &utxos, &preview(), |l| Ok(MachineConfig::new(l))).passed());
+
+    let prices = ScriptPrices::from_protocol_params(r#"{"price_mem": 0.0577, "price_step": 0.0000721}"#).unwrap();
+    let total = fitted.total();
+    let fee = (total.mem as f64 * 0.0577 + total.cpu as f64 * 0.0000721).ceil() as u64;
+    assert_eq!(prices.fee(&total), fee);
+}
+
+#[test]
+fn fit_tx_writes_the_patched_transaction() {
+    let dir = TempDir::new("fit");
+    let params = dir.write("params.json", r#"{"executionUnitPrices": {"priceMemory": 0.0577, "priceSteps": 7.21e-5}}"#);
+    let output = dir.join("fitted.tx");
+
+    let result = cek_debugger()
+        .args(["fit-tx", TX, UTXOS, "--slot-config", "preview", "--margin", "5%", "--format", "json"])
+        .args(["--protocol-params", params.to_str().unwrap(), "--output", output.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let json: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    assert!(json["script_fee"].as_u64().unwrap() > 0);
+    assert_eq!(json["redeemers"][0]["redeemer"], "mint:0");
+
+    let written = transaction::read_cbor(&output).unwrap();
+    assert_eq!(hex::encode(&written), json["cbor"]);
+    assert!(transaction::decode_tx(&written).is_ok());
+}
+
+#[test]
+fn json_utxo_fixture_evaluates_like_its_cbor() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let tx = transaction::decode_tx(&tx_bytes).unwrap();
+
+    let cbor = transaction::read_utxos(Path::new(UTXOS)).unwrap();
+    let json = transaction::read_utxos(Path::new("tests/fixtures/mint.utxos.json")).unwrap();
+    assert_eq!(json.len(), cbor.len());
+
+    let units = |utxos| eval_phase_two(&tx, utxos, None, None, &preview(), true, |_| ()).unwrap()[0].0.ex_units;
+    assert_eq!(units(&json), units(&cbor));
+
+    let fixture = r#"{
+        "975c17a4fed0051be622328efa548e206657d2b65a19224bf6ff8132571e6a50#3": {
+            "address": "addr_test1vzmvs72wnfazvkv5gzjdpltee5rkgng4j9llzd5578m8ydgkp6edr",
+            "value": 2000000,
+            "inlineDatum": {"map": [{"k": {"bytes": "cafe"}, "v": {"int": "123456789012345678901234567890"}}]},
+            "referenceScript": {"file": "integer/integer.flat", "language": "v3"}
+        }
+    }"#;
+    let utxos = utxos::from_json(fixture, Path::new("crates/uplc/test_data/basic")).unwrap();
+    let TransactionOutput::PostAlonzo(output) = &utxos[0].output else {
+        panic!("fixtures build post-Alonzo outputs");
+    };
+
+    let Some(CborWrap(ScriptRef::PlutusV3Script(script))) = &output.script_ref else {
+        panic!("expected a PlutusV3 reference script, got {:?}", output.script_ref);
+    };
+    let uplc = loader::read_script(Path::new("crates/uplc/test_data/basic/integer/integer.uplc")).unwrap();
+    assert_eq!(script.0.to_vec(), uplc);
+    assert!(matches!(output.datum_option, Some(DatumOption::Data(_))));
+}
+
+#[test]
+fn overridden_scripts_run_in_place_of_the_transactions() {
+    let tx_bytes = transaction::read_cbor(Path::new(TX)).unwrap();
+    let tx = transaction::decode_tx(&tx_bytes).unwrap();
+    let utxos = transaction::read_utxos(Path::new(UTXOS)).unwrap();
+
+    let script = tx.transaction_witness_set.plutus_v2_script.as_ref().unwrap()[0].0.to_vec();
+    let flat: pallas_codec::minicbor::bytes::ByteVec = pallas_codec::minicbor::decode(&script).unwrap();
+
+    let dir = TempDir::new("override");
+    let same = dir.write("mint.flat", flat.as_slice());
+
+    let overrides = transaction::load_overrides(&tx, &utxos, &[format!("{}={}", MINT, same.display()).parse().unwrap()]).unwrap();
+    assert!(overrides[0].matches());
+
+    let other = "crates/uplc/test_data/basic/integer/integer.uplc";
+    let overrides = transaction::load_overrides(&tx, &utxos, &[format!("{}={}", MINT, other).parse().unwrap()]).unwrap();
+    assert!(!overrides[0].matches());
+
+    let redeemer = transaction::find_redeemer(&tx, None).unwrap();
+    let prepared = transaction::prepare_with_overrides(&tx, &utxos, &redeemer, &preview(), &transaction::override_map(&overrides)).unwrap();
+    assert_eq!(prepared.script_hash, overrides[0].replacement_hash);
+    assert_ne!(prepared.program, transaction::prepare(&tx, &utxos, &redeemer, &preview()).unwrap().program);
+
+    let unknown = format!("{}={}", "00".repeat(28), other).parse().unwrap();
+    let error = transaction::load_overrides(&tx, &utxos, &[unknown]).unwrap_err();
+    assert!(error.to_string().contains(MINT), "{}", error);
+
+    let output = cek_debugger()
+        .args(["eval-tx", TX, UTXOS, "--slot-config", "preview", "--format", "json"])
+        .args(["--override", &format!("{}={}", MINT, other)])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is overridden by"));
+
+    let report: TxReport = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report.phase_one_error.is_none());
+    assert!(!report.redeemers[0].passed);
+}
+
+#[test]
+fn fit_tx_fits_the_overriding_scripts() {
+    let dir = TempDir::new("fit-override");
+    let accept = dir.write("accept.uplc", "(program 1.0.0 (lam r (lam ctx (con unit ()))))");
+    let fit = |overrides: &[String]| {
+        let mut command = cek_debugger();
+        command.args(["fit-tx", TX, UTXOS, "--slot-config", "preview", "--format", "json", "--output"]);
+        command.arg(dir.join("fitted.tx"));
+        for o in overrides {
+            command.args(["--override", o]);
+        }
+        command.output().unwrap()
+    };
+
+    let original = fit(&[]);
+    assert!(original.status.success(), "{}", String::from_utf8_lossy(&original.stderr));
+    assert!(!String::from_utf8_lossy(&original.stderr).contains("is overridden by"));
+
+    let overridden = fit(&[format!("{}={}", MINT, accept.display())]);
+    assert!(overridden.status.success(), "{}", String::from_utf8_lossy(&overridden.stderr));
+    assert!(String::from_utf8_lossy(&overridden.stderr).contains("is overridden by"));
+
+    let evaluated = |output: &std::process::Output| {
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["redeemers"][0]["evaluated"]["cpu"].as_i64().unwrap()
+    };
+    assert!(evaluated(&overridden) < evaluated(&original));
+}